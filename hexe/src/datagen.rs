@@ -0,0 +1,305 @@
+//! Training data generated from self-play games.
+//!
+//! Games are played by [`Engine::self_play`] from openings of random legal
+//! moves, searching each move to a fixed depth or number of nodes. Positions
+//! where the player to move is not in check and the best move is quiet are
+//! recorded as [`Sample`]s with the search score and the result of the game,
+//! for training networks and tuning evaluation weights.
+//!
+//! # Formats
+//!
+//! Samples are stored in a binary format of [`PACKED_SIZE`] bytes each:
+//!
+//! | Bytes    | Contents                                                  |
+//! | -------- | --------------------------------------------------------- |
+//! | `0..8`   | Occupancy of each square from A1 to H8, little-endian     |
+//! | `8..24`  | A nibble per occupied square in order, low nibble first   |
+//! | `24`     | En passant square or 64 for none; bit 7 if Black to move  |
+//! | `25`     | Halfmove clock                                            |
+//! | `26..28` | Fullmove number, little-endian                            |
+//! | `28..30` | Score in centipawns for White, little-endian              |
+//! | `30`     | Result for White: 0 for a loss, 1 for a draw, 2 for a win |
+//! | `31`     | Unused                                                    |
+//!
+//! Each nibble holds the piece's role from 0 for a pawn to 5 for a king, or 6
+//! for a rook that can still castle, with bit 3 set for Black.
+//!
+//! The plain-text format has a line per sample of the FEN, score and result,
+//! separated by `|`, which [`tune::parse_entry`] reads:
+//!
+//! ```text
+//! rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2 | 31 | 0.5
+//! ```
+//!
+//! [`Engine::self_play`]: ../engine/struct.Engine.html#method.self_play
+//! [`Sample`]: struct.Sample.html
+//! [`PACKED_SIZE`]: constant.PACKED_SIZE.html
+//! [`tune::parse_entry`]: ../tune/fn.parse_entry.html
+
+use std::{cmp, fmt};
+use std::io::{self, Read};
+
+use core::board::{PieceMap, Variant};
+use core::castle::{Files, Side};
+use core::fen::Fen;
+use prelude::*;
+
+/// The number of bytes of a packed [`Sample`](struct.Sample.html).
+pub const PACKED_SIZE: usize = 32;
+
+/// The nibble of a rook that can still castle.
+const CASTLE_ROOK: u8 = 6;
+
+/// The en passant byte of a position without an en passant square.
+const NO_EN_PASSANT: u8 = 64;
+
+/// How self-play games are played.
+///
+/// Limits that are 0 do not apply, but moves must be searched to a depth or a
+/// number of nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The number of games to play.
+    pub games: u64,
+    /// The depth to which each move is searched.
+    pub depth: u32,
+    /// The number of nodes searched for each move.
+    pub nodes: u32,
+    /// The number of random legal moves that start each game.
+    pub random_plies: u32,
+    /// The number of plies after which a game is scored as a draw.
+    pub max_plies: u32,
+    /// The seed of the random openings. The opening of each game depends only
+    /// on the seed and the index of the game.
+    pub seed: u64,
+    /// The size in megabytes of the transposition table of each thread.
+    pub hash_size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            games: 1,
+            depth: 0,
+            nodes: 5000,
+            random_plies: 8,
+            max_plies: 400,
+            seed: 0,
+            hash_size: 16,
+        }
+    }
+}
+
+/// A position from a self-play game.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Sample {
+    /// The position.
+    pub fen: Fen,
+    /// The search score in centipawns from White's perspective.
+    pub score: i16,
+    /// The result of the game for White: 0 for a loss, 1 for a draw and 2 for
+    /// a win.
+    pub result: u8,
+}
+
+impl fmt::Display for Sample {
+    /// Formats `self` as a line of the plain-text format.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = match self.result {
+            2 => "1.0",
+            1 => "0.5",
+            _ => "0.0",
+        };
+        write!(f, "{} | {} | {}", self.fen, self.score, result)
+    }
+}
+
+impl Sample {
+    /// Returns the position of `self`, or `None` if it is invalid.
+    #[inline]
+    pub fn position(&self) -> Option<Position> {
+        let variant = if self.fen.castle_files.is_standard() {
+            Variant::Standard
+        } else {
+            Variant::Chess960
+        };
+        Position::from_fen_variant(&self.fen, variant)
+    }
+
+    /// Packs `self` into the binary format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position has more than 32 pieces.
+    pub fn pack(&self) -> [u8; PACKED_SIZE] {
+        let mut bytes = [0u8; PACKED_SIZE];
+        let mut occupancy = 0u64;
+        let mut count = 0;
+
+        for sq in Square::ALL {
+            let piece = match self.fen.pieces.get(sq) {
+                Some(&piece) => piece,
+                None => continue,
+            };
+            assert!(count < 32, "too many pieces to pack");
+
+            let castles = piece.role() == Role::Rook && self.fen.castling.into_iter().any(|right| {
+                right.color() == piece.color() && self.fen.castle_files.rook_square(right) == sq
+            });
+            let role = if castles { CASTLE_ROOK } else { piece.role() as u8 };
+            let nibble = role | (piece.color() as u8) << 3;
+
+            occupancy |= 1 << sq as u64;
+            bytes[8 + count / 2] |= nibble << (4 * (count % 2));
+            count += 1;
+        }
+        for (i, byte) in bytes[..8].iter_mut().enumerate() {
+            *byte = (occupancy >> (8 * i)) as u8;
+        }
+
+        let en_passant = self.fen.en_passant.map_or(NO_EN_PASSANT, |sq| sq as u8);
+        bytes[24] = en_passant | (self.fen.color as u8) << 7;
+        bytes[25] = cmp::min(self.fen.halfmoves, 255) as u8;
+        let fullmoves = cmp::min(self.fen.fullmoves, u16::max_value() as u32) as u16;
+        bytes[26] = fullmoves as u8;
+        bytes[27] = (fullmoves >> 8) as u8;
+        bytes[28] = self.score as u8;
+        bytes[29] = (self.score >> 8) as u8;
+        bytes[30] = self.result;
+        bytes
+    }
+
+    /// Unpacks a sample from the binary format, returning `None` if `bytes`
+    /// are invalid.
+    pub fn unpack(bytes: &[u8; PACKED_SIZE]) -> Option<Sample> {
+        let occupancy = bytes[..8].iter().rev().fold(0u64, |n, &b| n << 8 | b as u64);
+        if occupancy.count_ones() > 32 {
+            return None;
+        }
+
+        let mut pieces = PieceMap::new();
+        let mut castle_rooks = BitBoard::EMPTY;
+        for (count, sq) in Square::ALL.filter(|&sq| occupancy >> sq as u64 & 1 != 0).enumerate() {
+            let nibble = bytes[8 + count / 2] >> (4 * (count % 2)) & 0xF;
+            let color = Color::from(nibble >> 3);
+            let role = match nibble & 0x7 {
+                CASTLE_ROOK => {
+                    castle_rooks |= sq;
+                    Role::Rook
+                },
+                role if role <= Role::King as u8 => Role::from(role),
+                _ => return None,
+            };
+            pieces.insert(sq, Piece::new(role, color));
+        }
+
+        // Each castling rook is on the side of its king that it castles to,
+        // which also covers Chess960
+        let mut castling = Rights::EMPTY;
+        let mut castle_files = Files::STANDARD;
+        for sq in castle_rooks {
+            let color = pieces[sq].color();
+            if sq.rank() != Rank::first(color) {
+                return None;
+            }
+            let king_sq = pieces.find(Piece::new(Role::King, color))?;
+            if king_sq.rank() != sq.rank() {
+                return None;
+            }
+            let side = if sq.file() as u8 > king_sq.file() as u8 { Side::King } else { Side::Queen };
+            let right = Right::new(color, side);
+            castling |= right;
+            castle_files = castle_files.with_king(color, king_sq.file())
+                                       .with_rook(right, sq.file());
+        }
+
+        let en_passant = match bytes[24] & 0x7F {
+            NO_EN_PASSANT => None,
+            sq if sq < 64 => Some(Square::from(sq)),
+            _ => return None,
+        };
+        if bytes[30] > 2 {
+            return None;
+        }
+
+        Some(Sample {
+            fen: Fen {
+                pieces,
+                color: Color::from(bytes[24] >> 7),
+                castling,
+                castle_files,
+                en_passant,
+                halfmoves: bytes[25] as u32,
+                fullmoves: bytes[26] as u32 | (bytes[27] as u32) << 8,
+                checks: None,
+            },
+            score: (bytes[28] as u16 | (bytes[29] as u16) << 8) as i16,
+            result: bytes[30],
+        })
+    }
+
+    /// Reads the next packed sample from `reader`, returning `None` at the end
+    /// of its data.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Sample>> {
+        let mut bytes = [0u8; PACKED_SIZE];
+        let mut len = 0;
+        while len < PACKED_SIZE {
+            match reader.read(&mut bytes[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        }
+        match len {
+            0 => Ok(None),
+            PACKED_SIZE => match Sample::unpack(&bytes) {
+                Some(sample) => Ok(Some(sample)),
+                None => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid packed sample")),
+            },
+            _ => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated packed sample")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(fen: &str, score: i16, result: u8) -> Sample {
+        Sample { fen: fen.parse().unwrap(), score, result }
+    }
+
+    #[test]
+    fn pack() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 3 41",
+            "8/8/8/8/8/8/8/K6k b - - 99 300",
+        ];
+        for (i, fen) in fens.iter().enumerate() {
+            let sample = sample(fen, -250 + 100 * i as i16, i as u8 % 3);
+            let packed = sample.pack();
+            assert!(Sample::unpack(&packed) == Some(sample.clone()), "{}", fen);
+
+            let mut reader = &packed[..];
+            assert!(Sample::read_from(&mut reader).unwrap() == Some(sample));
+            assert!(Sample::read_from(&mut reader).unwrap().is_none());
+            assert!(Sample::read_from(&mut &packed[..20]).is_err());
+        }
+
+        // A black castling rook on H1 and an unknown role on A1
+        let mut packed = sample(fens[3], 0, 1).pack();
+        packed[8] = (CASTLE_ROOK | 8) << 4;
+        assert!(Sample::unpack(&packed).is_none());
+        packed[8] = 7;
+        assert!(Sample::unpack(&packed).is_none());
+    }
+
+    #[test]
+    fn text() {
+        let sample = sample("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", 112, 2);
+        assert_eq!(sample.to_string(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1 | 112 | 1.0");
+    }
+}
@@ -0,0 +1,288 @@
+//! Typed searches on an [`Engine`](../struct.Engine.html).
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::*;
+use core::color::Color;
+use core::mv::Move;
+use engine::thread::Job;
+use position::Position;
+
+/// The limits of a search started with
+/// [`Engine::search`](struct.Engine.html#method.search).
+///
+/// Limits that are 0 do not apply. A search without any limits runs until
+/// [`SearchHandle::stop`](struct.SearchHandle.html#method.stop) is called.
+///
+/// # Examples
+///
+/// ```
+/// use hexe::engine::SearchParams;
+///
+/// let params = SearchParams { depth: 8, multi_pv: 3, ..Default::default() };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchParams {
+    /// The maximum depth in plies.
+    pub depth: u32,
+    /// The maximum number of nodes.
+    pub nodes: u32,
+    /// Stops once a mate in this many moves is found.
+    pub mate: u32,
+    /// The exact number of milliseconds to search for.
+    pub move_time: u32,
+    /// The milliseconds left on each player's clock, indexed by `Color`.
+    pub time: [u32; 2],
+    /// The milliseconds added to each player's clock per move, indexed by
+    /// `Color`.
+    pub inc: [u32; 2],
+    /// The number of moves until the next time control.
+    pub moves_to_go: u32,
+    /// Whether to search until stopped, regardless of other limits.
+    pub infinite: bool,
+    /// Whether to search the position after the expected reply while the
+    /// opponent thinks. The clock starts upon
+    /// [`SearchHandle::ponder_hit`](struct.SearchHandle.html#method.ponder_hit).
+    pub ponder: bool,
+    /// The number of lines to report, or 0 for the engine's setting.
+    pub multi_pv: u32,
+    /// The moves to consider at the root, or all legal moves if empty.
+    pub moves: Vec<Move>,
+    /// Whether to collect the statistics returned by
+    /// [`SearchHandle::wait_with_stats`](struct.SearchHandle.html#method.wait_with_stats).
+    pub stats: bool,
+}
+
+impl SearchParams {
+    /// Sets the clock limits of `color`.
+    #[inline]
+    pub fn clock(&mut self, color: Color, time: u32, inc: u32) -> &mut SearchParams {
+        self.time[color as usize] = time;
+        self.inc[color as usize] = inc;
+        self
+    }
+
+    fn limits(&self, engine: &Engine) -> Limits {
+        Limits {
+            ponder: self.ponder,
+            infinite: self.infinite,
+            moves_to_go: self.moves_to_go,
+            time: self.time,
+            inc: self.inc,
+            depth: self.depth,
+            nodes: self.nodes,
+            mate: self.mate,
+            move_time: self.move_time,
+            move_overhead: engine.move_overhead(),
+            multi_pv: match self.multi_pv {
+                0 => engine.multi_pv(),
+                n => n,
+            },
+            checks_only: engine.checks_only_mate(),
+        }
+    }
+}
+
+/// Where a search started through the API reports to.
+pub(crate) struct Listener {
+    pub info: Sender<SearchInfo>,
+    pub best: Sender<Option<BestMove>>,
+    pub stats: Option<Sender<SearchStats>>,
+}
+
+/// A search running on an [`Engine`](struct.Engine.html)'s thread pool.
+///
+/// Dropping the handle does not stop the search.
+pub struct SearchHandle<'a> {
+    engine: &'a Engine,
+    info: Receiver<SearchInfo>,
+    best: Receiver<Option<BestMove>>,
+    stats: Receiver<SearchStats>,
+}
+
+impl<'a> SearchHandle<'a> {
+    /// Returns the receiver of the lines of each completed iteration.
+    ///
+    /// The channel disconnects once the search has finished.
+    #[inline]
+    pub fn info(&self) -> &Receiver<SearchInfo> {
+        &self.info
+    }
+
+    /// Has the search finish as soon as possible.
+    ///
+    /// The best move found so far is still returned by
+    /// [`wait`](#method.wait).
+    #[inline]
+    pub fn stop(&self) {
+        self.engine.halt_all();
+    }
+
+    /// Starts the clock of a pondering search.
+    #[inline]
+    pub fn ponder_hit(&self) {
+        self.engine.ponder_hit();
+    }
+
+    /// Blocks until the search finishes, returning the best move or `None` if
+    /// there are no legal moves.
+    pub fn wait(self) -> Option<BestMove> {
+        self.best.recv().ok().and_then(|best| best)
+    }
+
+    /// Blocks until the search finishes, returning the best move along with
+    /// the statistics of each completed iteration if
+    /// [`SearchParams::stats`](struct.SearchParams.html#structfield.stats) was
+    /// set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::engine::{Engine, SearchParams};
+    /// use hexe::position::Position;
+    ///
+    /// let engine = Engine::builder().num_threads(1).build();
+    /// let params = SearchParams { depth: 4, stats: true, ..Default::default() };
+    ///
+    /// let (_, stats) = engine.search(&Position::default(), params).wait_with_stats();
+    /// println!("{}", stats.unwrap().to_json());
+    /// ```
+    pub fn wait_with_stats(self) -> (Option<BestMove>, Option<SearchStats>) {
+        let best = self.best.recv().ok().and_then(|best| best);
+        // The statistics are sent before the best move
+        (best, self.stats.try_recv().ok())
+    }
+}
+
+impl Engine {
+    /// Starts a search of `position` within `params`, finishing any running
+    /// searches first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::engine::{Engine, SearchParams};
+    /// use hexe::position::Position;
+    ///
+    /// let engine = Engine::builder().num_threads(1).build();
+    /// let params = SearchParams { depth: 4, ..Default::default() };
+    ///
+    /// let handle = engine.search(&Position::default(), params);
+    /// for info in handle.info() {
+    ///     println!("{}: {:?}", info.depth, info.score);
+    /// }
+    /// let best = handle.wait().unwrap();
+    /// println!("best move: {}", best.mv);
+    /// ```
+    pub fn search(&self, position: &Position, params: SearchParams) -> SearchHandle<'_> {
+        // Only one search may report at a time
+        self.halt_all();
+        self.wait_idle();
+
+        let (info_tx, info) = mpsc::channel();
+        let (best_tx, best) = mpsc::channel();
+        let (stats_tx, stats) = mpsc::channel();
+
+        self.pool.enqueue(Job::Search {
+            limits: params.limits(self),
+            moves: params.moves.into(),
+            position: position.clone(),
+            listener: Some(Listener {
+                info: info_tx,
+                best: best_tx,
+                stats: if params.stats { Some(stats_tx) } else { None },
+            }),
+        });
+
+        SearchHandle { engine: self, info, best, stats }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fen::Fen;
+
+    fn engine() -> Engine {
+        Engine::builder().num_threads(1).build()
+    }
+
+    #[test]
+    fn info_and_best_move() {
+        let engine = engine();
+        let params = SearchParams { depth: 3, ..Default::default() };
+        let handle = engine.search(&Position::default(), params);
+
+        let infos: Vec<SearchInfo> = handle.info().iter().collect();
+        let depths: Vec<u32> = infos.iter().map(|info| info.depth).collect();
+        assert_eq!(depths, [1, 2, 3]);
+
+        let best = handle.wait().unwrap();
+        assert_eq!(infos[2].pv[0], best.mv);
+    }
+
+    #[test]
+    fn mate_score() {
+        let fen: Fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".parse().unwrap();
+        let position = Position::from_fen(&fen).unwrap();
+
+        let engine = engine();
+        let params = SearchParams { depth: 2, multi_pv: 2, ..Default::default() };
+        let handle = engine.search(&position, params);
+
+        let infos: Vec<SearchInfo> = handle.info().iter().filter(|info| {
+            info.depth == 2
+        }).collect();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].score, Score::Mate(1));
+        assert_eq!(infos[1].multi_pv, 2);
+        assert_eq!(handle.wait().unwrap().mv.to_string(), "a1a8");
+    }
+
+    #[test]
+    fn stop() {
+        let engine = engine();
+        let params = SearchParams { infinite: true, ..Default::default() };
+        let handle = engine.search(&Position::default(), params);
+
+        handle.info().recv().unwrap();
+        handle.stop();
+        assert!(handle.wait().is_some());
+    }
+
+    #[test]
+    fn stop_all() {
+        let mut engine = engine();
+        engine.stop_all();
+
+        // The search is discarded without running, whether or not it was
+        // stolen before being drained
+        let params = SearchParams { depth: 3, ..Default::default() };
+        let handle = engine.search(&Position::default(), params);
+        engine.stop_all();
+        assert!(handle.wait().is_none());
+
+        engine.resume_all();
+        engine.clear_hash();
+        let params = SearchParams { depth: 1, ..Default::default() };
+        assert!(engine.search(&Position::default(), params).wait().is_some());
+    }
+
+    #[test]
+    fn stats() {
+        let engine = engine();
+        let params = SearchParams { depth: 3, stats: true, ..Default::default() };
+        let handle = engine.search(&Position::default(), params);
+
+        let nodes = handle.info().iter().last().unwrap().nodes;
+        let (best, stats) = handle.wait_with_stats();
+        assert!(best.is_some());
+        let stats = stats.unwrap();
+        assert_eq!(stats.depths.len(), 3);
+        assert_eq!(stats.nodes(), nodes);
+
+        let params = SearchParams { depth: 3, ..Default::default() };
+        let (_, stats) = engine.search(&Position::default(), params).wait_with_stats();
+        assert!(stats.is_none());
+    }
+}
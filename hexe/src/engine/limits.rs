@@ -1,3 +1,4 @@
+#[derive(Copy, Clone)]
 pub struct Limits {
     pub ponder: bool,
     pub infinite: bool,
@@ -8,4 +9,7 @@ pub struct Limits {
     pub nodes: u32,
     pub mate: u32,
     pub move_time: u32,
+    pub move_overhead: u32,
+    pub multi_pv: u32,
+    pub checks_only: bool,
 }
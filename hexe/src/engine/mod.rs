@@ -3,16 +3,41 @@
 // TODO lint when everything is implemented
 #![allow(unused_variables)]
 
-use std::usize;
+use std::{cmp, usize};
+use std::sync::Arc;
+
+use core::board::Variant;
+use nnue::Network;
+use syzygy::Tablebase;
+
+pub mod bench;
+
+mod handle;
+pub use self::handle::{SearchHandle, SearchParams};
 
 mod limits;
 pub(crate) use self::limits::Limits;
 
+mod output;
+pub use self::output::Output;
+
+pub mod option;
+
+mod search;
+pub use self::search::{BestMove, DepthStats, Score, SearchInfo, SearchStats};
+
+mod self_play;
+
+mod time;
+
 mod thread;
 use self::thread::Pool;
 
 mod uci;
-pub use self::uci::Uci;
+pub use self::uci::{Uci, UciDriver};
+
+mod xboard;
+pub use self::xboard::XBoard;
 
 /// The maximum number of threads that may be running in an
 /// [`Engine`](struct.Engine.html)'s thread pool.
@@ -22,6 +47,18 @@ pub const MAX_THREADS: usize = 512;
 /// [`Engine::set_hash_size`](struct.Engine.html#method.set_hash_size).
 pub const MAX_TABLE_SIZE: usize = 131072;
 
+/// The default number of milliseconds reserved per move for communication
+/// delays.
+pub const DEFAULT_MOVE_OVERHEAD: u32 = 10;
+
+/// The maximum value that may be passed to
+/// [`Engine::set_move_overhead`](struct.Engine.html#method.set_move_overhead).
+pub const MAX_MOVE_OVERHEAD: u32 = 5000;
+
+/// The maximum value that may be passed to
+/// [`Engine::set_multi_pv`](struct.Engine.html#method.set_multi_pv).
+pub const MAX_MULTI_PV: u32 = 256;
+
 /// An instance of the Hexe chess engine.
 ///
 /// # Thread Pool Management
@@ -38,6 +75,9 @@ pub const MAX_TABLE_SIZE: usize = 131072;
 /// - **Resume** - Continues all **stop**ped threads, having them each pick up a
 ///   new job to perform, if any.
 ///
+/// - **Halt** - Has all running searches report their best move and finish.
+///   Unlike **stop**, worker threads continue to pick up new jobs.
+///
 /// # Examples
 ///
 /// Basic usage:
@@ -53,6 +93,10 @@ pub const MAX_TABLE_SIZE: usize = 131072;
 /// ```
 pub struct Engine {
     pool: Pool,
+    move_overhead: u32,
+    multi_pv: u32,
+    checks_only_mate: bool,
+    variant: Variant,
 }
 
 impl Default for Engine {
@@ -67,6 +111,10 @@ impl Engine {
         EngineBuilder(Options {
             num_threads: 0,
             hash_size: 0,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            multi_pv: 1,
+            checks_only_mate: false,
+            variant: Variant::Standard,
         })
     }
 
@@ -82,6 +130,10 @@ impl Engine {
         Options {
             num_threads: self.num_threads(),
             hash_size: self.pool.shared().table.size_mb(),
+            move_overhead: self.move_overhead,
+            multi_pv: self.multi_pv,
+            checks_only_mate: self.checks_only_mate,
+            variant: self.variant,
         }
     }
 
@@ -91,6 +143,13 @@ impl Engine {
         Uci::from(self)
     }
 
+    /// Creates a Chess Engine Communication Protocol interface for this
+    /// engine.
+    #[inline]
+    pub fn xboard(&mut self) -> XBoard {
+        XBoard::from(self)
+    }
+
     /// Ceases execution of all current jobs.
     pub fn stop_all(&self) {
         self.pool.stop_all();
@@ -101,6 +160,45 @@ impl Engine {
         self.pool.resume_all();
     }
 
+    /// Has all running searches finish as soon as possible.
+    pub fn halt_all(&self) {
+        self.pool.halt_all();
+    }
+
+    /// Switches a pondering search to normal, timed search after the
+    /// expected move was played, keeping the work done so far.
+    pub fn ponder_hit(&self) {
+        self.pool.ponder_hit();
+    }
+
+    /// Returns where the engine writes its responses.
+    #[inline]
+    pub fn output(&self) -> Output {
+        self.pool.shared().output.read().clone()
+    }
+
+    /// Sets where the engine writes its responses, which is the standard
+    /// output by default.
+    ///
+    /// Searches that are already running keep writing to the previous output.
+    pub fn set_output(&self, output: Output) {
+        *self.pool.shared().output.write() = output;
+    }
+
+    /// Sets where searches started through [`uci`](#method.uci),
+    /// [`xboard`](#method.xboard) and [`bench`](#method.bench) write their
+    /// statistics as a line of JSON, or `None` to not collect them.
+    ///
+    /// Searches that are already running keep their previous setting.
+    pub fn set_stats_output(&self, output: Option<Output>) {
+        *self.pool.shared().stats.write() = output;
+    }
+
+    /// Blocks the current thread until all enqueued jobs have finished.
+    pub fn wait_idle(&self) {
+        self.pool.wait_idle();
+    }
+
     /// Attempts to kill `thread`, returning whether or not it is in the pool.
     pub fn kill(&self, thread: usize) -> bool {
         self.pool.kill(thread)
@@ -133,6 +231,127 @@ impl Engine {
         self.pool.shared().table.size_mb()
     }
 
+    /// Returns the number of milliseconds reserved per move for communication
+    /// delays.
+    #[inline]
+    pub fn move_overhead(&self) -> u32 {
+        self.move_overhead
+    }
+
+    /// Sets the number of milliseconds reserved per move for communication
+    /// delays, returning `false` if `ms` is greater than
+    /// `engine::MAX_MOVE_OVERHEAD`.
+    pub fn set_move_overhead(&mut self, ms: u32) -> bool {
+        if ms > MAX_MOVE_OVERHEAD { false } else {
+            self.move_overhead = ms;
+            true
+        }
+    }
+
+    /// Returns the number of best lines reported by searches.
+    #[inline]
+    pub fn multi_pv(&self) -> u32 {
+        self.multi_pv
+    }
+
+    /// Sets the number of best lines reported by searches, returning `false`
+    /// if `n` is 0 or greater than `engine::MAX_MULTI_PV`.
+    pub fn set_multi_pv(&mut self, n: u32) -> bool {
+        match n {
+            1...MAX_MULTI_PV => {
+                self.multi_pv = n;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns whether searches for a mate only consider checking moves for
+    /// the attacking side.
+    #[inline]
+    pub fn checks_only_mate(&self) -> bool {
+        self.checks_only_mate
+    }
+
+    /// Sets whether searches for a mate only consider checking moves for the
+    /// attacking side.
+    #[inline]
+    pub fn set_checks_only_mate(&mut self, checks_only: bool) {
+        self.checks_only_mate = checks_only;
+    }
+
+    /// Returns the variant of the positions set up by the engine's interfaces.
+    #[inline]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Sets the variant of the positions set up by the engine's interfaces.
+    #[inline]
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Returns the endgame tablebases probed by searches.
+    #[inline]
+    pub fn tablebase(&self) -> Arc<Tablebase> {
+        self.pool.shared().tablebase.read().clone()
+    }
+
+    /// Loads the Syzygy tablebases within `paths`, a list of directories
+    /// separated as in the `PATH` environment variable, returning `false` if
+    /// a directory cannot be read.
+    ///
+    /// An empty path unloads all tablebases. Searches that are already
+    /// running keep probing the previous tablebases.
+    pub fn set_syzygy_path(&mut self, paths: &str) -> bool {
+        match Tablebase::open(paths) {
+            Ok(tablebase) => {
+                debug!("Found {} tablebases in \"{}\"", tablebase.len(), paths);
+                *self.pool.shared().tablebase.write() = Arc::new(tablebase);
+                true
+            },
+            Err(err) => {
+                error!("Cannot read tablebases in \"{}\": {}", paths, err);
+                false
+            },
+        }
+    }
+
+    /// Returns the network evaluating searched positions, if any.
+    #[inline]
+    pub fn network(&self) -> Option<Arc<Network>> {
+        self.pool.shared().network.read().clone()
+    }
+
+    /// Loads the network in the file at `path` to evaluate searched positions,
+    /// returning `false` if it cannot be read.
+    ///
+    /// An empty path unloads the network, falling back to the classical
+    /// evaluation.
+    pub fn set_eval_file(&mut self, path: &str) -> bool {
+        if path.is_empty() {
+            *self.pool.shared().network.write() = None;
+            return true;
+        }
+        match Network::open(path) {
+            Ok(network) => {
+                debug!("Loaded {:?} from \"{}\"", network, path);
+                *self.pool.shared().network.write() = Some(Arc::new(network));
+                true
+            },
+            Err(err) => {
+                error!("Cannot load network \"{}\": {}", path, err);
+                false
+            },
+        }
+    }
+
+    /// Clears the engine's hash table after all enqueued jobs have finished.
+    pub fn clear_hash(&mut self) {
+        self.pool.clear_table();
+    }
+
     /// Sets the engine's hash table size to `size` [MiB], returning `false` if
     /// the value is not within the inclusive range of 1 through 131072.
     ///
@@ -141,10 +360,7 @@ impl Engine {
     /// [MiB]: https://en.wikipedia.org/wiki/Mebibyte
     pub fn set_hash_size(&mut self, size: usize) -> bool {
         match size {
-            1...MAX_TABLE_SIZE => {
-                warn!("Cannot currently set table size");
-                true
-            },
+            1...MAX_TABLE_SIZE => self.pool.resize_table(size),
             _ => false,
         }
     }
@@ -172,7 +388,13 @@ impl EngineBuilder {
             0 => 1,
             n => n,
         };
-        Engine { pool: Pool::new(num_threads, hash_size) }
+        Engine {
+            pool: Pool::new(num_threads, hash_size),
+            move_overhead: self.0.move_overhead,
+            multi_pv: cmp::max(self.0.multi_pv, 1),
+            checks_only_mate: self.0.checks_only_mate,
+            variant: self.0.variant,
+        }
     }
 
     /// Set the number of threads to be used by the engine.
@@ -199,6 +421,46 @@ impl EngineBuilder {
         self.0.hash_size = size;
         self
     }
+
+    /// The number of milliseconds reserved per move for communication delays
+    /// with the GUI.
+    ///
+    /// The default is 10.
+    #[inline]
+    pub fn move_overhead(&mut self, ms: u32) -> &mut EngineBuilder {
+        self.0.move_overhead = ms;
+        self
+    }
+
+    /// The number of best lines reported by searches, each with its own score
+    /// and principal variation.
+    ///
+    /// The default is 1.
+    #[inline]
+    pub fn multi_pv(&mut self, n: u32) -> &mut EngineBuilder {
+        self.0.multi_pv = n;
+        self
+    }
+
+    /// Whether searches for a mate only consider checking moves for the
+    /// attacking side. This finds long mates in problems quickly, but misses
+    /// mates that start with a quiet move.
+    ///
+    /// The default is `false`.
+    #[inline]
+    pub fn checks_only_mate(&mut self, checks_only: bool) -> &mut EngineBuilder {
+        self.0.checks_only_mate = checks_only;
+        self
+    }
+
+    /// The variant of the positions set up by the engine's interfaces.
+    ///
+    /// The default is `Variant::Standard`.
+    #[inline]
+    pub fn variant(&mut self, variant: Variant) -> &mut EngineBuilder {
+        self.0.variant = variant;
+        self
+    }
 }
 
 /// Chess engine options.
@@ -209,4 +471,14 @@ pub struct Options {
     /// The number of [MiB](https://en.wikipedia.org/wiki/Mebibyte) for the
     /// engine's transposition table.
     pub hash_size: usize,
+    /// The number of milliseconds subtracted from the clock per move to
+    /// account for communication delays.
+    pub move_overhead: u32,
+    /// The number of best lines reported by searches.
+    pub multi_pv: u32,
+    /// Whether searches for a mate only consider checking moves for the
+    /// attacking side.
+    pub checks_only_mate: bool,
+    /// The variant of the positions set up by the engine's interfaces.
+    pub variant: Variant,
 }
@@ -0,0 +1,481 @@
+//! Typed engine options as exposed through the UCI.
+//!
+//! Each option declares its type, default value and constraints along with a
+//! callback that applies a validated value to an [`Engine`]. Both the options
+//! listed in reply to "uci" and the validation of "setoption" are generated
+//! from a [`Registry`].
+//!
+//! [`Engine`]: ../struct.Engine.html
+//! [`Registry`]: struct.Registry.html
+
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::slice;
+
+use super::*;
+
+/// The type of an option along with its default value, constraints and the
+/// callback that applies a new value.
+#[derive(Copy, Clone, Debug)]
+pub enum Kind {
+    /// An integer within an inclusive range.
+    Spin {
+        /// The value the option starts with.
+        default: i64,
+        /// The smallest allowed value.
+        min: i64,
+        /// The greatest allowed value.
+        max: i64,
+        /// Applies the value, returning whether the engine accepted it.
+        set: fn(&mut Engine, i64) -> bool,
+    },
+    /// Either `true` or `false`.
+    Check {
+        /// The value the option starts with.
+        default: bool,
+        /// Applies the value, returning whether the engine accepted it.
+        set: fn(&mut Engine, bool) -> bool,
+    },
+    /// One of a predefined set of strings.
+    Combo {
+        /// The value the option starts with.
+        default: &'static str,
+        /// The allowed values.
+        vars: &'static [&'static str],
+        /// Applies the value, returning whether the engine accepted it.
+        set: fn(&mut Engine, &str) -> bool,
+    },
+    /// Any string.
+    String {
+        /// The value the option starts with.
+        default: &'static str,
+        /// Applies the value, returning whether the engine accepted it.
+        set: fn(&mut Engine, &str) -> bool,
+    },
+    /// An action without a value.
+    Button {
+        /// Performs the action.
+        press: fn(&mut Engine),
+    },
+}
+
+/// A named engine option.
+#[derive(Copy, Clone, Debug)]
+pub struct UciOption {
+    /// The name by which the option is set, matched case-insensitively.
+    pub name: &'static str,
+    /// The type of the option.
+    pub kind: Kind,
+}
+
+/// The string used by the UCI to represent an empty string value.
+const EMPTY: &str = "<empty>";
+
+impl fmt::Display for UciOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match self.kind {
+            Kind::Spin { default, min, max, .. } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            },
+            Kind::Check { default, .. } => {
+                write!(f, "check default {}", default)
+            },
+            Kind::Combo { default, vars, .. } => {
+                write!(f, "combo default {}", default)?;
+                for var in vars {
+                    write!(f, " var {}", var)?;
+                }
+                Ok(())
+            },
+            Kind::String { default, .. } => {
+                let default = if default.is_empty() { EMPTY } else { default };
+                write!(f, "string default {}", default)
+            },
+            Kind::Button { .. } => f.write_str("button"),
+        }
+    }
+}
+
+impl UciOption {
+    /// Returns whether `name` refers to `self`.
+    #[inline]
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name.trim())
+    }
+
+    /// Validates `value` against the type of `self` and applies it to
+    /// `engine`.
+    pub fn set(&self, engine: &mut Engine, value: &str) -> Result<(), Error> {
+        let value = value.trim();
+        let accepted = match self.kind {
+            Kind::Spin { min, max, set, .. } => {
+                let n: i64 = value.parse().map_err(|_| Error::Invalid)?;
+                if n < min || n > max {
+                    return Err(Error::OutOfRange);
+                }
+                set(engine, n)
+            },
+            Kind::Check { set, .. } => {
+                if value.eq_ignore_ascii_case("true") {
+                    set(engine, true)
+                } else if value.eq_ignore_ascii_case("false") {
+                    set(engine, false)
+                } else {
+                    return Err(Error::Invalid);
+                }
+            },
+            Kind::Combo { vars, set, .. } => {
+                match vars.iter().find(|var| var.eq_ignore_ascii_case(value)) {
+                    Some(var) => set(engine, var),
+                    None => return Err(Error::Invalid),
+                }
+            },
+            Kind::String { set, .. } => {
+                set(engine, if value == EMPTY { "" } else { value })
+            },
+            Kind::Button { press } => {
+                press(engine);
+                true
+            },
+        };
+        if accepted { Ok(()) } else { Err(Error::Rejected) }
+    }
+}
+
+/// An error returned when setting an option fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// No option has the given name.
+    Unknown,
+    /// The value is not of the option's type.
+    Invalid,
+    /// The value is outside of the option's range.
+    OutOfRange,
+    /// The engine did not accept the value.
+    Rejected,
+}
+
+impl Error {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Error::Unknown    => "no such option",
+            Error::Invalid    => "invalid value for option type",
+            Error::OutOfRange => "value out of range",
+            Error::Rejected   => "value rejected by engine",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// A set of engine options.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    options: Vec<UciOption>,
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        let threads = cmp::min(::num_cpus::get(), MAX_THREADS);
+        Registry::from(vec![
+            UciOption {
+                name: "Threads",
+                kind: Kind::Spin {
+                    default: threads as i64,
+                    min: 1,
+                    max: MAX_THREADS as i64,
+                    set: |engine, n| engine.set_threads(n as usize),
+                },
+            },
+            UciOption {
+                name: "Hash",
+                kind: Kind::Spin {
+                    default: 1,
+                    min: 1,
+                    max: MAX_TABLE_SIZE as i64,
+                    set: |engine, n| engine.set_hash_size(n as usize),
+                },
+            },
+            UciOption {
+                name: "Clear Hash",
+                kind: Kind::Button {
+                    press: |engine| engine.clear_hash(),
+                },
+            },
+            UciOption {
+                name: "Move Overhead",
+                kind: Kind::Spin {
+                    default: DEFAULT_MOVE_OVERHEAD as i64,
+                    min: 0,
+                    max: MAX_MOVE_OVERHEAD as i64,
+                    set: |engine, n| engine.set_move_overhead(n as u32),
+                },
+            },
+            UciOption {
+                name: "Ponder",
+                kind: Kind::Check {
+                    default: false,
+                    // The GUI decides when to ponder via "go ponder"
+                    set: |_, _| true,
+                },
+            },
+            UciOption {
+                name: "MultiPV",
+                kind: Kind::Spin {
+                    default: 1,
+                    min: 1,
+                    max: MAX_MULTI_PV as i64,
+                    set: |engine, n| engine.set_multi_pv(n as u32),
+                },
+            },
+            UciOption {
+                name: "Checks Only Mate",
+                kind: Kind::Check {
+                    default: false,
+                    set: |engine, checks_only| {
+                        engine.set_checks_only_mate(checks_only);
+                        true
+                    },
+                },
+            },
+            UciOption {
+                name: "UCI_Chess960",
+                kind: Kind::Check {
+                    default: false,
+                    set: |engine, chess960| {
+                        if chess960 {
+                            engine.set_variant(Variant::Chess960);
+                        } else if engine.variant() == Variant::Chess960 {
+                            engine.set_variant(Variant::Standard);
+                        }
+                        true
+                    },
+                },
+            },
+            UciOption {
+                name: "UCI_Variant",
+                kind: Kind::Combo {
+                    default: "chess",
+                    vars: &["chess", "3check", "kingofthehill"],
+                    set: |engine, name| {
+                        let variant = match name {
+                            "3check" => Variant::ThreeCheck,
+                            "kingofthehill" => Variant::KingOfTheHill,
+                            // Chess960 is chess with UCI_Chess960 set
+                            _ if engine.variant() == Variant::Chess960 => Variant::Chess960,
+                            _ => Variant::Standard,
+                        };
+                        engine.set_variant(variant);
+                        true
+                    },
+                },
+            },
+            UciOption {
+                name: "SyzygyPath",
+                kind: Kind::String {
+                    default: "",
+                    set: |engine, paths| {
+                        if !engine.set_syzygy_path(paths) {
+                            return false;
+                        }
+                        let found = engine.tablebase().len();
+                        writeln!(engine.output(), "info string Found {} tablebases", found);
+                        true
+                    },
+                },
+            },
+            UciOption {
+                name: "EvalFile",
+                kind: Kind::String {
+                    default: "",
+                    set: |engine, path| engine.set_eval_file(path),
+                },
+            },
+        ])
+    }
+}
+
+impl<'a> From<&'a Engine> for Registry {
+    /// Creates the default options with their defaults taken from the current
+    /// configuration of `engine`, such as its number of threads.
+    fn from(engine: &Engine) -> Registry {
+        let mut registry = Registry::default();
+        for option in registry.options.iter_mut() {
+            match (option.name, &mut option.kind) {
+                ("Threads", &mut Kind::Spin { ref mut default, .. }) => {
+                    *default = engine.num_threads() as i64;
+                },
+                ("Hash", &mut Kind::Spin { ref mut default, .. }) => {
+                    *default = engine.hash_size() as i64;
+                },
+                ("Move Overhead", &mut Kind::Spin { ref mut default, .. }) => {
+                    *default = i64::from(engine.move_overhead());
+                },
+                ("MultiPV", &mut Kind::Spin { ref mut default, .. }) => {
+                    *default = i64::from(engine.multi_pv());
+                },
+                ("Checks Only Mate", &mut Kind::Check { ref mut default, .. }) => {
+                    *default = engine.checks_only_mate();
+                },
+                ("UCI_Chess960", &mut Kind::Check { ref mut default, .. }) => {
+                    *default = engine.variant() == Variant::Chess960;
+                },
+                ("UCI_Variant", &mut Kind::Combo { ref mut default, .. }) => {
+                    *default = match engine.variant() {
+                        Variant::ThreeCheck => "3check",
+                        Variant::KingOfTheHill => "kingofthehill",
+                        _ => "chess",
+                    };
+                },
+                _ => {},
+            }
+        }
+        registry
+    }
+}
+
+impl From<Vec<UciOption>> for Registry {
+    #[inline]
+    fn from(options: Vec<UciOption>) -> Registry {
+        Registry { options }
+    }
+}
+
+impl<'a> IntoIterator for &'a Registry {
+    type Item = &'a UciOption;
+    type IntoIter = slice::Iter<'a, UciOption>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.options.iter()
+    }
+}
+
+/// Lists every option on its own line, as sent in reply to "uci".
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for option in self {
+            writeln!(f, "{}", option)?;
+        }
+        Ok(())
+    }
+}
+
+impl Registry {
+    /// Returns the options of `self`.
+    #[inline]
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    /// Adds `option` to `self`, replacing any option of the same name.
+    pub fn insert(&mut self, option: UciOption) {
+        match self.options.iter().position(|o| o.matches(option.name)) {
+            Some(index) => self.options[index] = option,
+            None => self.options.push(option),
+        }
+    }
+
+    /// Returns the option referred to by `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&UciOption> {
+        self.options.iter().find(|option| option.matches(name))
+    }
+
+    /// Sets the option referred to by `name` on `engine` to `value`.
+    pub fn set(&self, engine: &mut Engine, name: &str, value: &str) -> Result<(), Error> {
+        match self.get(name) {
+            Some(option) => option.set(engine, value),
+            None => Err(Error::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let registry = Registry::default();
+        let report = registry.to_string();
+        let threads = format!("option name Threads type spin default {} min 1 max {}\n",
+                              cmp::min(::num_cpus::get(), MAX_THREADS), MAX_THREADS);
+        assert!(report.starts_with(&threads), "{}", report);
+        assert!(report.contains("option name Clear Hash type button\n"));
+        assert!(report.contains("option name Ponder type check default false\n"));
+    }
+
+    #[test]
+    fn report_engine() {
+        let engine = Engine::builder().num_threads(3).hash_size(4).multi_pv(2).build();
+        let report = Registry::from(&engine).to_string();
+        let threads = format!("option name Threads type spin default 3 min 1 max {}\n", MAX_THREADS);
+        assert!(report.starts_with(&threads), "{}", report);
+        assert!(report.contains("option name Hash type spin default 4 min 1 max "));
+        assert!(report.contains("option name MultiPV type spin default 2 min 1 max "));
+    }
+
+    #[test]
+    fn report_types() {
+        let combo = UciOption {
+            name: "Style",
+            kind: Kind::Combo { default: "Normal", vars: &["Solid", "Normal"], set: |_, _| true },
+        };
+        let string = UciOption {
+            name: "Book File",
+            kind: Kind::String { default: "", set: |_, _| true },
+        };
+        assert_eq!(combo.to_string(),
+                   "option name Style type combo default Normal var Solid var Normal");
+        assert_eq!(string.to_string(), "option name Book File type string default <empty>");
+    }
+
+    #[test]
+    fn set() {
+        let mut engine = Engine::builder().num_threads(1).build();
+        let mut registry = Registry::default();
+
+        registry.set(&mut engine, "multipv", "3").unwrap();
+        assert_eq!(engine.multi_pv(), 3);
+        registry.set(&mut engine, "CHECKS ONLY MATE", "True").unwrap();
+        assert!(engine.checks_only_mate());
+        registry.set(&mut engine, "UCI_Chess960", "true").unwrap();
+        assert_eq!(engine.variant(), Variant::Chess960);
+        registry.set(&mut engine, "UCI_Variant", "3Check").unwrap();
+        assert_eq!(engine.variant(), Variant::ThreeCheck);
+        registry.set(&mut engine, "UCI_Chess960", "false").unwrap();
+        assert_eq!(engine.variant(), Variant::ThreeCheck);
+        registry.set(&mut engine, "UCI_Variant", "KingOfTheHill").unwrap();
+        assert_eq!(engine.variant(), Variant::KingOfTheHill);
+        registry.set(&mut engine, "UCI_Variant", "chess").unwrap();
+        assert_eq!(engine.variant(), Variant::Standard);
+        registry.set(&mut engine, "Clear Hash", "").unwrap();
+
+        assert_eq!(registry.set(&mut engine, "MultiPV", "0"), Err(Error::OutOfRange));
+        assert_eq!(registry.set(&mut engine, "MultiPV", "many"), Err(Error::Invalid));
+        assert_eq!(registry.set(&mut engine, "Ponder", "yes"), Err(Error::Invalid));
+        assert_eq!(registry.set(&mut engine, "Contempt", "10"), Err(Error::Unknown));
+        assert_eq!(registry.set(&mut engine, "EvalFile", "/nonexistent.nnue"), Err(Error::Rejected));
+        assert!(engine.network().is_none());
+        assert_eq!(engine.multi_pv(), 3);
+
+        registry.insert(UciOption {
+            name: "Style",
+            kind: Kind::Combo { default: "Normal", vars: &["Solid", "Normal"], set: |_, _| true },
+        });
+        registry.set(&mut engine, "style", "solid").unwrap();
+        assert_eq!(registry.set(&mut engine, "style", "wild"), Err(Error::Invalid));
+    }
+}
@@ -0,0 +1,98 @@
+//! Destinations for the lines written by the engine.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+enum Sink {
+    Stdout,
+    Writer(Mutex<Box<dyn Write + Send>>),
+    Callback(Box<dyn Fn(&str) + Send + Sync>),
+}
+
+/// Where the engine writes its responses, such as "info" and "bestmove".
+///
+/// Responses are written from both the thread driving the protocol and the
+/// worker threads running searches, so each line is written as a whole.
+/// Cloning an `Output` yields a handle to the same destination.
+///
+/// # Examples
+///
+/// Lines can be collected through a callback:
+///
+/// ```
+/// use hexe::engine::Output;
+/// use std::sync::mpsc;
+///
+/// let (tx, rx) = mpsc::channel();
+/// let output = Output::from_fn(move |line| tx.send(line.to_owned()).unwrap());
+///
+/// output.write_str("readyok\n");
+/// assert_eq!(rx.recv().unwrap(), "readyok");
+/// ```
+#[derive(Clone)]
+pub struct Output(Arc<Sink>);
+
+impl Default for Output {
+    #[inline]
+    fn default() -> Output { Output::stdout() }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match *self.0 {
+            Sink::Stdout      => "Stdout",
+            Sink::Writer(_)   => "Writer",
+            Sink::Callback(_) => "Callback",
+        };
+        f.debug_tuple("Output").field(&kind).finish()
+    }
+}
+
+impl Output {
+    /// Writes to the standard output of the process.
+    #[inline]
+    pub fn stdout() -> Output {
+        Output(Arc::new(Sink::Stdout))
+    }
+
+    /// Writes to `writer`, flushing after each write.
+    #[inline]
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> Output {
+        Output(Arc::new(Sink::Writer(Mutex::new(Box::new(writer)))))
+    }
+
+    /// Calls `f` with each line written, without its trailing newline.
+    #[inline]
+    pub fn from_fn<F: Fn(&str) + Send + Sync + 'static>(f: F) -> Output {
+        Output(Arc::new(Sink::Callback(Box::new(f))))
+    }
+
+    /// Writes `s`, which is expected to consist of whole lines.
+    ///
+    /// Errors are ignored since there is no one left to report them to.
+    pub fn write_str(&self, s: &str) {
+        match *self.0 {
+            Sink::Stdout => {
+                let stdout = io::stdout();
+                let mut stdout = stdout.lock();
+                let _ = stdout.write_all(s.as_bytes()).and_then(|_| stdout.flush());
+            },
+            Sink::Writer(ref writer) => {
+                let mut writer = writer.lock();
+                let _ = writer.write_all(s.as_bytes()).and_then(|_| writer.flush());
+            },
+            Sink::Callback(ref f) => for line in s.lines() {
+                f(line);
+            },
+        }
+    }
+
+    /// Writes formatted text, allowing for use with `write!` and `writeln!`.
+    #[inline]
+    pub fn write_fmt(&self, args: fmt::Arguments) {
+        self.write_str(&fmt::format(args));
+    }
+}
@@ -0,0 +1,58 @@
+//! Search extensions.
+//!
+//! Forcing moves are searched more deeply than their remaining depth so that
+//! tactical lines are not cut off at the horizon. A single move is extended by
+//! at most `MAX_PER_MOVE` plies, and the extensions on a path from the root
+//! may not add up to more than the depth of the current iteration. This keeps
+//! long checking sequences from exploding the tree.
+
+/// The maximum number of plies that a single move may be extended by.
+pub const MAX_PER_MOVE: i32 = 1;
+
+/// The minimum remaining depth at which singular extensions are attempted.
+pub const SINGULAR_DEPTH: i32 = 6;
+
+/// The minimum depth of a table entry, relative to the remaining depth, for
+/// its move to be considered for a singular extension.
+pub const SINGULAR_TT_MARGIN: i32 = 3;
+
+/// The reasons for which a move may be extended.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Extensions {
+    /// The move gives check.
+    pub check: bool,
+    /// The move is the table move and all alternatives fail low against a
+    /// reduced-depth exclusion search.
+    pub singular: bool,
+    /// The move recaptures on the square where the previous move captured.
+    pub recapture: bool,
+}
+
+impl Extensions {
+    /// Returns the number of plies to extend the move by, given the `total`
+    /// extensions applied on the path from the root and the `root_depth` of
+    /// the current iteration.
+    #[inline]
+    pub fn plies(&self, total: i32, root_depth: i32) -> i32 {
+        if total >= root_depth {
+            return 0;
+        }
+        let plies = self.check as i32
+                  + self.singular as i32
+                  + self.recapture as i32;
+        plies.min(MAX_PER_MOVE).min(root_depth - total)
+    }
+}
+
+/// Returns the null window bound used to verify that the table move with
+/// value `tt_val` is singular at `depth`.
+#[inline]
+pub fn singular_beta(tt_val: i32, depth: i32) -> i32 {
+    tt_val - 2 * depth
+}
+
+/// Returns the depth of the exclusion search at `depth`.
+#[inline]
+pub fn singular_depth(depth: i32) -> i32 {
+    (depth - 1) / 2
+}
@@ -0,0 +1,87 @@
+//! Progress reported by running searches.
+
+use std::cmp;
+use std::fmt;
+use std::time::Duration;
+
+use super::{MATE, MATE_BOUND};
+use prelude::*;
+
+/// The score of a line from the perspective of the player to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Score {
+    /// An evaluation in centipawns.
+    Cp(i32),
+    /// A forced mate in the given number of moves, negative if the player to
+    /// move is getting mated.
+    Mate(i32),
+}
+
+/// Formats `self` as in UCI "info" output, such as `cp 35` or `mate -2`.
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Score::Cp(cp) => write!(f, "cp {}", cp),
+            Score::Mate(n) => write!(f, "mate {}", n),
+        }
+    }
+}
+
+impl Score {
+    /// Converts a search value into a score.
+    pub(crate) fn from_value(value: i32) -> Score {
+        if value >= MATE_BOUND {
+            Score::Mate((MATE - value + 1) / 2)
+        } else if value <= -MATE_BOUND {
+            Score::Mate(-(MATE + value) / 2)
+        } else {
+            Score::Cp(value)
+        }
+    }
+}
+
+/// The result of a completed iteration for a single line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchInfo {
+    /// The depth of the iteration in plies.
+    pub depth: u32,
+    /// The greatest number of plies reached from the root.
+    pub sel_depth: u32,
+    /// The rank of the line, starting at 1 for the best line.
+    pub multi_pv: u32,
+    /// The score of the line.
+    pub score: Score,
+    /// The number of nodes searched so far.
+    pub nodes: u64,
+    /// The number of successful tablebase probes so far.
+    pub tb_hits: u64,
+    /// The time spent searching so far.
+    pub time: Duration,
+    /// The principal variation, starting with the move at the root.
+    pub pv: Vec<Move>,
+}
+
+/// Formats `self` as the arguments of a UCI "info" command.
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = self.time_ms();
+        write!(
+            f,
+            "depth {} seldepth {} multipv {} score {} nodes {} nps {} tbhits {} time {} pv",
+            self.depth, self.sel_depth, self.multi_pv, self.score, self.nodes,
+            self.nodes * 1000 / cmp::max(time, 1), self.tb_hits, time,
+        )?;
+        for mv in &self.pv {
+            write!(f, " {}", mv)?;
+        }
+        Ok(())
+    }
+}
+
+impl SearchInfo {
+    /// Returns the time spent searching so far in milliseconds.
+    #[inline]
+    pub fn time_ms(&self) -> u64 {
+        super::as_millis(self.time)
+    }
+}
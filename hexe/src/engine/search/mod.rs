@@ -0,0 +1,1082 @@
+//! Iterative deepening alpha-beta search.
+
+use std::cmp;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use core::board::Variant;
+use core::mv::{Kind, MoveVec};
+use core::mv::kind::Promotion;
+use core::piece::Promotion as Prom;
+use engine::{Limits, Output};
+use engine::time::TimeManager;
+use eval;
+use prelude::*;
+use syzygy::{Tablebase, Wdl};
+use table::{Bound, Table};
+use uncon::*;
+
+pub mod ext;
+use self::ext::Extensions;
+
+mod info;
+pub use self::info::{Score, SearchInfo};
+
+mod stats;
+pub use self::stats::{DepthStats, SearchStats};
+
+#[cfg(test)]
+mod tests;
+
+/// Adds to the counters of the current iteration if statistics are collected.
+macro_rules! count {
+    ($search:expr, $($field:ident += $n:expr),+) => {
+        if $search.stats.is_some() {
+            $($search.counters.$field += $n;)+
+        }
+    };
+}
+
+/// The maximum number of plies that may be searched from the root.
+pub const MAX_PLY: usize = 128;
+
+/// A value greater than any score.
+pub const INFINITE: i32 = 32_001;
+
+/// The score of delivering checkmate at the root.
+pub const MATE: i32 = 32_000;
+
+/// Scores beyond this bound indicate a forced mate.
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+/// The score of a position won according to tablebases at the root, which is
+/// below that of any mate.
+pub const TB_WIN: i32 = MATE_BOUND - 1;
+
+/// Returns the score of delivering checkmate `ply` plies from the root.
+#[inline]
+pub fn mate_in(ply: usize) -> i32 {
+    MATE - ply as i32
+}
+
+/// Returns the score of being checkmated `ply` plies from the root.
+#[inline]
+pub fn mated_in(ply: usize) -> i32 {
+    ply as i32 - MATE
+}
+
+/// The number of nodes between checks of the stop conditions.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Per-ply search data.
+#[derive(Copy, Clone, Default)]
+struct Frame {
+    /// The move excluded from a singular extension verification search.
+    excluded: Option<Move>,
+    /// Quiet moves that caused a beta cutoff at this ply.
+    killers: [Option<Move>; 2],
+    /// The move made from this ply, or `None` for a null move.
+    mv: Option<Move>,
+    /// The sum of extensions applied on the path to this ply.
+    extensions: i32,
+}
+
+/// Flags through which a running search is controlled from other threads.
+#[derive(Copy, Clone)]
+pub struct Signals<'a> {
+    /// Has the search report its best move and finish.
+    pub halt: &'a AtomicBool,
+    /// Has the search finish as soon as possible.
+    pub kill: &'a AtomicBool,
+    /// Set while the search ponders; cleared upon "ponderhit".
+    pub ponder: &'a AtomicBool,
+}
+
+impl<'a> Signals<'a> {
+    #[inline]
+    fn should_finish(&self) -> bool {
+        self.halt.load(Ordering::Relaxed) || self.kill.load(Ordering::Relaxed)
+    }
+}
+
+/// The result of a search.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BestMove {
+    /// The best move found.
+    pub mv: Move,
+    /// The expected reply to `mv`, if known.
+    pub ponder: Option<Move>,
+}
+
+/// A principal variation from the root along with its score.
+#[derive(Clone, Debug)]
+struct Line {
+    pv: Vec<Move>,
+    score: i32,
+}
+
+/// A single search of a position.
+pub struct Search<'a> {
+    pos: &'a mut Position,
+    table: &'a Table,
+    signals: Signals<'a>,
+    limits: Limits,
+    /// Whether the search is pondering and not yet limited by the clock.
+    pondering: bool,
+    root_moves: MoveVec,
+    root_depth: i32,
+    /// The best lines found, ordered by score.
+    lines: Vec<Line>,
+    /// The index of the line currently being searched. Root moves that start
+    /// a previous line are excluded.
+    pv_index: usize,
+    start: Instant,
+    /// When the clock started running for the search.
+    clock_start: Instant,
+    time: Option<TimeManager>,
+    deadline: Option<Instant>,
+    nodes: u64,
+    sel_depth: usize,
+    stopped: bool,
+    frames: Vec<Frame>,
+    pv: Vec<Vec<Move>>,
+    history: Box<[[i32; 64]; 64]>,
+    out: Output,
+    /// Receives the lines of each iteration in place of `out`.
+    listener: Option<Sender<SearchInfo>>,
+    tablebase: Option<Arc<Tablebase>>,
+    /// The greatest number of pieces for which tablebases are probed.
+    tb_pieces: usize,
+    tb_hits: u64,
+    /// The counters of the current iteration.
+    counters: DepthStats,
+    /// The counters of each completed iteration, if collected.
+    stats: Option<SearchStats>,
+}
+
+impl<'a> Search<'a> {
+    /// Creates a search of `pos` within `limits`, restricted to `moves` at the
+    /// root if not empty.
+    pub fn new(pos: &'a mut Position,
+               table: &'a Table,
+               signals: Signals<'a>,
+               limits: Limits,
+               moves: &[Move]) -> Search<'a>
+    {
+        let mut root_moves = MoveVec::new();
+        pos.gen(&mut root_moves).legal();
+        if !moves.is_empty() {
+            let len = root_moves.len();
+            let legal = root_moves.clone();
+            root_moves.clear();
+            for &mv in legal[..len].iter().filter(|mv| moves.contains(mv)) {
+                root_moves.push(mv);
+            }
+        }
+
+        let start = Instant::now();
+        let pondering = limits.ponder && signals.ponder.load(Ordering::SeqCst);
+        let time = TimeManager::new(&Limits { ponder: pondering, ..limits }, pos.player());
+        let deadline = time.as_ref().map(|time| {
+            start + Duration::from_millis(time.maximum())
+        });
+
+        Search {
+            pos,
+            table,
+            signals,
+            limits,
+            pondering,
+            root_moves,
+            root_depth: 0,
+            lines: Vec::new(),
+            pv_index: 0,
+            start,
+            clock_start: start,
+            time,
+            deadline,
+            nodes: 0,
+            sel_depth: 0,
+            stopped: false,
+            frames: vec![Frame::default(); MAX_PLY + 2],
+            pv: vec![Vec::with_capacity(MAX_PLY); MAX_PLY + 2],
+            history: Box::new([[0; 64]; 64]),
+            out: Output::default(),
+            listener: None,
+            tablebase: None,
+            tb_pieces: 0,
+            tb_hits: 0,
+            counters: DepthStats::default(),
+            stats: None,
+        }
+    }
+
+    /// Sets where "info" lines are written, which is the standard output by
+    /// default.
+    #[inline]
+    pub fn set_output(&mut self, out: Output) {
+        self.out = out;
+    }
+
+    /// Sends the lines of each iteration to `listener` instead of writing
+    /// "info" lines.
+    #[inline]
+    pub fn set_listener(&mut self, listener: Sender<SearchInfo>) {
+        self.listener = Some(listener);
+    }
+
+    /// Probes `tablebase` at the root and within the search.
+    #[inline]
+    pub fn set_tablebase(&mut self, tablebase: Arc<Tablebase>) {
+        self.tb_pieces = tablebase.max_pieces();
+        self.tablebase = Some(tablebase);
+    }
+
+    /// Collects the statistics of each completed iteration.
+    #[inline]
+    pub fn collect_stats(&mut self) {
+        self.stats = Some(SearchStats::default());
+    }
+
+    /// Takes the statistics collected since
+    /// [`collect_stats`](#method.collect_stats) was called.
+    #[inline]
+    pub fn take_stats(&mut self) -> Option<SearchStats> {
+        self.stats.take()
+    }
+
+    /// Returns the score of the best line found from the perspective of the
+    /// player to move, or `None` if no line has been searched.
+    #[inline]
+    pub fn score(&self) -> Option<i32> {
+        self.lines.first().map(|line| line.score)
+    }
+
+    /// Runs the search to completion, returning the best move found, if any.
+    pub fn run(&mut self) -> Option<BestMove> {
+        // Mates are searched for regardless of the shortest win
+        if self.limits.mate == 0 {
+            self.probe_root();
+        }
+        if !self.root_moves.is_empty() {
+            if self.limits.mate != 0 && self.limits.checks_only {
+                self.iterate_checks();
+            } else {
+                self.iterate();
+            }
+        }
+
+        let best = match self.lines.first() {
+            Some(line) => line.pv.first().cloned(),
+            None => self.root_moves.first().cloned(),
+        };
+
+        // The best move may not be reported until the GUI tells us to stop
+        // or the ponder move is played
+        while self.limits.infinite || self.pondering {
+            if self.signals.should_finish() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+            self.check_ponder_hit();
+        }
+
+        best.map(|mv| BestMove { mv, ponder: self.ponder_move(mv) })
+    }
+
+    /// Keeps only the root moves that tablebases rank the best.
+    fn probe_root(&mut self) {
+        let tablebase = match self.tablebase {
+            Some(ref tablebase) => tablebase.clone(),
+            None => return,
+        };
+        if self.root_moves.is_empty() || self.pos.board().len() > self.tb_pieces {
+            return;
+        }
+
+        let moves: Vec<Move> = self.root_moves.iter().cloned().collect();
+        let ranks = match tablebase.rank_root_moves(self.pos, &moves) {
+            Some(ranks) => ranks,
+            None => return,
+        };
+        self.tb_hits += moves.len() as u64;
+
+        let best = ranks.iter().cloned().max().unwrap_or(0);
+        self.root_moves.clear();
+        for (&mv, &rank) in moves.iter().zip(ranks.iter()) {
+            if rank == best {
+                self.root_moves.push(mv);
+            }
+        }
+    }
+
+    /// Deepens the search one iteration at a time until a limit is reached.
+    fn iterate(&mut self) {
+        let max_depth = match self.limits.depth as usize {
+            0 => MAX_PLY,
+            n => cmp::min(n, MAX_PLY),
+        };
+        let multi_pv = cmp::min(
+            cmp::max(self.limits.multi_pv as usize, 1),
+            self.root_moves.len(),
+        );
+
+        for depth in 1..(max_depth as i32 + 1) {
+            self.root_depth = depth;
+            self.sel_depth = 0;
+            self.counters = DepthStats { nodes: self.nodes, ..Default::default() };
+
+            for index in 0..multi_pv {
+                self.pv_index = index;
+
+                let prev = self.lines.get(index).map_or(0, |line| line.score);
+                let value = self.aspiration(depth, prev);
+
+                // An interrupted iteration still yields its best move so far
+                if !self.pv[0].is_empty() {
+                    let line = Line { pv: self.pv[0].clone(), score: value };
+                    if index < self.lines.len() {
+                        self.lines[index] = line;
+                    } else {
+                        self.lines.push(line);
+                    }
+                }
+                if self.stopped {
+                    break;
+                }
+            }
+            if self.stopped {
+                break;
+            }
+
+            self.lines.sort_by(|a, b| b.score.cmp(&a.score));
+            self.report(depth);
+            self.record_stats(depth);
+
+            // Stop once a mate within the requested number of moves is proven
+            let (best, score) = (self.lines[0].pv[0], self.lines[0].score);
+            if self.limits.mate != 0 && score >= self.mate_limit() {
+                break;
+            }
+
+            self.check_ponder_hit();
+            let elapsed = self.clock_ms();
+            let single = self.root_moves.len() == 1;
+            if let Some(ref mut time) = self.time {
+                time.update(best, score);
+                if time.should_stop(elapsed, single) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Searches for a mate within the requested number of moves, only
+    /// considering checking moves for the player to move.
+    fn iterate_checks(&mut self) {
+        let max_depth = cmp::min(self.mate_plies(), MAX_PLY);
+
+        for depth in (1..(max_depth + 1)).filter(|d| d % 2 == 1) {
+            self.root_depth = depth as i32;
+            self.sel_depth = 0;
+            self.counters = DepthStats { nodes: self.nodes, ..Default::default() };
+
+            let value = self.search_checks(depth as i32, -INFINITE, INFINITE, 0);
+            if self.stopped {
+                break;
+            }
+            self.record_stats(depth as i32);
+            if value >= self.mate_limit() {
+                self.lines = vec![Line { pv: self.pv[0].clone(), score: value }];
+                self.report(depth as i32);
+                break;
+            }
+            if self.listener.is_none() {
+                writeln!(self.out, "info depth {} nodes {} time {}",
+                         depth, self.nodes, self.elapsed_ms());
+            }
+        }
+    }
+
+    /// Returns the number of plies within which `go mate` requests a mate.
+    fn mate_plies(&self) -> usize {
+        (self.limits.mate as usize).saturating_mul(2).saturating_sub(1)
+    }
+
+    /// Returns the lowest score of a mate within the requested number of
+    /// moves.
+    fn mate_limit(&self) -> i32 {
+        mate_in(cmp::min(self.mate_plies(), MAX_PLY))
+    }
+
+    /// Returns the expected reply to `best`, taken from the principal
+    /// variation or else from the table.
+    fn ponder_move(&mut self, best: Move) -> Option<Move> {
+        if let Some(line) = self.lines.first() {
+            if line.pv.first() == Some(&best) && line.pv.len() > 1 {
+                return Some(line.pv[1]);
+            }
+        }
+
+        self.pos.make_move(best);
+        let mut moves = MoveVec::new();
+        self.pos.gen(&mut moves).legal();
+        let reply = self.table.probe(self.pos.key())
+                              .and_then(|entry| entry.mv())
+                              .filter(|mv| moves.contains(mv));
+        self.pos.unmake_move(best);
+        reply
+    }
+
+    /// Starts the clock if pondering ended with the expected move played.
+    fn check_ponder_hit(&mut self) {
+        if !self.pondering || self.signals.ponder.load(Ordering::SeqCst) {
+            return;
+        }
+        self.pondering = false;
+        self.clock_start = Instant::now();
+
+        let limits = Limits { ponder: false, ..self.limits };
+        self.time = TimeManager::new(&limits, self.pos.player());
+
+        let start = self.clock_start;
+        self.deadline = self.time.as_ref().map(|time| {
+            start + Duration::from_millis(time.maximum())
+        });
+    }
+
+    /// Reports the lines of a completed iteration.
+    fn report(&self, depth: i32) {
+        let time = self.start.elapsed();
+        for (index, line) in self.lines.iter().enumerate() {
+            let info = SearchInfo {
+                depth: depth as u32,
+                sel_depth: self.sel_depth as u32,
+                multi_pv: index as u32 + 1,
+                score: Score::from_value(line.score),
+                nodes: self.nodes,
+                tb_hits: self.tb_hits,
+                time,
+                pv: line.pv.clone(),
+            };
+            match self.listener {
+                // The receiver may no longer be interested
+                Some(ref listener) => { let _ = listener.send(info); },
+                None => writeln!(self.out, "info {}", info),
+            }
+        }
+    }
+
+    /// Records the counters of the iteration at `depth` if statistics are
+    /// collected.
+    fn record_stats(&mut self, depth: i32) {
+        if let Some(ref mut stats) = self.stats {
+            let counters = self.counters;
+            stats.depths.push(DepthStats {
+                depth: depth as u32,
+                nodes: self.nodes - counters.nodes,
+                ..counters
+            });
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        as_millis(self.start.elapsed())
+    }
+
+    /// Returns the milliseconds elapsed since the clock started.
+    fn clock_ms(&self) -> u64 {
+        as_millis(self.clock_start.elapsed())
+    }
+
+    /// Returns whether the search should stop, checking the stop conditions
+    /// every few nodes.
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.limits.nodes != 0 && self.nodes >= self.limits.nodes as u64 {
+            self.stopped = true;
+        } else if self.nodes % CHECK_INTERVAL == 0 {
+            self.check_ponder_hit();
+            let expired = match self.deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => false,
+            };
+            self.stopped = expired || self.signals.should_finish();
+        }
+        self.stopped
+    }
+
+    /// Searches the root with a window around the previous iteration's score,
+    /// widening it upon failing low or high.
+    fn aspiration(&mut self, depth: i32, prev: i32) -> i32 {
+        let mut delta = 25;
+        let (mut alpha, mut beta) = if depth >= 5 {
+            (cmp::max(prev - delta, -INFINITE), cmp::min(prev + delta, INFINITE))
+        } else {
+            (-INFINITE, INFINITE)
+        };
+
+        loop {
+            let value = self.search(depth, alpha, beta, 0);
+            if self.stopped {
+                return value;
+            }
+            if value <= alpha {
+                alpha = cmp::max(alpha - delta, -INFINITE);
+            } else if value >= beta {
+                beta = cmp::min(beta + delta, INFINITE);
+            } else {
+                return value;
+            }
+            delta *= 2;
+        }
+    }
+
+    fn search(&mut self, depth: i32, mut alpha: i32, mut beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+
+        let root    = ply == 0;
+        let pv_node = beta - alpha > 1;
+
+        if !root {
+            // The previous move won by the rules of the variant
+            if self.pos.variant_winner().is_some() {
+                return mated_in(ply);
+            }
+
+            // Mate distance pruning: no line can beat a shorter mate
+            alpha = cmp::max(alpha, mated_in(ply));
+            beta = cmp::min(beta, mate_in(ply + 1));
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        if depth <= 0 {
+            return self.qsearch(alpha, beta, ply);
+        }
+
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return eval::evaluate(self.pos);
+        }
+        self.sel_depth = cmp::max(self.sel_depth, ply);
+
+        if !root && self.is_draw() {
+            return 0;
+        }
+
+        let in_check = self.pos.in_check();
+        let key      = self.pos.key();
+        let excluded = self.frames[ply].excluded;
+
+        // Exclusion searches must not see the result of the full search
+        let entry = if excluded.is_none() { self.table.probe(key) } else { None };
+        if excluded.is_none() {
+            count!(self, tt_probes += 1, tt_hits += entry.is_some() as u64);
+        }
+
+        if let Some(entry) = entry {
+            if !pv_node && entry.depth() >= depth {
+                let value = value_from_tt(entry.val(), ply);
+                match entry.bound() {
+                    Bound::Exact => return value,
+                    Bound::Lower if value >= beta => return value,
+                    Bound::Upper if value <= alpha => return value,
+                    _ => {},
+                }
+            }
+        }
+
+        // Tablebases are exact once a capture or pawn move leaves few pieces
+        if !root && excluded.is_none() && self.pos.halfmoves() == 0
+            && self.pos.board().len() <= self.tb_pieces
+        {
+            let wdl = match self.tablebase {
+                Some(ref tablebase) => tablebase.probe_wdl(self.pos),
+                None => None,
+            };
+            if let Some(wdl) = wdl {
+                self.tb_hits += 1;
+                let (value, bound) = match wdl {
+                    Wdl::Win  => (TB_WIN - ply as i32, Bound::Lower),
+                    Wdl::Loss => (ply as i32 - TB_WIN, Bound::Upper),
+                    draw => (draw as i32, Bound::Exact),
+                };
+                let cutoff = match bound {
+                    Bound::Lower => value >= beta,
+                    Bound::Upper => value <= alpha,
+                    _ => true,
+                };
+                if cutoff {
+                    let depth = cmp::min(depth + 6, MAX_PLY as i32 - 1);
+                    self.table.store(key, None, value, depth, bound);
+                    return value;
+                }
+            }
+        }
+
+        // Null move pruning
+        if !pv_node && !in_check && excluded.is_none() && ply > 0 && depth >= 3
+            && self.frames[ply - 1].mv.is_some()
+            && beta.abs() < MATE_BOUND
+            && self.has_non_pawns()
+            && eval::evaluate(self.pos) >= beta
+        {
+            let reduction = 2 + depth / 4;
+            count!(self, null_moves += 1);
+            self.frames[ply].mv = None;
+            self.frames[ply + 1].extensions = self.frames[ply].extensions;
+
+            self.pos.make_null();
+            let value = -self.search(depth - 1 - reduction, -beta, -beta + 1, ply + 1);
+            self.pos.unmake_null();
+
+            if self.stopped {
+                return 0;
+            }
+            if value >= beta {
+                count!(self, null_cutoffs += 1);
+                return if value >= MATE_BOUND { beta } else { value };
+            }
+        }
+
+        let mut moves = MoveVec::new();
+        if root {
+            let lines = &self.lines[..self.pv_index];
+            for &mv in self.root_moves.iter() {
+                if !lines.iter().any(|line| line.pv[0] == mv) {
+                    moves.push(mv);
+                }
+            }
+        } else {
+            self.pos.gen(&mut moves).legal();
+        }
+
+        let tt_move = if root {
+            self.lines.get(self.pv_index).map(|line| line.pv[0])
+        } else {
+            entry.and_then(|e| e.mv())
+        }.filter(|mv| moves.contains(mv));
+
+        let mut scores = [0; 256];
+        for (score, &mv) in scores.iter_mut().zip(moves.iter()) {
+            *score = self.score_move(mv, tt_move, ply);
+        }
+
+        let mut best_value = -INFINITE;
+        let mut best_move  = None;
+        let mut searched   = 0;
+
+        for i in 0..moves.len() {
+            pick_best(&mut moves, &mut scores, i);
+            let mv = moves[i];
+            if Some(mv) == excluded {
+                continue;
+            }
+
+            let capture = self.pos.is_capture(mv);
+            let quiet   = !capture && mv.kind() != Kind::Promotion;
+
+            let mut extensions = Extensions::default();
+
+            // Singular extension and multi-cut
+            if Some(mv) == tt_move && !root && excluded.is_none()
+                && depth >= ext::SINGULAR_DEPTH
+            {
+                if let Some(entry) = entry {
+                    let tt_value = value_from_tt(entry.val(), ply);
+                    if entry.depth() >= depth - ext::SINGULAR_TT_MARGIN
+                        && entry.bound() != Bound::Upper
+                        && tt_value.abs() < MATE_BOUND
+                    {
+                        let s_beta = ext::singular_beta(tt_value, depth);
+                        let s_depth = ext::singular_depth(depth);
+
+                        self.frames[ply].excluded = Some(mv);
+                        let value = self.search(s_depth, s_beta - 1, s_beta, ply);
+                        self.frames[ply].excluded = None;
+
+                        if self.stopped {
+                            return 0;
+                        }
+                        if value < s_beta {
+                            extensions.singular = true;
+                        } else if s_beta >= beta {
+                            // Multiple moves beat beta without the table move
+                            return s_beta;
+                        }
+                    }
+                }
+            }
+
+            extensions.recapture = capture && ply > 0
+                && self.pos.state().captured().is_some()
+                && self.frames[ply - 1].mv.map(Move::dst) == Some(mv.dst());
+
+            self.frames[ply].mv = Some(mv);
+            self.pos.make_move(mv);
+
+            extensions.check = self.pos.in_check();
+            let total = self.frames[ply].extensions;
+            let extension = extensions.plies(total, self.root_depth);
+            self.frames[ply + 1].extensions = total + extension;
+
+            let new_depth = depth - 1 + extension;
+
+            let mut value;
+            if searched == 0 {
+                value = -self.search(new_depth, -beta, -alpha, ply + 1);
+            } else {
+                // Late move reductions
+                let mut reduction = 0;
+                if depth >= 3 && searched >= 3 && quiet && !in_check
+                    && extension == 0
+                {
+                    reduction = 1 + (searched >= 6) as i32
+                                  + (depth >= 8) as i32
+                                  - pv_node as i32;
+                    reduction = cmp::max(0, cmp::min(reduction, new_depth - 1));
+                }
+
+                if reduction > 0 {
+                    count!(self, reductions += 1);
+                }
+                value = -self.search(new_depth - reduction, -alpha - 1, -alpha, ply + 1);
+                if reduction > 0 && value > alpha {
+                    count!(self, re_searches += 1);
+                    value = -self.search(new_depth, -alpha - 1, -alpha, ply + 1);
+                }
+                if value > alpha && value < beta {
+                    value = -self.search(new_depth, -beta, -alpha, ply + 1);
+                }
+            }
+
+            self.pos.unmake_move(mv);
+            searched += 1;
+
+            if self.stopped {
+                return 0;
+            }
+
+            if value > best_value {
+                best_value = value;
+
+                if value > alpha {
+                    alpha = value;
+                    best_move = Some(mv);
+                    self.update_pv(ply, mv);
+
+                    if value >= beta {
+                        count!(self, cutoffs += 1, first_move_cutoffs += (searched == 1) as u64);
+                        if quiet {
+                            self.update_quiet(ply, mv, depth);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        if searched == 0 {
+            return if excluded.is_some() {
+                alpha
+            } else if in_check {
+                mated_in(ply)
+            } else {
+                0
+            };
+        }
+
+        // The root result is incomplete when moves of previous lines are left out
+        if excluded.is_none() && !(root && self.pv_index > 0) {
+            let bound = if best_value >= beta {
+                Bound::Lower
+            } else if best_move.is_some() {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            let value = value_to_tt(best_value, ply);
+            self.table.store(key, best_move, value, depth, bound);
+        }
+
+        best_value
+    }
+
+    /// Searches for mates within `depth` plies where the attacker, the player
+    /// to move at the root, only plays checking moves.
+    ///
+    /// Lines without a mate score 0.
+    fn search_checks(&mut self, depth: i32, mut alpha: i32, mut beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        self.sel_depth = cmp::max(self.sel_depth, ply);
+
+        if self.pos.variant_winner().is_some() {
+            return mated_in(ply);
+        }
+
+        let mut moves = MoveVec::new();
+        if ply == 0 {
+            moves = self.root_moves.clone();
+        } else {
+            self.pos.gen(&mut moves).legal();
+        }
+
+        if moves.is_empty() {
+            return if self.pos.in_check() { mated_in(ply) } else { 0 };
+        }
+        if depth <= 0 {
+            return 0;
+        }
+
+        alpha = cmp::max(alpha, mated_in(ply));
+        beta = cmp::min(beta, mate_in(ply + 1));
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let attacker = ply % 2 == 0;
+        let mut best_value = -INFINITE;
+
+        for &mv in moves.iter() {
+            self.pos.make_move(mv);
+            if attacker && !self.pos.in_check() {
+                self.pos.unmake_move(mv);
+                continue;
+            }
+            let value = -self.search_checks(depth - 1, -beta, -alpha, ply + 1);
+            self.pos.unmake_move(mv);
+
+            if self.stopped {
+                return 0;
+            }
+            if value > best_value {
+                best_value = value;
+                if value > alpha {
+                    alpha = value;
+                    self.update_pv(ply, mv);
+                    if value >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // The attacker has no checks left
+        if best_value == -INFINITE { 0 } else { best_value }
+    }
+
+    /// Searches captures and promotions until the position is quiet.
+    fn qsearch(&mut self, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.nodes += 1;
+        count!(self, qnodes += 1);
+        if self.should_stop() {
+            return 0;
+        }
+        if self.pos.variant_winner().is_some() {
+            return mated_in(ply);
+        }
+        if ply >= MAX_PLY {
+            return eval::evaluate(self.pos);
+        }
+        self.sel_depth = cmp::max(self.sel_depth, ply);
+
+        let in_check = self.pos.in_check();
+        let mut best_value = -INFINITE;
+
+        if !in_check {
+            let stand_pat = eval::evaluate(self.pos);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = cmp::max(alpha, stand_pat);
+            best_value = stand_pat;
+        }
+
+        let mut moves = MoveVec::new();
+        if in_check {
+            self.pos.gen(&mut moves).legal();
+            if moves.is_empty() {
+                return mated_in(ply);
+            }
+        } else {
+            self.pos.gen(&mut moves).noisy();
+        }
+
+        let mut scores = [0; 256];
+        for (score, &mv) in scores.iter_mut().zip(moves.iter()) {
+            *score = self.score_move(mv, None, ply);
+        }
+
+        for i in 0..moves.len() {
+            pick_best(&mut moves, &mut scores, i);
+            let mv = moves[i];
+
+            // Underpromotions are almost never better than a queen
+            if !in_check && scores[i] < 0 {
+                break;
+            }
+
+            self.pos.make_move(mv);
+            let value = -self.qsearch(-beta, -alpha, ply + 1);
+            self.pos.unmake_move(mv);
+
+            if self.stopped {
+                return 0;
+            }
+            if value > best_value {
+                best_value = value;
+                if value > alpha {
+                    alpha = value;
+                    if value >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        best_value
+    }
+
+    /// Returns the ordering score of `mv`, where greater scores are searched
+    /// first.
+    fn score_move(&self, mv: Move, tt_move: Option<Move>, ply: usize) -> i32 {
+        if Some(mv) == tt_move {
+            return 1_000_000;
+        }
+
+        let pieces = self.pos.pieces();
+        let mut score = 0;
+
+        // Reaching the center wins King of the Hill
+        if self.pos.variant() == Variant::KingOfTheHill && mv.dst().center_distance() == 0
+            && pieces.get(mv.src()).map(|pc| pc.role()) == Some(Role::King)
+        {
+            return 300_000;
+        }
+
+        if mv.kind() == Kind::Promotion {
+            let promotion = unsafe { Promotion::from_unchecked(mv) };
+            if promotion.piece() != Prom::Queen {
+                return -100_000;
+            }
+            score += 200_000;
+        }
+
+        if self.pos.is_capture(mv) {
+            // Most valuable victim, least valuable attacker
+            let victim = pieces.get(mv.dst()).map_or(0, |p| p.role() as i32);
+            let attacker = pieces.get(mv.src()).map_or(0, |p| p.role() as i32);
+            return score + 100_000 + victim * 8 - attacker;
+        } else if score != 0 {
+            return score;
+        }
+
+        let frame = &self.frames[ply];
+        if frame.killers[0] == Some(mv) {
+            90_000
+        } else if frame.killers[1] == Some(mv) {
+            89_000
+        } else {
+            self.history[mv.src() as usize][mv.dst() as usize]
+        }
+    }
+
+    /// Records `mv` at `ply` followed by the principal variation of the next
+    /// ply.
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let pv = &mut head[ply];
+        pv.clear();
+        pv.push(mv);
+        pv.extend_from_slice(&tail[0]);
+    }
+
+    /// Updates the move ordering heuristics for a quiet move that caused a
+    /// beta cutoff.
+    fn update_quiet(&mut self, ply: usize, mv: Move, depth: i32) {
+        let killers = &mut self.frames[ply].killers;
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        // Gravity keeps scores within the bounds of the bonus
+        let bonus = cmp::min(depth * depth, 400);
+        let entry = &mut self.history[mv.src() as usize][mv.dst() as usize];
+        *entry += bonus - *entry * bonus / 16_384;
+    }
+
+    /// Returns whether the position is drawn regardless of the moves made.
+    ///
+    /// A single repetition is enough since the same moves can be repeated.
+    fn is_draw(&self) -> bool {
+        self.pos.is_repetition(2)
+            || self.pos.is_fifty_move_draw()
+            || self.pos.has_insufficient_material()
+    }
+
+    /// Returns whether the player to move has pieces other than pawns and
+    /// their king.
+    fn has_non_pawns(&self) -> bool {
+        let board = self.pos.board();
+        let pawns = board.bits(Role::Pawn) | board.bits(Role::King);
+        !(self.pos.player_bits() - pawns).is_empty()
+    }
+}
+
+/// Swaps the move with the greatest score at or after `start` into `start`.
+#[inline]
+fn pick_best(moves: &mut MoveVec, scores: &mut [i32; 256], start: usize) {
+    let mut best = start;
+    for i in (start + 1)..moves.len() {
+        if scores[i] > scores[best] {
+            best = i;
+        }
+    }
+    moves.swap(start, best);
+    scores.swap(start, best);
+}
+
+#[inline]
+fn as_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + (duration.subsec_nanos() / 1_000_000) as u64
+}
+
+/// Converts a mate score relative to the root into one relative to `ply`.
+#[inline]
+fn value_to_tt(value: i32, ply: usize) -> i32 {
+    if value >= MATE_BOUND {
+        value + ply as i32
+    } else if value <= -MATE_BOUND {
+        value - ply as i32
+    } else {
+        value
+    }
+}
+
+/// Converts a mate score relative to `ply` into one relative to the root.
+#[inline]
+fn value_from_tt(value: i32, ply: usize) -> i32 {
+    if value >= MATE_BOUND {
+        value - ply as i32
+    } else if value <= -MATE_BOUND {
+        value + ply as i32
+    } else {
+        value
+    }
+}
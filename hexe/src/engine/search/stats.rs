@@ -0,0 +1,135 @@
+//! Statistics of how the search prunes and orders moves.
+
+use std::fmt::Write;
+
+/// Counters of a single iteration of a search.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepthStats {
+    /// The depth of the iteration.
+    pub depth: u32,
+    /// The number of nodes searched, including `qnodes`.
+    pub nodes: u64,
+    /// The number of nodes searched by the quiescence search.
+    pub qnodes: u64,
+    /// The number of transposition table probes.
+    pub tt_probes: u64,
+    /// The number of probes that found an entry.
+    pub tt_hits: u64,
+    /// The number of nodes that failed high.
+    pub cutoffs: u64,
+    /// The number of nodes that failed high on the first move searched.
+    pub first_move_cutoffs: u64,
+    /// The number of null move searches.
+    pub null_moves: u64,
+    /// The number of null move searches that failed high.
+    pub null_cutoffs: u64,
+    /// The number of moves searched with a late move reduction.
+    pub reductions: u64,
+    /// The number of reduced moves searched again at full depth.
+    pub re_searches: u64,
+}
+
+#[inline]
+fn rate(count: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { count as f64 / total as f64 }
+}
+
+impl DepthStats {
+    /// Returns the fraction of transposition table probes that hit.
+    #[inline]
+    pub fn tt_hit_rate(&self) -> f64 {
+        rate(self.tt_hits, self.tt_probes)
+    }
+
+    /// Returns the fraction of cutoffs caused by the first move searched.
+    #[inline]
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        rate(self.first_move_cutoffs, self.cutoffs)
+    }
+
+    /// Returns the fraction of null move searches that failed high.
+    #[inline]
+    pub fn null_move_rate(&self) -> f64 {
+        rate(self.null_cutoffs, self.null_moves)
+    }
+
+    /// Returns the fraction of reduced moves that were searched again.
+    #[inline]
+    pub fn re_search_rate(&self) -> f64 {
+        rate(self.re_searches, self.reductions)
+    }
+}
+
+/// Statistics collected by a search for each completed iteration.
+///
+/// Collection is enabled by
+/// [`SearchParams::stats`](struct.SearchParams.html#structfield.stats) or
+/// [`Engine::set_stats_output`](struct.Engine.html#method.set_stats_output).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The counters of each iteration in order of depth.
+    pub depths: Vec<DepthStats>,
+}
+
+impl SearchStats {
+    /// Returns the number of nodes searched by all iterations.
+    pub fn nodes(&self) -> u64 {
+        self.depths.iter().map(|d| d.nodes).sum()
+    }
+
+    /// Returns the ratio of the nodes of iteration `index` to those of the
+    /// previous iteration, if any.
+    pub fn branching_factor(&self, index: usize) -> Option<f64> {
+        if index == 0 || index >= self.depths.len() || self.depths[index - 1].nodes == 0 {
+            return None;
+        }
+        Some(self.depths[index].nodes as f64 / self.depths[index - 1].nodes as f64)
+    }
+
+    /// Returns the geometric mean of the branching factor of each iteration
+    /// after the first.
+    pub fn average_branching_factor(&self) -> Option<f64> {
+        let (first, last) = (self.depths.first()?, self.depths.last()?);
+        if self.depths.len() < 2 || first.nodes == 0 {
+            return None;
+        }
+        let ratio = last.nodes as f64 / first.nodes as f64;
+        Some(ratio.powf(1.0 / (self.depths.len() - 1) as f64))
+    }
+
+    /// Returns `self` as a single line of JSON, with the counters and rates
+    /// of each iteration.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(json, "{{\"nodes\":{},\"branching_factor\":{},\"depths\":[",
+                       self.nodes(), json_number(self.average_branching_factor()));
+        for (i, d) in self.depths.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"depth\":{},\"nodes\":{},\"qnodes\":{},\"tt_probes\":{},\"tt_hits\":{},\
+                 \"tt_hit_rate\":{:.4},\"cutoffs\":{},\"first_move_cutoffs\":{},\
+                 \"first_move_cutoff_rate\":{:.4},\"null_moves\":{},\"null_cutoffs\":{},\
+                 \"null_move_rate\":{:.4},\"reductions\":{},\"re_searches\":{},\
+                 \"re_search_rate\":{:.4},\"branching_factor\":{}}}",
+                d.depth, d.nodes, d.qnodes, d.tt_probes, d.tt_hits, d.tt_hit_rate(),
+                d.cutoffs, d.first_move_cutoffs, d.first_move_cutoff_rate(),
+                d.null_moves, d.null_cutoffs, d.null_move_rate(),
+                d.reductions, d.re_searches, d.re_search_rate(),
+                json_number(self.branching_factor(i)),
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+fn json_number(value: Option<f64>) -> String {
+    match value {
+        Some(value) if value.is_finite() => format!("{:.4}", value),
+        _ => "null".to_owned(),
+    }
+}
+
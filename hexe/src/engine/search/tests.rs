@@ -0,0 +1,281 @@
+use super::*;
+use core::board::Variant;
+use core::fen::Fen;
+
+fn position(fen: &str) -> Position {
+    let fen: Fen = fen.parse().unwrap();
+    Position::from_fen(&fen).unwrap()
+}
+
+/// Creates a search that does not write "info" lines.
+fn search<'a>(pos: &'a mut Position,
+              table: &'a Table,
+              signals: Signals<'a>,
+              limits: Limits,
+              moves: &[Move]) -> Search<'a>
+{
+    let mut search = Search::new(pos, table, signals, limits, moves);
+    search.set_output(Output::from_fn(|_| {}));
+    search
+}
+
+fn limits(depth: u32) -> Limits {
+    Limits { depth, ..Limits::default() }
+}
+
+/// Searches `fen` to `depth`, returning the best move in UCI notation.
+fn best_move(fen: &str, depth: u32, moves: &[&str]) -> Option<String> {
+    let mut pos = position(fen);
+    let table = Table::new(1);
+    let halt = AtomicBool::new(false);
+    let kill = AtomicBool::new(false);
+    let ponder = AtomicBool::new(false);
+    let signals = Signals { halt: &halt, kill: &kill, ponder: &ponder };
+
+    let mut legal = MoveVec::new();
+    pos.gen(&mut legal).legal();
+    let moves: Vec<Move> = legal.iter().cloned().filter(|mv| {
+        moves.contains(&&*mv.to_string())
+    }).collect();
+
+    let mut search = search(&mut pos, &table, signals, limits(depth), &moves);
+    search.run().map(|best| best.mv.to_string())
+}
+
+#[test]
+fn mate_in_one() {
+    let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    assert_eq!(best_move(fen, 3, &[]).unwrap(), "a1a8");
+}
+
+#[test]
+fn smothered_mate() {
+    // 1. Qg8+ Rxg8 2. Nf7#
+    let fen = "r6k/1p4pp/7N/3Q4/8/8/8/6K1 w - - 0 1";
+    assert_eq!(best_move(fen, 5, &[]).unwrap(), "d5g8");
+}
+
+#[test]
+fn wins_material() {
+    // The knight forks king and queen
+    let fen = "q3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+    assert_eq!(best_move(fen, 4, &[]).unwrap(), "d5c7");
+}
+
+#[test]
+fn no_legal_moves() {
+    let mate = "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1";
+    let stalemate = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1";
+    assert_eq!(best_move(mate, 2, &[]), None);
+    assert_eq!(best_move(stalemate, 2, &[]), None);
+}
+
+#[test]
+fn search_moves() {
+    let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    assert_eq!(best_move(fen, 3, &["g1f2"]).unwrap(), "g1f2");
+}
+
+#[test]
+fn extensions_bounded() {
+    let all = Extensions { check: true, singular: true, recapture: true };
+    assert_eq!(all.plies(0, 10), ext::MAX_PER_MOVE);
+    assert_eq!(all.plies(10, 10), 0);
+    assert_eq!(all.plies(12, 10), 0);
+    assert_eq!(Extensions::default().plies(0, 10), 0);
+}
+
+#[test]
+fn ponder_move() {
+    // 1. Qg8+ Rxg8 is forced
+    let mut pos = position("r6k/1p4pp/7N/3Q4/8/8/8/6K1 w - - 0 1");
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let best = search(&mut pos, &table, signals, limits(5), &[]).run().unwrap();
+    assert_eq!(best.mv.to_string(), "d5g8");
+    assert_eq!(best.ponder.map(|mv| mv.to_string()), Some("a8g8".to_owned()));
+}
+
+#[test]
+fn ponder_hit() {
+    let mut pos = Position::default();
+    let table = Table::new(1);
+    let halt = AtomicBool::new(false);
+    let kill = AtomicBool::new(false);
+    let ponder = Arc::new(AtomicBool::new(true));
+    let signals = Signals { halt: &halt, kill: &kill, ponder: &ponder };
+    let limits = Limits { ponder: true, move_time: 50, ..Limits::default() };
+
+    // Without a ponder hit, the search would never finish
+    let hit = {
+        let ponder = ponder.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            ponder.store(false, Ordering::SeqCst);
+        })
+    };
+    let best = search(&mut pos, &table, signals, limits, &[]).run();
+    hit.join().unwrap();
+    assert!(best.is_some());
+}
+
+#[test]
+fn multi_pv() {
+    let mut pos = Position::default();
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let limits = Limits { depth: 3, multi_pv: 4, ..Limits::default() };
+    let mut search = search(&mut pos, &table, signals, limits, &[]);
+    let best = search.run().unwrap();
+
+    assert_eq!(search.lines.len(), 4);
+    assert_eq!(search.lines[0].pv[0], best.mv);
+    for (i, a) in search.lines.iter().enumerate() {
+        for b in &search.lines[(i + 1)..] {
+            assert_ne!(a.pv[0], b.pv[0]);
+            assert!(a.score >= b.score);
+        }
+    }
+}
+
+#[test]
+fn multi_pv_search_moves() {
+    // The mate ranks above the quiet king move
+    let mut pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let moves = [Move::normal(Square::A1, Square::A8), Move::normal(Square::G1, Square::F2)];
+    let limits = Limits { depth: 3, multi_pv: 5, ..Limits::default() };
+    let mut search = search(&mut pos, &table, signals, limits, &moves);
+    search.run();
+
+    let lines: Vec<Move> = search.lines.iter().map(|line| line.pv[0]).collect();
+    assert_eq!(lines, moves);
+}
+
+/// Searches `fen` for a mate in `moves`, returning the best move and score.
+fn find_mate(fen: &str, moves: u32, checks_only: bool) -> (String, i32) {
+    let mut pos = position(fen);
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let limits = Limits { mate: moves, checks_only, ..Limits::default() };
+    let mut search = search(&mut pos, &table, signals, limits, &[]);
+    let best = search.run().unwrap();
+    (best.mv.to_string(), search.lines[0].score)
+}
+
+#[test]
+fn score() {
+    assert_eq!(Score::from_value(35), Score::Cp(35));
+    assert_eq!(Score::from_value(mate_in(1)), Score::Mate(1));
+    assert_eq!(Score::from_value(mate_in(3)), Score::Mate(2));
+    assert_eq!(Score::from_value(mated_in(2)), Score::Mate(-1));
+    assert_eq!(Score::from_value(mated_in(4)), Score::Mate(-2));
+
+    assert_eq!(Score::Cp(-120).to_string(), "cp -120");
+    assert_eq!(Score::Mate(-2).to_string(), "mate -2");
+}
+
+#[test]
+fn go_mate() {
+    let fen = "r6k/1p4pp/7N/3Q4/8/8/8/6K1 w - - 0 1";
+    assert_eq!(find_mate(fen, 2, false), ("d5g8".to_owned(), mate_in(3)));
+    assert_eq!(find_mate(fen, 2, true), ("d5g8".to_owned(), mate_in(3)));
+}
+
+#[test]
+fn checks_only_mate() {
+    // 1. Ng6+ hxg6 2. Qh3#, found before the longer smothered mate
+    let fen = "2r4k/6pp/8/4N3/8/1Q6/B5PP/7K w - - 0 1";
+    assert_eq!(find_mate(fen, 4, true), ("e5g6".to_owned(), mate_in(3)));
+    assert_eq!(find_mate(fen, 4, false), ("e5g6".to_owned(), mate_in(3)));
+}
+
+#[test]
+fn three_check() {
+    // The third check wins, so the rook gives it instead of the queen taking
+    let fen: Fen = "k7/p7/1p6/8/3r4/8/8/3Q2KR w - - 0 1 +2+0".parse().unwrap();
+    let mut pos = Position::from_fen_variant(&fen, Variant::ThreeCheck).unwrap();
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let mut search = search(&mut pos, &table, signals, limits(3), &[]);
+    let best = search.run().unwrap();
+    assert_eq!(best.mv.to_string(), "h1h8");
+    assert_eq!(search.lines[0].score, mate_in(1));
+
+    assert_eq!(best_move(&fen.to_string(), 3, &[]).unwrap(), "d1d4");
+}
+
+#[test]
+fn king_of_the_hill() {
+    // The king reaches the center before Black's can
+    let fen: Fen = "8/8/8/8/8/8/4K3/k7 w - - 0 1".parse().unwrap();
+    let mut pos = Position::from_fen_variant(&fen, Variant::KingOfTheHill).unwrap();
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let mut search = search(&mut pos, &table, signals, limits(4), &[]);
+    let best = search.run().unwrap();
+    assert_eq!(search.lines[0].score, mate_in(3));
+    assert!(best.mv.dst().center_distance() == 1, "{}", best.mv);
+}
+
+fn depth_stats(depth: u32, nodes: u64) -> DepthStats {
+    DepthStats { depth, nodes, tt_probes: nodes, tt_hits: nodes / 2, ..Default::default() }
+}
+
+#[test]
+fn stats() {
+    let stats = SearchStats { depths: vec![depth_stats(1, 10), depth_stats(2, 40), depth_stats(3, 90)] };
+    assert_eq!(stats.nodes(), 140);
+    assert_eq!(stats.branching_factor(0), None);
+    assert_eq!(stats.branching_factor(1), Some(4.0));
+    assert!((stats.average_branching_factor().unwrap() - 3.0).abs() < 1e-9);
+    assert_eq!(stats.depths[1].tt_hit_rate(), 0.5);
+    assert_eq!(stats.depths[1].null_move_rate(), 0.0);
+
+    let json = SearchStats { depths: vec![depth_stats(1, 10), depth_stats(2, 40)] }.to_json();
+    assert!(json.starts_with("{\"nodes\":50,\"branching_factor\":4.0000,\"depths\":[{\"depth\":1,"));
+    assert!(json.contains("\"tt_hit_rate\":0.5000"));
+    assert!(json.contains("\"branching_factor\":null}"));
+    assert!(json.ends_with("\"branching_factor\":4.0000}]}"));
+    assert_eq!(SearchStats::default().to_json(), "{\"nodes\":0,\"branching_factor\":null,\"depths\":[]}");
+}
+
+#[test]
+fn collect_stats() {
+    let mut pos = Position::default();
+    let table = Table::new(1);
+    let flag = AtomicBool::new(false);
+    let signals = Signals { halt: &flag, kill: &flag, ponder: &flag };
+
+    let mut search = search(&mut pos, &table, signals, limits(5), &[]);
+    search.collect_stats();
+    search.run().unwrap();
+    let nodes = search.nodes;
+    let stats = search.take_stats().unwrap();
+
+    let depths: Vec<u32> = stats.depths.iter().map(|d| d.depth).collect();
+    assert_eq!(depths, [1, 2, 3, 4, 5]);
+    assert_eq!(stats.nodes(), nodes);
+    for d in stats.depths.iter() {
+        assert!(d.qnodes <= d.nodes);
+        assert!(d.tt_hits <= d.tt_probes);
+        assert!(d.first_move_cutoffs <= d.cutoffs);
+        assert!(d.null_cutoffs <= d.null_moves);
+        assert!(d.re_searches <= d.reductions);
+    }
+    assert!(stats.depths[4].cutoffs > 0);
+    assert!(search.take_stats().is_none());
+}
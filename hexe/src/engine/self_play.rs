@@ -0,0 +1,280 @@
+//! Self-play games on an [`Engine`](../struct.Engine.html)'s thread pool.
+
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::*;
+use core::color::Color;
+use core::mv::{Matches, Move, MoveVec};
+use parking_lot::Mutex;
+use datagen::{Config, Sample};
+use engine::search::{Search, Signals, MAX_PLY, TB_WIN};
+use engine::thread::Job;
+use position::Position;
+use table::Table;
+
+/// Scores at or beyond this are forced mates or tablebase wins, which end the
+/// game.
+const DECISIVE: i32 = TB_WIN - MAX_PLY as i32;
+
+/// A xorshift64* generator for choosing opening moves.
+struct Rng(u64);
+
+impl Rng {
+    /// Creates the generator of game `index`, mixing it with `seed` so that
+    /// nearby games get unrelated sequences.
+    fn new(seed: u64, index: u64) -> Rng {
+        let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Plays games of `config`, taking the index of each from `next`, until
+/// there are none left or the thread is signaled to finish.
+pub(crate) fn play_games(config: &Config,
+                         next: &Mutex<u64>,
+                         samples: &Sender<Vec<Sample>>,
+                         signals: Signals,
+                         network: Option<Arc<Network>>,
+                         tablebase: Arc<Tablebase>)
+{
+    let mut table = Table::new(config.hash_size);
+    loop {
+        let index = {
+            let mut next = next.lock();
+            *next += 1;
+            *next - 1
+        };
+        if index >= config.games {
+            return;
+        }
+        // Each game starts from a clear table for reproducible results
+        table.clear();
+
+        let game = match play(index, config, &table, signals, &network, &tablebase) {
+            Some(game) => game,
+            None => return,
+        };
+        // The receiver may no longer be interested
+        if samples.send(game).is_err() {
+            return;
+        }
+    }
+}
+
+/// Plays game `index`, returning its samples or `None` if it was stopped.
+fn play(index: u64,
+        config: &Config,
+        table: &Table,
+        signals: Signals,
+        network: &Option<Arc<Network>>,
+        tablebase: &Arc<Tablebase>) -> Option<Vec<Sample>>
+{
+    let mut rng = Rng::new(config.seed, index);
+    let mut pos = opening(&mut rng, config.random_plies);
+    pos.set_network(network.clone());
+
+    let limits = Limits {
+        ponder: false,
+        infinite: false,
+        moves_to_go: 0,
+        time: [0; 2],
+        inc: [0; 2],
+        depth: config.depth,
+        nodes: config.nodes,
+        mate: 0,
+        move_time: 0,
+        move_overhead: 0,
+        multi_pv: 1,
+        checks_only: false,
+    };
+
+    let mut samples = Vec::new();
+    let mut plies = 0;
+    let result = loop {
+        match pos.outcome() {
+            Some(outcome) => match outcome.winner() {
+                Some(winner) => break win(winner),
+                None => break 1,
+            },
+            None if plies >= config.max_plies => break 1,
+            None => {},
+        }
+
+        let (best, score) = {
+            let mut search = Search::new(&mut pos, table, signals, limits, &[]);
+            search.set_output(Output::from_fn(|_| {}));
+            search.set_tablebase(tablebase.clone());
+            let best = search.run();
+            (best, search.score())
+        };
+        if signals.halt.load(Ordering::Relaxed) || signals.kill.load(Ordering::Relaxed) {
+            return None;
+        }
+        let (mv, score) = match (best, score) {
+            (Some(best), Some(score)) => (best.mv, score),
+            _ => return None,
+        };
+
+        let player = pos.player();
+        let score = match player {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        if score.abs() >= DECISIVE {
+            break if score > 0 { 2 } else { 0 };
+        }
+        if !pos.in_check() && !pos.is_capture(mv) && !is_promotion(mv) {
+            samples.push(Sample { fen: pos.to_fen(), score: score as i16, result: 1 });
+        }
+
+        pos.make_move(mv);
+        plies += 1;
+    };
+
+    for sample in samples.iter_mut() {
+        sample.result = result;
+    }
+    Some(samples)
+}
+
+/// Returns a position after `plies` random legal moves that has not ended.
+fn opening(rng: &mut Rng, plies: u32) -> Position {
+    let mut moves = MoveVec::new();
+    'retry: loop {
+        let mut pos = Position::default();
+        for _ in 0..plies {
+            moves.clear();
+            pos.gen(&mut moves).legal();
+            if moves.is_empty() {
+                continue 'retry;
+            }
+            let mv = moves[rng.below(moves.len())];
+            pos.make_move(mv);
+        }
+        if pos.outcome().is_none() {
+            return pos;
+        }
+    }
+}
+
+fn win(color: Color) -> u8 {
+    match color {
+        Color::White => 2,
+        Color::Black => 0,
+    }
+}
+
+fn is_promotion(mv: Move) -> bool {
+    match mv.matches() {
+        Matches::Promotion(_) => true,
+        _ => false,
+    }
+}
+
+impl Engine {
+    /// Plays the self-play games of `config` across the thread pool,
+    /// finishing any running searches first.
+    ///
+    /// The samples of each game are sent once it ends, in no particular
+    /// order. The channel disconnects once all games are played or
+    /// [`halt_all`](#method.halt_all) is called, discarding the games in
+    /// progress.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` limits neither the depth nor the nodes searched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::datagen::Config;
+    /// use hexe::engine::Engine;
+    ///
+    /// let engine = Engine::builder().num_threads(2).build();
+    /// let config = Config { games: 2, depth: 2, nodes: 0, max_plies: 20, ..Default::default() };
+    ///
+    /// for game in engine.self_play(config) {
+    ///     for sample in game {
+    ///         println!("{}", sample);
+    ///     }
+    /// }
+    /// ```
+    pub fn self_play(&self, config: Config) -> Receiver<Vec<Sample>> {
+        assert!(config.depth != 0 || config.nodes != 0,
+                "self-play requires a depth or node limit");
+
+        self.halt_all();
+        self.wait_idle();
+
+        let (tx, rx) = mpsc::channel();
+        let config = Arc::new(config);
+        let next = Arc::new(Mutex::new(0));
+        for _ in 0..self.num_threads() {
+            self.pool.enqueue(Job::SelfPlay {
+                config: config.clone(),
+                next: next.clone(),
+                samples: tx.clone(),
+            });
+        }
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(threads: usize, config: &Config) -> Vec<Vec<Sample>> {
+        let engine = Engine::builder().num_threads(threads).build();
+        engine.self_play(config.clone()).iter().collect()
+    }
+
+    #[test]
+    fn self_play() {
+        let config = Config {
+            games: 4,
+            depth: 2,
+            nodes: 0,
+            max_plies: 16,
+            seed: 7,
+            ..Default::default()
+        };
+        let games_a = games(2, &config);
+        assert_eq!(games_a.len(), 4);
+
+        for game in &games_a {
+            for sample in game {
+                let pos = sample.position().unwrap();
+                assert!(!pos.in_check());
+                assert_eq!(sample.result, game[0].result);
+                assert!((sample.score as i32).abs() < DECISIVE);
+            }
+        }
+
+        // Games depend only on the seed and their index
+        let keys = |games: Vec<Vec<Sample>>| {
+            let mut keys: Vec<String> = games.iter().map(|game| {
+                game.iter().map(|sample| sample.to_string()).collect::<Vec<_>>().join("\n")
+            }).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(games_a), keys(games(1, &config)));
+    }
+}
@@ -1,12 +1,20 @@
 use std::thread::{self, JoinHandle};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
 
 use crossbeam_deque::{Deque, Stealer, Steal};
-use parking_lot::{Condvar, Mutex};
+use parking_lot::{Condvar, Mutex, RwLock};
 
 use core::mv::Move;
-use engine::Limits;
+use datagen::{Config, Sample};
+use engine::{Limits, Output};
+use engine::handle::Listener;
+use engine::search::{BestMove, Search, Signals};
+use engine::self_play;
 use position::Position;
+use nnue::Network;
+use syzygy::Tablebase;
 use table::Table;
 use util::AnySend;
 
@@ -50,8 +58,32 @@ pub struct Shared {
     stop_cond: Condvar,
     stop_mutex: Mutex<()>,
 
+    /// Signals running searches to report their best move and finish.
+    halt: AtomicBool,
+
+    /// Set while the running search ponders; cleared upon "ponderhit".
+    ponder: AtomicBool,
+
+    /// The number of jobs that have been enqueued but not yet finished.
+    pending: Mutex<usize>,
+    /// The condition variable for `pending` reaching zero.
+    idle_cond: Condvar,
+
+    /// Where searches write their results.
+    pub output: RwLock<Output>,
+
+    /// Where searches without a listener write their statistics, if they
+    /// are collected.
+    pub stats: RwLock<Option<Output>>,
+
     /// The transposition table.
     pub table: Table,
+
+    /// The endgame tablebases probed by searches.
+    pub tablebase: RwLock<Arc<Tablebase>>,
+
+    /// The network evaluating searched positions, if any.
+    pub network: RwLock<Option<Arc<Network>>>,
 }
 
 impl Shared {
@@ -59,8 +91,47 @@ impl Shared {
     pub fn stop(&self) {
         trace!("Stopping all threads");
         self.stop.store(true, Ordering::SeqCst);
+        self.notify_empty();
+    }
+
+    /// Wakes up all threads waiting on an empty deque.
+    ///
+    /// The lock is held while notifying so that a thread cannot miss the
+    /// wakeup between checking its wait condition and waiting.
+    fn notify_empty(&self) {
+        let _guard = self.empty_mutex.lock();
         self.empty_cond.notify_all();
     }
+
+    /// Signals running searches to finish as soon as possible.
+    pub fn halt(&self) {
+        trace!("Halting all searches");
+        self.halt.store(true, Ordering::SeqCst);
+    }
+
+    /// Has a pondering search start its clock.
+    pub fn ponder_hit(&self) {
+        trace!("Ponder hit");
+        self.ponder.store(false, Ordering::SeqCst);
+    }
+
+    /// Marks an enqueued job as finished.
+    fn finish_job(&self) {
+        let mut pending = self.pending.lock();
+        *pending -= 1;
+        if *pending == 0 {
+            self.idle_cond.notify_all();
+        }
+    }
+}
+
+/// Marks a stolen job as finished when dropped, however its execution ends.
+struct FinishJob<'a>(&'a Shared);
+
+impl<'a> Drop for FinishJob<'a> {
+    fn drop(&mut self) {
+        self.0.finish_job();
+    }
 }
 
 #[cfg(test)]
@@ -70,6 +141,16 @@ pub enum Job {
     Search {
         limits: Limits,
         moves: Box<[Move]>,
+        position: Position,
+        /// Receives the results in place of the engine's output.
+        listener: Option<Listener>,
+    },
+    SelfPlay {
+        config: Arc<Config>,
+        /// The index of the next game to play, shared by all threads.
+        next: Arc<Mutex<u64>>,
+        /// Receives the samples of each game.
+        samples: Sender<Vec<Sample>>,
     },
 }
 
@@ -124,13 +205,19 @@ impl<'ctx> Context<'ctx> {
                 trace!("Thread {} found empty deque", self.thread);
                 let mut guard = self.shared.empty_mutex.lock();
 
-                trace!("Thread {} now waiting", self.thread);
-                self.shared.empty_cond.wait(&mut guard);
+                // Jobs and interrupts are signaled while holding the lock
+                if self.jobs.is_empty() && self.interrupt().is_ok() {
+                    trace!("Thread {} now waiting", self.thread);
+                    self.shared.empty_cond.wait(&mut guard);
+                }
 
                 trace!("Thread {} finished waiting", self.thread);
                 Ok(())
             },
-            Steal::Data(job) => self.execute(job),
+            Steal::Data(job) => {
+                let _finish = FinishJob(self.shared);
+                self.execute(job)
+            },
             Steal::Retry => Ok(()),
         }
     }
@@ -141,8 +228,76 @@ impl<'ctx> Context<'ctx> {
         self.interrupt()?;
 
         match job {
-            Job::Search { limits, moves } => {
+            Job::Search { limits, moves, position, listener } => {
                 trace!("Thread {} is now searching", self.thread);
+                self.position = position;
+                self.position.set_network(self.shared.network.read().clone());
+
+                let signals = Signals {
+                    halt: &self.shared.halt,
+                    kill: &self.worker.kill,
+                    ponder: &self.shared.ponder,
+                };
+                let out = self.shared.output.read().clone();
+                let mut search = Search::new(
+                    &mut self.position,
+                    &self.shared.table,
+                    signals,
+                    limits,
+                    &moves,
+                );
+                search.set_output(out.clone());
+                search.set_tablebase(self.shared.tablebase.read().clone());
+                let stats_out = match listener {
+                    Some(ref listener) => {
+                        search.set_listener(listener.info.clone());
+                        None
+                    },
+                    None => self.shared.stats.read().clone(),
+                };
+                if listener.as_ref().map_or(stats_out.is_some(), |l| l.stats.is_some()) {
+                    search.collect_stats();
+                }
+
+                let best = search.run();
+                let search_stats = search.take_stats();
+                if let Some(listener) = listener {
+                    // The handle may have been dropped
+                    if let (Some(tx), Some(stats)) = (listener.stats, search_stats) {
+                        let _ = tx.send(stats);
+                    }
+                    let _ = listener.best.send(best);
+                } else {
+                    if let (Some(out), Some(stats)) = (stats_out, search_stats) {
+                        writeln!(out, "{}", stats.to_json());
+                    }
+                    match best {
+                        Some(BestMove { mv, ponder: Some(ponder) }) => {
+                            writeln!(out, "bestmove {} ponder {}", mv, ponder);
+                        },
+                        Some(BestMove { mv, ponder: None }) => {
+                            writeln!(out, "bestmove {}", mv);
+                        },
+                        None => writeln!(out, "bestmove (none)"),
+                    }
+                }
+            },
+            Job::SelfPlay { config, next, samples } => {
+                trace!("Thread {} is now playing games", self.thread);
+                let ponder = AtomicBool::new(false);
+                let signals = Signals {
+                    halt: &self.shared.halt,
+                    kill: &self.worker.kill,
+                    ponder: &ponder,
+                };
+                self_play::play_games(
+                    &config,
+                    &next,
+                    &samples,
+                    signals,
+                    self.shared.network.read().clone(),
+                    self.shared.tablebase.read().clone(),
+                );
             },
         }
 
@@ -172,8 +327,11 @@ impl<'ctx> Context<'ctx> {
         trace!("Thread {} should stop", self.thread);
         let mut guard = self.shared.stop_mutex.lock();
 
-        info!("Thread {} will stop now", self.thread);
-        self.shared.stop_cond.wait(&mut guard);
+        // Resuming is signaled while holding the lock
+        if self.interrupt().is_err() {
+            info!("Thread {} will stop now", self.thread);
+            self.shared.stop_cond.wait(&mut guard);
+        }
     }
 }
 
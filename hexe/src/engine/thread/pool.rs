@@ -62,8 +62,11 @@ impl Pool {
         }
 
         // Wake up anyone who might have been erm... killed?
-        self.shared.empty_cond.notify_all();
-        self.shared.stop_cond.notify_all();
+        self.shared.notify_empty();
+        {
+            let _guard = self.shared.stop_mutex.lock();
+            self.shared.stop_cond.notify_all();
+        }
 
         for thread in self.threads.drain(n..) {
             if thread.handle.join().is_err() {
@@ -72,12 +75,6 @@ impl Pool {
         }
     }
 
-    /// Adds `n` number of threads to the pool.
-    pub fn add_threads(&mut self, n: usize) {
-        let i = self.num_threads();
-        self.add_range(i..(i + n));
-    }
-
     fn add_range(&mut self, range: ops::Range<usize>) {
         self.threads.reserve(range.len());
 
@@ -112,14 +109,21 @@ impl Pool {
         self.threads.len()
     }
 
-    /// Stops what each thread is currently doing.
+    /// Stops what each thread is currently doing, discarding any jobs that
+    /// have yet to start.
     pub fn stop_all(&self) {
-        self.shared.stop()
+        self.shared.stop();
+        // Stopped threads never get to the queued jobs, which would otherwise
+        // keep the pool from becoming idle
+        while let Some(_) = self.jobs.pop() {
+            self.shared.finish_job();
+        }
     }
 
     /// Resumes all stopped threads.
     pub fn resume_all(&self) {
         trace!("Resuming all stopped threads");
+        let _guard = self.shared.stop_mutex.lock();
         self.shared.stop.store(false, Ordering::SeqCst);
         self.shared.stop_cond.notify_all();
     }
@@ -141,25 +145,61 @@ impl Pool {
             thread.worker.kill.store(true, Ordering::SeqCst);
         }
         // Wake up anyone sleeping
-        self.shared.empty_cond.notify_all();
+        self.shared.notify_empty();
         self.resume_all();
     }
 
     /// Returns a reference to the data shared by all threads.
     pub fn shared(&self) -> &Shared { &self.shared }
 
-    /// Returns a mutable reference to the data shared by all threads.
-    ///
-    /// # Safety
-    ///
-    /// The caller must ensure that no thread is currently accessing `Shared`,
-    /// such as with thread pool resizing.
-    pub unsafe fn shared_mut(&mut self) -> &mut Shared {
-        &mut self.shared
+    /// Signals all running searches to finish as soon as possible.
+    pub fn halt_all(&self) {
+        self.shared.halt();
+    }
+
+    /// Has a pondering search start its clock.
+    pub fn ponder_hit(&self) {
+        self.shared.ponder_hit();
+    }
+
+    /// Blocks the current thread until all enqueued jobs have finished.
+    pub fn wait_idle(&self) {
+        let mut pending = self.shared.pending.lock();
+        while *pending != 0 {
+            self.shared.idle_cond.wait(&mut pending);
+        }
+    }
+
+    /// Clears the transposition table after all enqueued jobs have finished.
+    pub fn clear_table(&mut self) {
+        self.wait_idle();
+        // Jobs are only enqueued through `self`, which is borrowed mutably, so
+        // every thread stays idle and none accesses the table
+        self.shared.table.clear();
+    }
+
+    /// Resizes the transposition table to `size_mb` megabytes, halting any
+    /// running searches and waiting for all enqueued jobs to finish first.
+    pub fn resize_table(&mut self, size_mb: usize) -> bool {
+        self.halt_all();
+        self.wait_idle();
+        // As with clearing, no thread accesses the table while idle
+        self.shared.table.resize(size_mb)
     }
 
     /// Enqueues the job to be executed.
     pub fn enqueue(&self, job: Job) {
+        *self.shared.pending.lock() += 1;
+        self.shared.halt.store(false, Ordering::SeqCst);
+
+        match job {
+            Job::Search { ref limits, .. } => {
+                self.shared.ponder.store(limits.ponder, Ordering::SeqCst);
+            },
+            Job::SelfPlay { .. } => {},
+        }
+
+        let _guard = self.shared.empty_mutex.lock();
         self.jobs.push(job);
         self.shared.empty_cond.notify_one();
     }
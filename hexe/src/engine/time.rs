@@ -0,0 +1,235 @@
+//! Time management for searches with a clock.
+
+use std::cmp;
+
+use core::color::Color;
+use core::mv::Move;
+use engine::Limits;
+
+/// The number of moves that remaining time is spread over when the number of
+/// moves until the next time control is not known.
+const DEFAULT_MOVES_TO_GO: u64 = 40;
+
+/// The maximum number of moves that remaining time is spread over.
+const MAX_MOVES_TO_GO: u64 = 50;
+
+/// The factor by which the maximum time may exceed the optimum time.
+const MAX_RATIO: u64 = 5;
+
+/// The number of consecutive iterations with the same best move after which
+/// it is considered stable.
+const STABLE_ITERATIONS: u32 = 5;
+
+/// Computes how long a search may take and decides when to stop it.
+///
+/// The **optimum** time is spent on a typical move. It is extended when the
+/// score drops or the best move keeps changing, and shortened when the best
+/// move is stable. The search never exceeds the **maximum** time.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    optimum: u64,
+    maximum: u64,
+    /// Whether the time is fixed by "movetime" and should be used in full.
+    fixed: bool,
+    prev_best: Option<Move>,
+    prev_score: Option<i32>,
+    /// The number of best move changes, halved after each iteration.
+    instability: f64,
+    /// The factor by which the optimum time is scaled due to score drops.
+    score_factor: f64,
+    stable: u32,
+}
+
+impl TimeManager {
+    /// Creates a time manager for `player` from `limits`, returning `None` if
+    /// the search is not limited by time.
+    pub fn new(limits: &Limits, player: Color) -> Option<TimeManager> {
+        if limits.infinite || limits.ponder {
+            return None;
+        }
+
+        let overhead = limits.move_overhead as u64;
+
+        if limits.move_time != 0 {
+            let time = cmp::max(1, (limits.move_time as u64).saturating_sub(overhead));
+            return Some(TimeManager::with_times(time, time, true));
+        }
+
+        let time = limits.time[player as usize] as u64;
+        if time == 0 {
+            return None;
+        }
+        let inc = limits.inc[player as usize] as u64;
+
+        let moves_to_go = match limits.moves_to_go as u64 {
+            0 => DEFAULT_MOVES_TO_GO,
+            n => cmp::min(n, MAX_MOVES_TO_GO),
+        };
+
+        // Reserve the overhead for every move until the next time control
+        let available = time.saturating_sub(overhead * cmp::min(moves_to_go, 10));
+        let available = cmp::max(available, time / 10);
+
+        let safe = available * 8 / 10;
+        let maximum = cmp::max(1, cmp::min(
+            (available / moves_to_go + inc * 3 / 4) * MAX_RATIO,
+            safe,
+        ));
+        let optimum = cmp::max(1, cmp::min(
+            available / moves_to_go + inc * 3 / 4,
+            maximum,
+        ));
+
+        Some(TimeManager::with_times(optimum, maximum, false))
+    }
+
+    fn with_times(optimum: u64, maximum: u64, fixed: bool) -> TimeManager {
+        TimeManager {
+            optimum,
+            maximum,
+            fixed,
+            prev_best: None,
+            prev_score: None,
+            instability: 0.0,
+            score_factor: 1.0,
+            stable: 0,
+        }
+    }
+
+    /// Returns the time in milliseconds that a typical move should take.
+    #[inline]
+    pub fn optimum(&self) -> u64 {
+        self.optimum
+    }
+
+    /// Returns the time in milliseconds that the search may never exceed.
+    #[inline]
+    pub fn maximum(&self) -> u64 {
+        self.maximum
+    }
+
+    /// Records the best move and score of a completed iteration.
+    pub fn update(&mut self, best: Move, score: i32) {
+        self.instability /= 2.0;
+        if self.prev_best == Some(best) {
+            self.stable += 1;
+        } else {
+            if self.prev_best.is_some() {
+                self.instability += 1.0;
+            }
+            self.stable = 0;
+        }
+
+        self.score_factor = match self.prev_score {
+            // Spend up to twice as long when the score drops
+            Some(prev) if score < prev => {
+                1.0 + cmp::min(prev - score, 100) as f64 / 100.0
+            },
+            _ => 1.0,
+        };
+
+        self.prev_best = Some(best);
+        self.prev_score = Some(score);
+    }
+
+    /// Returns the time in milliseconds after which no new iteration should be
+    /// started.
+    pub fn target(&self) -> u64 {
+        if self.fixed {
+            return self.maximum;
+        }
+        let mut scale = self.score_factor * (1.0 + self.instability);
+        if self.stable >= STABLE_ITERATIONS {
+            scale /= 2.0;
+        }
+        let target = (self.optimum() as f64 * scale) as u64;
+        cmp::min(target, self.maximum)
+    }
+
+    /// Returns whether the search should stop after completing an iteration,
+    /// `elapsed` milliseconds after it started.
+    ///
+    /// A search with a single legal move stops after its first iteration.
+    pub fn should_stop(&self, elapsed: u64, single_move: bool) -> bool {
+        single_move || elapsed >= self.target()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::square::Square;
+
+    fn limits(time: u32, inc: u32, moves_to_go: u32) -> Limits {
+        Limits {
+            time: [time, time],
+            inc: [inc, inc],
+            moves_to_go,
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn unlimited() {
+        let infinite = Limits { infinite: true, ..limits(1000, 0, 0) };
+        let ponder = Limits { ponder: true, ..limits(1000, 0, 0) };
+        assert!(TimeManager::new(&Limits::default(), Color::White).is_none());
+        assert!(TimeManager::new(&infinite, Color::White).is_none());
+        assert!(TimeManager::new(&ponder, Color::White).is_none());
+    }
+
+    #[test]
+    fn move_time() {
+        let limits = Limits { move_time: 500, move_overhead: 20, ..Limits::default() };
+        let tm = TimeManager::new(&limits, Color::Black).unwrap();
+        assert_eq!(tm.optimum(), 480);
+        assert_eq!(tm.maximum(), 480);
+        assert!(!tm.should_stop(100, false));
+    }
+
+    #[test]
+    fn within_bounds() {
+        for &(time, inc, mtg) in &[(60_000, 0, 0), (1_000, 100, 0), (10_000, 0, 1), (50, 0, 0)] {
+            let tm = TimeManager::new(&limits(time, inc, mtg), Color::White).unwrap();
+            assert!(tm.optimum() <= tm.maximum());
+            assert!(tm.maximum() < time as u64, "{} {} {}", time, inc, mtg);
+        }
+    }
+
+    #[test]
+    fn overhead() {
+        let plain = TimeManager::new(&limits(10_000, 0, 0), Color::White).unwrap();
+        let slow = Limits { move_overhead: 100, ..limits(10_000, 0, 0) };
+        let slow = TimeManager::new(&slow, Color::White).unwrap();
+        assert!(slow.optimum() < plain.optimum());
+    }
+
+    #[test]
+    fn adjusts_target() {
+        let mut tm = TimeManager::new(&limits(60_000, 0, 0), Color::White).unwrap();
+        let a = Move::normal(Square::E2, Square::E4);
+        let b = Move::normal(Square::D2, Square::D4);
+
+        tm.update(a, 20);
+        let base = tm.target();
+
+        // Unstable best move
+        tm.update(b, 20);
+        assert!(tm.target() > base);
+
+        // Score drop
+        let mut drop = TimeManager::new(&limits(60_000, 0, 0), Color::White).unwrap();
+        drop.update(a, 20);
+        drop.update(a, -60);
+        assert!(drop.target() > base);
+
+        // Stable best move
+        let mut stable = TimeManager::new(&limits(60_000, 0, 0), Color::White).unwrap();
+        for _ in 0..(STABLE_ITERATIONS + 1) {
+            stable.update(a, 20);
+        }
+        assert!(stable.target() < base);
+        assert!(stable.should_stop(base, false));
+        assert!(stable.should_stop(0, true));
+    }
+}
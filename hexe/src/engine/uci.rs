@@ -3,11 +3,19 @@ use super::*;
 use std::io::{self, BufRead};
 use std::mem;
 use std::str;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use parking_lot::Mutex;
 
 use core::color::Color;
-use core::mv::Move;
+use core::fen::Fen;
+use core::mv::{Move, MoveVec};
 use engine::Limits;
+use engine::option::{Error as OptionError, Registry};
 use engine::thread::Job;
+use eval;
+use position::Position;
 
 const WHITE: usize = Color::White as usize;
 const BLACK: usize = Color::Black as usize;
@@ -21,7 +29,7 @@ macro_rules! id {
 }
 
 macro_rules! unknown_command {
-    ($cmd:expr) => { println!("Unknown command: {}", $cmd) }
+    ($out:expr, $cmd:expr) => { writeln!($out, "Unknown command: {}", $cmd) }
 }
 
 impl Default for Limits {
@@ -39,6 +47,12 @@ type UciIter<'a> = str::SplitWhitespace<'a>;
 pub struct Uci<'a> {
     engine: &'a mut Engine,
 
+    /// The position set by the last "position" command.
+    position: Position,
+
+    /// The options reported by "uci" and set by "setoption".
+    options: Registry,
+
     // Reusable string buffers
     string_buf_0: String,
     string_buf_1: String,
@@ -47,8 +61,11 @@ pub struct Uci<'a> {
 impl<'a> From<&'a mut Engine> for Uci<'a> {
     #[inline]
     fn from(engine: &'a mut Engine) -> Uci<'a> {
+        let options = Registry::from(&*engine);
         Uci {
             engine,
+            position: Position::default(),
+            options,
             string_buf_0: String::new(),
             string_buf_1: String::new(),
         }
@@ -115,22 +132,25 @@ impl<'a> Uci<'a> {
     {
         info!("Starting UCI from iterator");
         for line in commands {
-            self.run(line.as_ref());
+            if !self.run_lines(line.as_ref()) {
+                break;
+            }
         }
     }
 
     /// Runs a single UCI command or multiple if newlines are found.
     #[inline]
     pub fn run(&mut self, command: &str) {
+        self.run_lines(command);
+    }
+
+    /// Runs each line of `command`, returning `false` upon "quit".
+    fn run_lines(&mut self, command: &str) -> bool {
         if command.is_empty() {
-            unknown_command!(command);
-        } else {
-            for line in command.lines() {
-                if !self.run_line(line) {
-                    break;
-                }
-            }
+            unknown_command!(self.engine.output(), command);
+            return true;
         }
+        command.lines().all(|line| self.run_line(line))
     }
 
     fn run_line(&mut self, line: &str) -> bool {
@@ -146,39 +166,80 @@ impl<'a> Uci<'a> {
             "setoption"  => self.cmd_set_option(split),
             "ucinewgame" => self.cmd_new_game(),
             "go"         => self.cmd_go(split),
-            "isready"    => println!("readyok"),
+            "bench"      => self.cmd_bench(split),
+            "eval"       => self.cmd_eval(),
+            "d"          => self.cmd_display(split),
+            "isready"    => writeln!(self.engine.output(), "readyok"),
             "resume"     => self.engine.resume_all(),
-            _            => unknown_command!(line),
+            _            => unknown_command!(self.engine.output(), line),
         }
         true
     }
 
-    fn report_options(&self) {
-        println!(
-            "\noption name Threads type spin default {0} min 1 max {1}\
-             \noption name Hash type spin default 1 min 1 max {1}",
-            ::num_cpus::get(),
-            usize::MAX,
-        );
-    }
-
     fn cmd_uci(&self) {
-        println!(id!(name));
-        println!(id!(authors));
-        self.report_options();
-        println!("uciok");
+        let out = self.engine.output();
+        writeln!(out, id!(name));
+        writeln!(out, id!(authors));
+        write!(out, "\n{}", self.options);
+        writeln!(out, "uciok");
     }
 
     fn cmd_stop(&mut self) {
-        self.engine.stop_all();
+        self.engine.halt_all();
     }
 
     fn cmd_ponder_hit(&mut self) {
-        unimplemented!();
+        self.engine.ponder_hit();
     }
 
-    fn cmd_position(&mut self, _: UciIter) {
-        unimplemented!();
+    fn cmd_position(&mut self, mut iter: UciIter) {
+        let kind = iter.next().unwrap_or("");
+
+        let fen = &mut self.string_buf_0;
+        fen.clear();
+
+        while let Some(next) = iter.next() {
+            if next == "moves" {
+                break;
+            }
+            if !fen.is_empty() {
+                fen.push(' ');
+            }
+            fen.push_str(next);
+        }
+
+        let variant = self.engine.variant();
+        let position = match kind {
+            "startpos" => Position::from_fen_variant(&Fen::STANDARD, variant).unwrap(),
+            "fen" => match fen.parse::<Fen>() {
+                Ok(fen) => match Position::from_fen_variant(&fen, variant) {
+                    Some(position) => position,
+                    None => {
+                        error!("Invalid position: \"{}\"", fen);
+                        return;
+                    },
+                },
+                Err(e) => {
+                    parse_error!(fen, e);
+                    return;
+                },
+            },
+            _ => {
+                error!("Expected \"startpos\" or \"fen\", found \"{}\"", kind);
+                return;
+            },
+        };
+        self.position = position;
+
+        for next in iter {
+            match self.cmd_read_move(next) {
+                Some(mv) => self.position.make_move(mv),
+                None => {
+                    error!("Illegal move: \"{}\"", next);
+                    return;
+                },
+            }
+        }
     }
 
     fn cmd_set_option(&mut self, mut iter: UciIter) {
@@ -212,47 +273,33 @@ impl<'a> Uci<'a> {
             value.push_str(next);
         }
 
-        // Performs a case-insensitive check against the option
-        let match_option = |opt: &str| {
-            ::util::matches_lower_alpha(opt.as_ref(), name.as_ref())
-        };
-
         debug!("Setting UCI option \"{}\" to \"{}\"", name, value);
 
-        macro_rules! parse {
-            ($($x:ident @ $s:expr => $b:expr,)+ _ => $c:expr,) => {
-                $(if match_option($s) {
-                    match value.parse() {
-                        Ok($x) => $b,
-                        Err(e) => { parse_error!(value, e); },
-                    }
-                } else)+ { $c }
-            }
-        }
-
-        parse! {
-            threads @ "threads" => {
-                if !self.engine.set_threads(threads) {
-                    error!("Cannot set thread count to {}", threads);
-                }
+        match self.options.set(self.engine, name, value) {
+            Ok(()) => {},
+            Err(OptionError::Unknown) => {
+                writeln!(self.engine.output(), "No such option: {}", name);
             },
-            hash @ "hash" => {
-                if !self.engine.set_hash_size(hash) {
-                    error!("Cannot set table size to {}", hash);
-                }
+            Err(e) => {
+                error!("Cannot set option \"{}\" to \"{}\": {}", name, value, e);
             },
-            _ => println!("No such option: {}", name),
         }
     }
 
     fn cmd_new_game(&mut self) {
-        unimplemented!();
+        self.engine.halt_all();
+        self.engine.clear_hash();
+        self.position = Position::from_fen_variant(&Fen::STANDARD, self.engine.variant()).unwrap();
     }
 
     fn cmd_go(&mut self, mut iter: UciIter) {
         let mut limits = Limits::default();
         let mut moves  = Vec::<Move>::new();
 
+        limits.move_overhead = self.engine.move_overhead();
+        limits.multi_pv = self.engine.multi_pv();
+        limits.checks_only = self.engine.checks_only_mate();
+
         macro_rules! update {
             ($val:expr) => {
                 if let Some(Ok(val)) = iter.next().map(str::parse) {
@@ -286,12 +333,303 @@ impl<'a> Uci<'a> {
         self.cmd_start_thinking(limits, moves.into());
     }
 
+    /// Runs `Engine::bench`, with "depth", "hash" and "threads" overriding
+    /// the defaults until it finishes.
+    fn cmd_bench(&mut self, mut iter: UciIter) {
+        let (prev_hash, prev_threads) = (self.engine.hash_size(), self.engine.num_threads());
+        let mut depth = bench::DEFAULT_DEPTH;
+        let mut hash = prev_hash;
+        let mut threads = 1;
+
+        while let Some(next) = iter.next() {
+            match (next, iter.next().and_then(|val| val.parse::<usize>().ok())) {
+                ("depth", Some(n)) if n > 0 => depth = n as u32,
+                ("hash", Some(n)) if n > 0 && n <= MAX_TABLE_SIZE => hash = n,
+                ("threads", Some(n)) if n > 0 && n <= MAX_THREADS => threads = n,
+                _ => {},
+            }
+        }
+
+        if hash != prev_hash {
+            self.engine.set_hash_size(hash);
+        }
+        self.engine.set_threads(threads);
+        let bench = self.engine.bench(depth);
+        writeln!(self.engine.output(), "{}", bench);
+
+        self.engine.set_threads(prev_threads);
+        if hash != prev_hash {
+            self.engine.set_hash_size(prev_hash);
+        }
+    }
+
+    /// Writes the terms of the evaluation of the current position, using the
+    /// engine's network if one is loaded.
+    fn cmd_eval(&self) {
+        let mut position = self.position.clone();
+        position.set_network(self.engine.network());
+        writeln!(self.engine.output(), "{}", eval::trace(&position));
+    }
+
+    /// Writes the current position along with its legal moves, drawing
+    /// pieces as figurines if followed by "unicode".
+    fn cmd_display(&self, mut iter: UciIter) {
+        let mut moves = MoveVec::new();
+        self.position.gen(&mut moves).legal();
+
+        let mut text = if iter.next() == Some("unicode") {
+            format!("{:#}", self.position)
+        } else {
+            self.position.to_string()
+        };
+        text.push_str(&format!("\nLegal moves ({}):", moves.len()));
+        for mv in moves.iter() {
+            text.push(' ');
+            text.push_str(&mv.to_string());
+        }
+        writeln!(self.engine.output(), "{}", text);
+    }
+
     fn cmd_read_move(&self, s: &str) -> Option<Move> {
-        unimplemented!();
+        let mut moves = MoveVec::new();
+        self.position.gen(&mut moves).legal();
+        moves.iter().cloned().find(|mv| mv.to_string() == s)
     }
 
     fn cmd_start_thinking(&mut self, limits: Limits, moves: Box<[Move]>) {
-        let job = Job::Search { limits, moves };
+        // Only one search may report at a time
+        self.engine.halt_all();
+        self.engine.wait_idle();
+
+        let position = self.position.clone();
+        let job = Job::Search { limits, moves, position, listener: None };
         self.engine.pool.enqueue(job);
     }
 }
+
+/// Runs the UCI on its own thread, communicating through channels.
+///
+/// This allows for embedding the engine within a host application, which
+/// sends commands and receives responses asynchronously, including the "info"
+/// and "bestmove" lines of searches running on the engine's thread pool.
+///
+/// Dropping the driver sends "quit" and waits for the engine to shut down.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use hexe::engine::{Engine, UciDriver};
+///
+/// let mut builder = Engine::builder();
+/// builder.num_threads(1);
+///
+/// let driver = UciDriver::spawn(builder);
+/// driver.send("isready");
+/// assert_eq!(driver.recv().unwrap(), "readyok");
+/// ```
+pub struct UciDriver {
+    commands: Sender<String>,
+    responses: Receiver<String>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for UciDriver {
+    fn drop(&mut self) {
+        self.send("quit");
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("UCI thread panicked");
+            }
+        }
+    }
+}
+
+impl UciDriver {
+    /// Spawns a thread running the UCI for an engine built by `builder`.
+    pub fn spawn(builder: EngineBuilder) -> UciDriver {
+        let (commands, command_rx) = mpsc::channel::<String>();
+        let (response_tx, responses) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let engine = &mut builder.build();
+            let response_tx = Mutex::new(response_tx);
+            engine.set_output(Output::from_fn(move |line| {
+                // The host may have stopped listening
+                let _ = response_tx.lock().send(line.to_owned());
+            }));
+            engine.uci().start_with(command_rx);
+        });
+
+        UciDriver { commands, responses, handle: Some(handle) }
+    }
+
+    /// Sends `command` to the UCI, returning `false` if it has quit.
+    #[inline]
+    pub fn send(&self, command: &str) -> bool {
+        self.commands.send(command.to_owned()).is_ok()
+    }
+
+    /// Blocks until the next response line, returning `None` if the UCI has
+    /// quit.
+    #[inline]
+    pub fn recv(&self) -> Option<String> {
+        self.responses.recv().ok()
+    }
+
+    /// Returns the receiving end of response lines, for use with
+    /// `try_recv` and `recv_timeout`.
+    #[inline]
+    pub fn responses(&self) -> &Receiver<String> {
+        &self.responses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Receives lines until one starts with `prefix`, returning all of them.
+    fn recv_until(driver: &UciDriver, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = driver.responses()
+                             .recv_timeout(Duration::from_secs(30))
+                             .expect("No response");
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+
+    fn driver() -> UciDriver {
+        let mut builder = Engine::builder();
+        builder.num_threads(1);
+        UciDriver::spawn(builder)
+    }
+
+    #[test]
+    fn handshake() {
+        let driver = driver();
+        driver.send("uci");
+        let lines = recv_until(&driver, "uciok");
+        assert_eq!(lines[0], id!(name));
+        assert!(lines.iter().any(|line| line.starts_with("option name Threads")));
+
+        driver.send("isready");
+        assert_eq!(driver.recv().unwrap(), "readyok");
+
+        driver.send("frobnicate");
+        assert_eq!(driver.recv().unwrap(), "Unknown command: frobnicate");
+    }
+
+    #[test]
+    fn search() {
+        let driver = driver();
+        driver.send("position startpos moves e2e4");
+        driver.send("go depth 3");
+
+        let lines = recv_until(&driver, "bestmove");
+        assert!(lines[..lines.len() - 1].iter().all(|line| line.starts_with("info")));
+        assert!(lines.iter().any(|line| line.starts_with("info depth 3 ")));
+    }
+
+    #[test]
+    fn bench() {
+        let driver = driver();
+        driver.send("bench depth 1 hash 2 threads 2");
+
+        let lines = recv_until(&driver, "Nodes/second");
+        assert_eq!(lines.iter().filter(|line| line.starts_with("Position")).count(), 50);
+        assert!(lines.iter().any(|line| line.starts_with("Nodes searched")));
+
+        driver.send("uci");
+        let lines = recv_until(&driver, "uciok");
+        assert!(lines.iter().any(|line| line == "option name Threads type spin default 1 min 1 max 512"));
+    }
+
+    #[test]
+    #[allow(deprecated)] // `trim_start` requires Rust 1.30
+    fn eval() {
+        let driver = driver();
+        driver.send("position startpos moves e2e4");
+        driver.send("eval");
+
+        let lines = recv_until(&driver, "Final");
+        assert!(lines.iter().any(|line| line.trim_left().starts_with("Material |")));
+        assert!(lines.iter().any(|line| line == "Phase:          24/24"));
+        let fen: Fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".parse().unwrap();
+        let score = -::eval::evaluate(&Position::from_fen(&fen).unwrap());
+        assert_eq!(lines.last().unwrap(), &format!("Final:          {:+} (White)", score));
+    }
+
+    #[test]
+    fn display() {
+        let driver = driver();
+        driver.send("position startpos moves e2e4 e7e5 g1f3");
+        driver.send("d");
+
+        let lines = recv_until(&driver, "Legal moves");
+        assert_eq!(lines[1], " | r | n | b | q | k | b | n | r | 8");
+        assert!(lines.contains(&"Fen: rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2".to_owned()));
+        assert!(lines.contains(&"Polyglot key: D3207FEC0612D89D".to_owned()));
+        assert!(lines.contains(&"Side to move: Black".to_owned()));
+        assert!(lines.contains(&"Checkers: -".to_owned()));
+        assert!(lines.last().unwrap().starts_with("Legal moves (29): "));
+
+        driver.send("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves h1h8");
+        driver.send("d unicode");
+        let lines = recv_until(&driver, "Legal moves");
+        assert_eq!(lines[1], " |   |   |   |   | ♚ |   |   | ♖ | 8");
+        assert!(lines.contains(&"Checkers: h8".to_owned()));
+    }
+
+    #[test]
+    fn chess960() {
+        let driver = driver();
+        // Castling with inner rooks is dropped without UCI_Chess960
+        driver.send("position fen 4k3/8/8/8/8/8/8/R3K1R1 w GA - 0 1");
+        driver.send("d");
+        let lines = recv_until(&driver, "Legal moves");
+        assert!(lines.contains(&"Fen: 4k3/8/8/8/8/8/8/R3K1R1 w Q - 0 1".to_owned()));
+
+        driver.send("setoption name UCI_Chess960 value true");
+        driver.send("position fen 4k3/8/8/8/8/8/8/R3K1R1 w GA - 0 1 moves e1g1");
+        driver.send("d");
+        let lines = recv_until(&driver, "Legal moves");
+        assert!(lines.contains(&"Fen: 4k3/8/8/8/8/8/8/R4RK1 b - - 1 1".to_owned()));
+    }
+
+    #[test]
+    fn three_check() {
+        let driver = driver();
+        driver.send("setoption name UCI_Variant value 3check");
+        driver.send("position startpos moves e2e4 f7f6 d1h5");
+        driver.send("d");
+        let lines = recv_until(&driver, "Legal moves");
+        assert!(lines.contains(&"Fen: rnbqkbnr/ppppp1pp/5p2/7Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2 +1+0".to_owned()));
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let driver = driver();
+        driver.send("setoption name UCI_Variant value kingofthehill");
+        driver.send("position fen 8/8/8/8/8/8/4K3/k7 w - - 0 1");
+        driver.send("go depth 4");
+        let lines = recv_until(&driver, "bestmove");
+        assert!(lines.iter().any(|line| line.starts_with("info depth 4 ") && line.contains(" score mate 2 ")));
+    }
+
+    #[test]
+    fn quit() {
+        let driver = driver();
+        driver.send("go infinite");
+        driver.send("quit");
+        while driver.recv().is_some() {}
+        assert!(!driver.send("isready"));
+    }
+}
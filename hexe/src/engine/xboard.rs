@@ -0,0 +1,667 @@
+use super::*;
+
+use std::io::{self, BufRead};
+use std::mem;
+use std::str;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+use parking_lot::Mutex;
+
+use core::color::Color;
+use core::fen::Fen;
+use core::mv::{Move, MoveVec};
+use engine::Limits;
+use engine::handle::Listener;
+use engine::thread::Job;
+use position::{Draw, Outcome, Position};
+
+/// The score reported for a mate in 0 moves in thinking output.
+const MATE_SCORE: i32 = 100_000;
+
+type XBoardIter<'a> = str::SplitWhitespace<'a>;
+
+/// Returns the starting position of the variant played by `engine`.
+fn start_position(engine: &Engine) -> Position {
+    Position::from_fen_variant(&Fen::STANDARD, engine.variant()).unwrap()
+}
+
+/// The time control set by "level".
+#[derive(Copy, Clone, Debug, Default)]
+struct Level {
+    /// The number of moves per time control, or 0 for the whole game.
+    moves: u32,
+    /// The increment per move in milliseconds.
+    inc: u32,
+}
+
+/// The move played by a search, shared with the thread forwarding its results.
+#[derive(Default)]
+struct Reply {
+    /// Set once the move should no longer be played.
+    discard: bool,
+    /// The move played, if any.
+    mv: Option<Move>,
+}
+
+/// A search running in the background.
+struct Thinking {
+    reply: Arc<Mutex<Reply>>,
+    forwarder: JoinHandle<()>,
+}
+
+/// Runs the engine via the [Chess Engine Communication Protocol][cecp] (CECP)
+/// version 2, also known as the XBoard or WinBoard protocol.
+///
+/// [cecp]: https://www.gnu.org/software/xboard/engine-intf.html
+pub struct XBoard<'a> {
+    engine: &'a mut Engine,
+
+    /// The current position.
+    position: Position,
+
+    /// The moves made since the position was set, for "undo".
+    moves: Vec<Move>,
+
+    /// The color played by the engine, or `None` in force mode.
+    engine_color: Option<Color>,
+
+    /// Whether thinking output is sent.
+    post: bool,
+
+    /// Whether the engine is analyzing rather than playing.
+    analyzing: bool,
+
+    level: Level,
+    /// The fixed number of milliseconds per move set by "st".
+    move_time: u32,
+    /// The maximum depth set by "sd".
+    depth: u32,
+    /// The engine's and the opponent's clocks in milliseconds.
+    time: u32,
+    otim: u32,
+
+    thinking: Option<Thinking>,
+}
+
+impl<'a> From<&'a mut Engine> for XBoard<'a> {
+    #[inline]
+    fn from(engine: &'a mut Engine) -> XBoard<'a> {
+        let position = start_position(engine);
+        XBoard {
+            engine,
+            position,
+            moves: Vec::new(),
+            engine_color: Some(Color::Black),
+            post: false,
+            analyzing: false,
+            level: Level::default(),
+            move_time: 0,
+            depth: 0,
+            time: 0,
+            otim: 0,
+            thinking: None,
+        }
+    }
+}
+
+impl<'a> Drop for XBoard<'a> {
+    fn drop(&mut self) {
+        self.stop_thinking();
+    }
+}
+
+impl<'a> XBoard<'a> {
+    /// Returns a reference to the underlying engine over which `self` iterates.
+    #[inline]
+    pub fn engine(&self) -> &Engine { &self.engine }
+
+    /// Returns a mutable reference to the underlying engine over which `self`
+    /// iterates.
+    #[inline]
+    pub fn engine_mut(&mut self) -> &mut Engine { &mut self.engine }
+
+    /// Runs the CECP loop, feeding commands from `stdin`.
+    ///
+    /// This method retains a lock on `stdin` until it exits. To feed commands
+    /// differently, use [`start_with`](#method.start_with).
+    pub fn start(&mut self) {
+        info!("Starting CECP from stdin");
+        let stdin = io::stdin();
+        let lines = stdin.lock().lines().filter_map(Result::ok);
+        self.start_with(lines);
+    }
+
+    /// Runs the CECP loop, feeding commands from an iterator.
+    pub fn start_with<I>(&mut self, commands: I)
+        where I: IntoIterator,
+              I::Item: AsRef<str>,
+    {
+        info!("Starting CECP from iterator");
+        for line in commands {
+            if !self.run_lines(line.as_ref()) {
+                break;
+            }
+        }
+        self.stop_thinking();
+    }
+
+    /// Runs a single CECP command or multiple if newlines are found.
+    #[inline]
+    pub fn run(&mut self, command: &str) {
+        self.run_lines(command);
+    }
+
+    /// Runs each line of `command`, returning `false` upon "quit".
+    fn run_lines(&mut self, command: &str) -> bool {
+        command.lines().all(|line| self.run_line(line))
+    }
+
+    fn run_line(&mut self, line: &str) -> bool {
+        debug!("Running CECP command: \"{}\"", line);
+
+        // Play the move of a finished search before anything else
+        self.poll();
+
+        let mut split = line.split_whitespace();
+        match split.next().unwrap_or("") {
+            "quit"     => return false,
+            "protover" => self.cmd_protover(),
+            "new"      => self.cmd_new(),
+            "force"    => self.cmd_force(),
+            "go"       => self.cmd_go(),
+            "usermove" => self.cmd_user_move(split),
+            "?"        => self.engine.halt_all(),
+            "ping"     => self.cmd_ping(split),
+            "level"    => self.cmd_level(split),
+            "st"       => self.cmd_st(split),
+            "sd"       => self.cmd_sd(split),
+            "time"     => self.time = read_centis(split),
+            "otim"     => self.otim = read_centis(split),
+            "analyze"  => self.cmd_analyze(),
+            "exit"     => self.cmd_exit(),
+            "undo"     => self.cmd_undo(1),
+            "remove"   => self.cmd_undo(2),
+            "setboard" => self.cmd_set_board(split),
+            "post"     => self.post = true,
+            "nopost"   => self.post = false,
+            "result"   => self.cmd_result(),
+            // Accepted without effect
+            "xboard" | "accepted" | "rejected" | "random" | "easy" | "hard" |
+            "computer" | "name" | "rating" | "ics" | "." | "" => {},
+            cmd => {
+                writeln!(self.engine.output(), "Error (unknown command): {}", cmd);
+            },
+        }
+        true
+    }
+
+    fn cmd_protover(&self) {
+        writeln!(
+            self.engine.output(),
+            "feature done=0 myname=\"Hexe {}\" ping=1 setboard=1 usermove=1 \
+             analyze=1 colors=0 sigint=0 sigterm=0 reuse=1 san=0\
+             \nfeature done=1",
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+
+    fn cmd_new(&mut self) {
+        self.stop_thinking();
+        self.engine.clear_hash();
+        let position = start_position(self.engine);
+        self.set_position(position);
+        self.engine_color = Some(Color::Black);
+        self.analyzing = false;
+        self.move_time = 0;
+        self.depth = 0;
+    }
+
+    fn cmd_force(&mut self) {
+        self.stop_thinking();
+        self.engine_color = None;
+    }
+
+    fn cmd_go(&mut self) {
+        self.stop_thinking();
+        self.engine_color = Some(self.position.player());
+        self.think();
+    }
+
+    fn cmd_user_move(&mut self, mut iter: XBoardIter) {
+        self.stop_thinking();
+
+        let input = iter.next().unwrap_or("");
+        let mv = match self.read_move(input) {
+            Some(mv) => mv,
+            None => {
+                writeln!(self.engine.output(), "Illegal move: {}", input);
+                return;
+            },
+        };
+        self.make_move(mv);
+
+        if self.analyzing || self.engine_color == Some(self.position.player()) {
+            self.think();
+        }
+    }
+
+    fn cmd_ping(&self, mut iter: XBoardIter) {
+        writeln!(self.engine.output(), "pong {}", iter.next().unwrap_or(""));
+    }
+
+    fn cmd_level(&mut self, mut iter: XBoardIter) {
+        let moves = iter.next().and_then(|s| s.parse().ok());
+        let base = iter.next().and_then(read_minutes);
+        let inc = iter.next().and_then(|s| s.parse::<f64>().ok());
+
+        match (moves, base, inc) {
+            (Some(moves), Some(base), Some(inc)) => {
+                self.level = Level { moves, inc: (inc * 1000.0) as u32 };
+                self.move_time = 0;
+                // Both clocks start at the base time until "time" and "otim"
+                self.time = base;
+                self.otim = base;
+            },
+            _ => {
+                writeln!(self.engine.output(), "Error (invalid level): level");
+            },
+        }
+    }
+
+    fn cmd_st(&mut self, mut iter: XBoardIter) {
+        if let Some(Ok(secs)) = iter.next().map(str::parse::<f64>) {
+            self.move_time = (secs * 1000.0) as u32;
+        }
+    }
+
+    fn cmd_sd(&mut self, mut iter: XBoardIter) {
+        if let Some(Ok(depth)) = iter.next().map(str::parse) {
+            self.depth = depth;
+        }
+    }
+
+    fn cmd_analyze(&mut self) {
+        self.stop_thinking();
+        self.analyzing = true;
+        self.engine_color = None;
+        self.think();
+    }
+
+    fn cmd_exit(&mut self) {
+        self.stop_thinking();
+        self.analyzing = false;
+    }
+
+    fn cmd_undo(&mut self, count: usize) {
+        self.stop_thinking();
+        for _ in 0..count {
+            match self.moves.pop() {
+                Some(mv) => self.position.unmake_move(mv),
+                None => {
+                    writeln!(self.engine.output(), "Error (no move to undo): undo");
+                    break;
+                },
+            }
+        }
+        if self.analyzing {
+            self.think();
+        }
+    }
+
+    fn cmd_set_board(&mut self, iter: XBoardIter) {
+        let fen = iter.collect::<Vec<_>>().join(" ");
+        let variant = self.engine.variant();
+        let position = fen.parse::<Fen>().ok().and_then(|fen| {
+            Position::from_fen_variant(&fen, variant)
+        });
+        match position {
+            Some(position) => {
+                self.stop_thinking();
+                self.set_position(position);
+                if self.analyzing {
+                    self.think();
+                }
+            },
+            None => {
+                writeln!(self.engine.output(), "tellusererror Illegal position");
+            },
+        }
+    }
+
+    fn cmd_result(&mut self) {
+        self.stop_thinking();
+        self.engine_color = None;
+    }
+
+    fn read_move(&self, s: &str) -> Option<Move> {
+        let mut moves = MoveVec::new();
+        self.position.gen(&mut moves).legal();
+        moves.iter().cloned().find(|mv| mv.to_string() == s)
+    }
+
+    fn make_move(&mut self, mv: Move) {
+        self.position.make_move(mv);
+        self.moves.push(mv);
+    }
+
+    fn set_position(&mut self, position: Position) {
+        self.position = position;
+        self.moves.clear();
+    }
+
+    /// Returns the limits of the next search.
+    fn limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.move_overhead = self.engine.move_overhead();
+        limits.multi_pv = 1;
+
+        if self.analyzing {
+            limits.infinite = true;
+            return limits;
+        }
+
+        limits.depth = self.depth;
+        if self.move_time != 0 {
+            limits.move_time = self.move_time;
+        } else if self.time != 0 {
+            let player = self.position.player();
+            limits.time[player as usize] = self.time;
+            limits.time[!player as usize] = self.otim;
+            limits.inc[player as usize] = self.level.inc;
+            limits.inc[!player as usize] = self.level.inc;
+            if self.level.moves != 0 {
+                // Moves made by the side to move since the start of the game
+                let played = self.position.fullmoves().saturating_sub(1);
+                limits.moves_to_go = self.level.moves - played % self.level.moves;
+            }
+        }
+        limits
+    }
+
+    /// Starts searching the current position in the background, playing the
+    /// best move found unless analyzing.
+    fn think(&mut self) {
+        if let Some(outcome) = self.position.outcome() {
+            if !self.analyzing {
+                writeln!(self.engine.output(), "{}", result(outcome));
+            }
+            return;
+        }
+
+        let (info_tx, info_rx) = mpsc::channel();
+        let (best_tx, best_rx) = mpsc::channel();
+        let (stats_tx, stats_rx) = mpsc::channel();
+        let stats_out = self.engine.pool.shared().stats.read().clone();
+
+        self.engine.halt_all();
+        self.engine.wait_idle();
+        self.engine.pool.enqueue(Job::Search {
+            limits: self.limits(),
+            moves: Box::new([]),
+            position: self.position.clone(),
+            listener: Some(Listener {
+                info: info_tx,
+                best: best_tx,
+                stats: if stats_out.is_some() { Some(stats_tx) } else { None },
+            }),
+        });
+
+        let out = self.engine.output();
+        let post = self.post || self.analyzing;
+        let play = !self.analyzing;
+        let mut position = self.position.clone();
+        let reply = Arc::new(Mutex::new(Reply::default()));
+        let shared = reply.clone();
+
+        let forwarder = thread::spawn(move || {
+            // Ends once the search finishes and drops its sender
+            for info in info_rx {
+                if post {
+                    let mut pv = String::new();
+                    for mv in &info.pv {
+                        pv.push(' ');
+                        pv.push_str(&mv.to_string());
+                    }
+                    writeln!(out, "{} {} {} {}{}", info.depth, score(info.score),
+                             info.time_ms() / 10, info.nodes, pv);
+                }
+            }
+            let best = best_rx.recv();
+            if let (Some(stats_out), Ok(stats)) = (stats_out, stats_rx.try_recv()) {
+                writeln!(stats_out, "{}", stats.to_json());
+            }
+            if let Ok(Some(best)) = best {
+                let mut reply = shared.lock();
+                if play && !reply.discard {
+                    reply.mv = Some(best.mv);
+                    writeln!(out, "move {}", best.mv);
+
+                    // Claim the result if the move ended the game
+                    position.make_move(best.mv);
+                    if let Some(outcome) = position.outcome() {
+                        writeln!(out, "{}", result(outcome));
+                    }
+                }
+            }
+        });
+
+        self.thinking = Some(Thinking { reply, forwarder });
+    }
+
+    /// Ends any search without playing its move, unless it was already sent.
+    fn stop_thinking(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.reply.lock().discard = true;
+            self.engine.halt_all();
+            self.finish(thinking);
+        }
+    }
+
+    /// Plays the move of a search that has finished.
+    fn poll(&mut self) {
+        let finished = match self.thinking {
+            Some(ref thinking) => thinking.reply.lock().mv.is_some(),
+            None => false,
+        };
+        if finished {
+            if let Some(thinking) = self.thinking.take() {
+                self.finish(thinking);
+            }
+        }
+    }
+
+    /// Waits for `thinking` to finish, playing its move if it was sent.
+    fn finish(&mut self, thinking: Thinking) {
+        if thinking.forwarder.join().is_err() {
+            error!("CECP forwarding thread panicked");
+        }
+        let reply = mem::replace(&mut *thinking.reply.lock(), Reply::default());
+        if let Some(mv) = reply.mv {
+            self.make_move(mv);
+        }
+    }
+}
+
+/// Reads a time in centiseconds as milliseconds.
+fn read_centis(mut iter: XBoardIter) -> u32 {
+    iter.next().and_then(|s| s.parse::<u32>().ok()).map_or(0, |cs| cs * 10)
+}
+
+/// Reads a base time given as "minutes" or "minutes:seconds" as milliseconds.
+fn read_minutes(s: &str) -> Option<u32> {
+    let mut split = s.splitn(2, ':');
+    let mins = split.next()?.parse::<u32>().ok()?;
+    let secs = match split.next() {
+        Some(secs) => secs.parse::<u32>().ok()?,
+        None => 0,
+    };
+    Some((mins * 60 + secs) * 1000)
+}
+
+/// Converts `score` to centipawns or to the CECP convention for mates.
+fn score(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(n) if n > 0 => MATE_SCORE + n,
+        Score::Mate(n) => -MATE_SCORE + n,
+    }
+}
+
+/// Returns the "result" line for `outcome`.
+fn result(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Checkmate { winner: Color::White } => "1-0 {White mates}",
+        Outcome::Checkmate { winner: Color::Black } => "0-1 {Black mates}",
+        Outcome::ThreeChecks { winner: Color::White } => "1-0 {White gives three checks}",
+        Outcome::ThreeChecks { winner: Color::Black } => "0-1 {Black gives three checks}",
+        Outcome::KingOfTheHill { winner: Color::White } => "1-0 {White king reaches the center}",
+        Outcome::KingOfTheHill { winner: Color::Black } => "0-1 {Black king reaches the center}",
+        Outcome::Stalemate => "1/2-1/2 {Stalemate}",
+        Outcome::Draw(Draw::Repetition) => "1/2-1/2 {Draw by repetition}",
+        Outcome::Draw(Draw::FiftyMoves) => "1/2-1/2 {Draw by fifty move rule}",
+        Outcome::Draw(Draw::InsufficientMaterial) => "1/2-1/2 {Insufficient material}",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    fn engine() -> (Engine, Receiver<String>) {
+        let engine = Engine::builder().num_threads(1).build();
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        engine.set_output(Output::from_fn(move |line| {
+            let _ = tx.lock().send(line.to_owned());
+        }));
+        (engine, rx)
+    }
+
+    fn recv_until(rx: &Receiver<String>, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = rx.recv_timeout(Duration::from_secs(30)).expect("No response");
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+
+    #[test]
+    fn variant() {
+        let (mut engine, _rx) = engine();
+        engine.set_variant(Variant::ThreeCheck);
+        let mut xboard = engine.xboard();
+        assert_eq!(xboard.position.variant(), Variant::ThreeCheck);
+
+        xboard.run("setboard 4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(xboard.position.variant(), Variant::ThreeCheck);
+        xboard.run("new");
+        assert_eq!(xboard.position.variant(), Variant::ThreeCheck);
+    }
+
+    #[test]
+    fn handshake() {
+        let (mut engine, rx) = engine();
+        let mut xboard = engine.xboard();
+        xboard.run("xboard\nprotover 2\nping 7");
+        let lines = recv_until(&rx, "pong");
+        assert!(lines.iter().any(|line| line.contains("usermove=1")));
+        assert!(lines.iter().any(|line| line == "feature done=1"));
+        assert_eq!(lines.last().unwrap(), "pong 7");
+
+        xboard.run("frobnicate");
+        assert_eq!(rx.recv().unwrap(), "Error (unknown command): frobnicate");
+        xboard.run("usermove e2e5");
+        assert_eq!(rx.recv().unwrap(), "Illegal move: e2e5");
+    }
+
+    #[test]
+    fn play() {
+        let (mut engine, rx) = engine();
+        let mut xboard = engine.xboard();
+        xboard.run("new\npost\nsd 3\nusermove e2e4");
+
+        let lines = recv_until(&rx, "move");
+        assert!(lines[0].starts_with("1 "));
+        let reply = lines.last().unwrap()["move ".len()..].to_owned();
+
+        // The engine's move is played before the next user move
+        xboard.run("usermove d2d4");
+        recv_until(&rx, "move");
+        xboard.run("ping 1");
+        recv_until(&rx, "pong");
+        assert_eq!(xboard.moves.len(), 4);
+        assert_eq!(xboard.moves[1].to_string(), reply);
+
+        xboard.run("force\nundo\nremove");
+        assert_eq!(xboard.moves.len(), 1);
+        assert_eq!(xboard.engine_color, None);
+    }
+
+    #[test]
+    fn mate_result() {
+        let (mut engine, rx) = engine();
+        let mut xboard = engine.xboard();
+        xboard.run("setboard R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1\ngo");
+        assert_eq!(rx.recv().unwrap(), "1-0 {White mates}");
+
+        xboard.run("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\nsd 2\ngo");
+        assert_eq!(recv_until(&rx, "move").last().unwrap(), "move a1a8");
+        assert_eq!(rx.recv().unwrap(), "1-0 {White mates}");
+        xboard.run("ping 1");
+        recv_until(&rx, "pong");
+        assert_eq!(xboard.position.outcome(), Some(Outcome::Checkmate { winner: Color::White }));
+    }
+
+    #[test]
+    fn level() {
+        let (mut engine, rx) = engine();
+        let mut xboard = engine.xboard();
+        xboard.run("force\nlevel 40 5 0");
+        let limits = xboard.limits();
+        assert_eq!(limits.time, [300_000; 2]);
+        assert_eq!(limits.moves_to_go, 40);
+
+        xboard.run("level 40 0:30 2\nusermove e2e4\nusermove e7e5");
+        let limits = xboard.limits();
+        assert_eq!(limits.time, [30_000; 2]);
+        assert_eq!(limits.inc, [2_000; 2]);
+        assert_eq!(limits.moves_to_go, 39);
+
+        // Moves to go follow the game's move number rather than "setboard"
+        xboard.run("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 52\ntime 6000");
+        let limits = xboard.limits();
+        assert_eq!(limits.time[Color::White as usize], 60_000);
+        assert_eq!(limits.moves_to_go, 29);
+
+        xboard.run("level 40 1:x 0");
+        assert_eq!(rx.recv().unwrap(), "Error (invalid level): level");
+    }
+
+    #[test]
+    fn analyze() {
+        let (mut engine, rx) = engine();
+        let mut xboard = engine.xboard();
+        xboard.run("analyze");
+        recv_until(&rx, "1 ");
+        xboard.run("usermove e2e4");
+        xboard.run("exit");
+        assert!(xboard.thinking.is_none());
+        assert_eq!(xboard.moves.len(), 1);
+        assert!(rx.try_iter().all(|line| !line.starts_with("move")));
+    }
+
+    #[test]
+    fn score() {
+        assert_eq!(super::score(Score::Cp(-35)), -35);
+        assert_eq!(super::score(Score::Mate(3)), 100_003);
+        assert_eq!(super::score(Score::Mate(-2)), -100_002);
+    }
+}
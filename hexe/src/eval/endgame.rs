@@ -0,0 +1,459 @@
+//! Specialized evaluation of endgames.
+//!
+//! The general evaluation has no notion of how technical endgames are won or
+//! held. Endgames with a known plan are evaluated by their own functions,
+//! selected by a key of the material on the board, and endgames that are
+//! likely drawn have their end game score scaled down.
+
+use std::collections::HashMap;
+
+use board::MultiBoard;
+use core::mv::MoveVec;
+use prelude::*;
+
+use super::{kpk, EG_VALUES};
+
+/// A score at which the strong side is known to win without a mate being
+/// found.
+pub const KNOWN_WIN: i32 = 10000;
+
+/// The scale factor that leaves an end game score unchanged.
+pub const SCALE_NORMAL: i32 = 64;
+
+/// The scale factor of an end game that is a draw.
+pub const SCALE_DRAW: i32 = 0;
+
+/// Evaluates a position from the perspective of the strong side.
+type ValueFn = fn(&Position, Color) -> i32;
+
+/// The endgames with their own evaluation, named with the strong side first.
+const ENDGAMES: [(&str, ValueFn); 8] = [
+    ("KPvK",  kpk),
+    ("KBNvK", kbnk),
+    ("KNNvK", knnk),
+    ("KRvKP", krkp),
+    ("KRvKB", krkb),
+    ("KRvKN", krkn),
+    ("KQvKP", kqkp),
+    ("KQvKR", kqkr),
+];
+
+/// The most pieces in any of `ENDGAMES`.
+const MAX_PIECES: usize = 4;
+
+const ROLES: [Role; 5] = [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen];
+
+lazy_static! {
+    static ref TABLE: HashMap<u64, ValueFn> = {
+        ENDGAMES.iter().map(|&(name, f)| (parse_key(name), f)).collect()
+    };
+}
+
+/// Returns the key of the material on `board` with `strong` as the first
+/// side.
+fn material_key(board: &MultiBoard, strong: Color) -> u64 {
+    [strong, !strong].iter().fold(0, |key, &color| {
+        ROLES.iter().fold(key, |key, &role| {
+            key << 4 | board.count(Piece::new(role, color)).min(15) as u64
+        })
+    })
+}
+
+/// Returns the key of an endgame named like "KBNvK".
+fn parse_key(name: &str) -> u64 {
+    name.split('v').fold(0, |key, side| {
+        ROLES.iter().fold(key, |key, &role| {
+            let ch = char::from(role).to_ascii_uppercase();
+            key << 4 | side.chars().filter(|&c| c == ch).count() as u64
+        })
+    })
+}
+
+/// Returns the score of `pos` from the perspective of the player to move if
+/// its material has a specialized evaluation.
+///
+/// Variants won other than by checkmate have none.
+pub fn evaluate(pos: &Position) -> Option<i32> {
+    if !pos.variant().has_standard_goal() {
+        return None;
+    }
+    let board = pos.board();
+    let mut found = None;
+
+    if board.all_bits().len() <= MAX_PIECES {
+        for &strong in [Color::White, Color::Black].iter() {
+            if let Some(&f) = TABLE.get(&material_key(board, strong)) {
+                found = Some((strong, f));
+                break;
+            }
+        }
+    }
+    if found.is_none() {
+        for &strong in [Color::White, Color::Black].iter() {
+            if board.count(!strong) == 1 && non_pawn(board, strong) >= EG_VALUES[Role::Rook as usize] {
+                found = Some((strong, kxk as ValueFn));
+                break;
+            }
+        }
+    }
+
+    found.map(|(strong, f)| {
+        let value = f(pos, strong);
+        if pos.player() == strong { value } else { -value }
+    })
+}
+
+/// Returns the factor out of `SCALE_NORMAL` by which the end game score of
+/// `pos` is scaled when `strong` is ahead.
+///
+/// Variants won other than by checkmate are never scaled.
+pub fn scale(pos: &Position, strong: Color) -> i32 {
+    if !pos.variant().has_standard_goal() {
+        return SCALE_NORMAL;
+    }
+    let board = pos.board();
+    let weak = !strong;
+    let pawns = board.bits(Piece::new(Role::Pawn, strong));
+    let weak_pawns = board.bits(Piece::new(Role::Pawn, weak));
+    let strong_npm = non_pawn(board, strong);
+    let weak_npm = non_pawn(board, weak);
+    let bishop = EG_VALUES[Role::Bishop as usize];
+    let weak_king = pos.king_square(weak);
+
+    // Without pawns, being up a minor piece is rarely enough to win
+    if pawns.is_empty() && strong_npm - weak_npm <= bishop {
+        return if strong_npm < EG_VALUES[Role::Rook as usize] {
+            SCALE_DRAW
+        } else if weak_npm <= bishop {
+            4
+        } else {
+            14
+        };
+    }
+
+    // Rook pawns are held by the defending king in front of them
+    let rook_file = pawns.lsb().map(|sq| sq.file()).filter(|&file| {
+        (file == File::A || file == File::H) && pawns.into_iter().all(|sq| sq.file() == file)
+    });
+    if let Some(file) = rook_file {
+        let queening = Square::new(file, Rank::last(strong));
+        if strong_npm == 0 && board.count(weak) == 1 {
+            let ahead = pawns.into_iter().all(|sq| match strong {
+                Color::White => sq.rank() < weak_king.rank(),
+                Color::Black => sq.rank() > weak_king.rank(),
+            });
+            if ahead && weak_king.file().distance(file) <= 1 {
+                return SCALE_DRAW;
+            }
+        }
+        // The bishop does not control the queening square
+        if strong_npm == bishop && weak_npm == 0 && weak_king.distance(queening) <= 1 {
+            let bishops = board.bits(Piece::new(Role::Bishop, strong));
+            if bishops.into_iter().all(|sq| !sq.color_eq(queening)) {
+                return SCALE_DRAW;
+            }
+        }
+    }
+
+    // Bishops of opposite colors leave the weak side a blockade
+    if strong_npm == bishop && weak_npm == bishop {
+        let ours = board.first(Piece::new(Role::Bishop, strong));
+        let theirs = board.first(Piece::new(Role::Bishop, weak));
+        if let (Some(ours), Some(theirs)) = (ours, theirs) {
+            if !ours.color_eq(theirs) {
+                return if pawns.len() <= weak_pawns.len() + 1 { 16 } else { 32 };
+            }
+        }
+    }
+
+    SCALE_NORMAL
+}
+
+/// Returns the end game value of the pieces of `color` other than pawns and
+/// the king.
+fn non_pawn(board: &MultiBoard, color: Color) -> i32 {
+    ROLES[1..].iter().map(|&role| {
+        EG_VALUES[role as usize] * board.count(Piece::new(role, color)) as i32
+    }).sum()
+}
+
+/// Returns the end game value of all pieces of `color`.
+fn material(board: &MultiBoard, color: Color) -> i32 {
+    non_pawn(board, color) + EG_VALUES[0] * board.count(Piece::new(Role::Pawn, color)) as i32
+}
+
+/// Returns `square` as seen by `strong`, so that the strong side plays up
+/// the board.
+#[inline]
+fn relative(square: Square, strong: Color) -> Square {
+    match strong {
+        Color::White => square,
+        Color::Black => square.rev_rank(),
+    }
+}
+
+/// Returns the square of the only `role` of `color` relative to `strong`.
+fn find(pos: &Position, role: Role, color: Color, strong: Color) -> Square {
+    let square = pos.board().first(Piece::new(role, color)).expect("missing piece");
+    relative(square, strong)
+}
+
+/// A bonus for driving the king towards the edge of the board.
+#[inline]
+fn push_to_edge(square: Square) -> i32 {
+    20 * square.center_man_distance() as i32
+}
+
+/// A bonus for driving the king towards a corner of `color`.
+fn push_to_corner(square: Square, color: Color) -> i32 {
+    let corners = if Square::A1.color() == color {
+        [Square::A1, Square::H8]
+    } else {
+        [Square::A8, Square::H1]
+    };
+    let distance = corners.iter().map(|&c| square.man_distance(c)).min().unwrap_or(0);
+    20 * (14 - distance as i32)
+}
+
+/// A bonus for bringing two pieces close together.
+#[inline]
+fn push_close(a: Square, b: Square) -> i32 {
+    140 - 20 * a.distance(b) as i32
+}
+
+/// A bonus for keeping two pieces apart.
+#[inline]
+fn push_away(a: Square, b: Square) -> i32 {
+    120 - push_close(a, b)
+}
+
+/// Mating material against a lone king: the weak king is driven to the edge.
+fn kxk(pos: &Position, strong: Color) -> i32 {
+    let weak = !strong;
+    if pos.player() == weak && !pos.in_check() {
+        let mut moves = MoveVec::new();
+        pos.gen(&mut moves).legal();
+        if moves.is_empty() {
+            return 0;
+        }
+    }
+
+    let board = pos.board();
+    let strong_king = pos.king_square(strong);
+    let weak_king = pos.king_square(weak);
+    let mut value = material(board, strong)
+                  + push_to_edge(weak_king)
+                  + push_close(strong_king, weak_king);
+
+    let count = |role| board.count(Piece::new(role, strong));
+    let bishops = board.bits(Piece::new(Role::Bishop, strong));
+    if count(Role::Queen) > 0 || count(Role::Rook) > 0
+        || (count(Role::Bishop) > 0 && count(Role::Knight) > 0)
+        || (bishops.intersects(BitBoard::WHITE) && bishops.intersects(BitBoard::BLACK))
+    {
+        value += KNOWN_WIN;
+    }
+    value
+}
+
+/// Bishop and knight: the weak king is mated in a corner of the bishop's
+/// color.
+fn kbnk(pos: &Position, strong: Color) -> i32 {
+    let board = pos.board();
+    let strong_king = pos.king_square(strong);
+    let weak_king = pos.king_square(!strong);
+    let bishop = board.first(Piece::new(Role::Bishop, strong)).expect("missing bishop");
+
+    KNOWN_WIN + material(board, strong)
+              + push_close(strong_king, weak_king)
+              + push_to_corner(weak_king, bishop.color())
+}
+
+/// Two knights cannot force mate.
+fn knnk(_: &Position, _: Color) -> i32 {
+    0
+}
+
+/// King and pawn, looked up in the bitbase.
+fn kpk(pos: &Position, strong: Color) -> i32 {
+    let mut strong_king = find(pos, Role::King, strong, strong);
+    let mut weak_king = find(pos, Role::King, !strong, strong);
+    let mut pawn = find(pos, Role::Pawn, strong, strong);
+    if pawn.file() > File::D {
+        strong_king = strong_king.rev_file();
+        weak_king = weak_king.rev_file();
+        pawn = pawn.rev_file();
+    }
+
+    let player = if pos.player() == strong { Color::White } else { Color::Black };
+    if kpk::probe(strong_king, pawn, weak_king, player) {
+        KNOWN_WIN + EG_VALUES[0] + 10 * pawn.rank() as i32
+    } else {
+        0
+    }
+}
+
+/// Rook against pawn: won unless the pawn is far advanced with its king
+/// supporting it.
+fn krkp(pos: &Position, strong: Color) -> i32 {
+    let weak = !strong;
+    let strong_king = find(pos, Role::King, strong, strong);
+    let weak_king = find(pos, Role::King, weak, strong);
+    let rook = find(pos, Role::Rook, strong, strong);
+    let pawn = find(pos, Role::Pawn, weak, strong);
+    let queening = Square::new(pawn.file(), Rank::One);
+    let advance = pawn.wrapping_down();
+    let rook_value = EG_VALUES[Role::Rook as usize];
+    let distance = |a: Square, b: Square| a.distance(b) as i32;
+    let to_move = |color| (pos.player() == color) as i32;
+
+    if strong_king.file() == pawn.file() && strong_king.rank() < pawn.rank() {
+        // The king stops the pawn
+        rook_value - distance(strong_king, pawn)
+    } else if distance(weak_king, pawn) >= 3 + to_move(weak) && distance(weak_king, rook) >= 3 {
+        // The pawn is left without its king
+        rook_value - distance(strong_king, pawn)
+    } else if weak_king.rank() <= Rank::Three && distance(weak_king, pawn) == 1
+           && strong_king.rank() >= Rank::Four
+           && distance(strong_king, pawn) > 2 + to_move(strong)
+    {
+        // The pawn is escorted and the strong king is too far away
+        80 - 8 * distance(strong_king, pawn)
+    } else {
+        200 - 8 * (distance(strong_king, advance)
+                 - distance(weak_king, advance)
+                 - distance(pawn, queening))
+    }
+}
+
+/// Rook against bishop is usually a draw.
+fn krkb(pos: &Position, strong: Color) -> i32 {
+    push_to_edge(pos.king_square(!strong))
+}
+
+/// Rook against knight is usually a draw, unless the knight strays from its
+/// king.
+fn krkn(pos: &Position, strong: Color) -> i32 {
+    let weak = !strong;
+    let weak_king = pos.king_square(weak);
+    let knight = pos.board().first(Piece::new(Role::Knight, weak)).expect("missing knight");
+    push_to_edge(weak_king) + push_away(weak_king, knight)
+}
+
+/// Queen against pawn: won unless a bishop or rook pawn on the seventh rank
+/// is supported by its king.
+fn kqkp(pos: &Position, strong: Color) -> i32 {
+    let weak = !strong;
+    let strong_king = pos.king_square(strong);
+    let weak_king = find(pos, Role::King, weak, strong);
+    let pawn = find(pos, Role::Pawn, weak, strong);
+    let mut value = push_close(strong_king, pos.king_square(weak));
+
+    let drawish = [File::A, File::C, File::F, File::H].contains(&pawn.file());
+    if pawn.rank() != Rank::Two || weak_king.distance(pawn) != 1 || !drawish {
+        value += EG_VALUES[Role::Queen as usize] - EG_VALUES[0];
+    }
+    value
+}
+
+/// Queen against rook: the weak king is driven to the edge.
+fn kqkr(pos: &Position, strong: Color) -> i32 {
+    let strong_king = pos.king_square(strong);
+    let weak_king = pos.king_square(!strong);
+    EG_VALUES[Role::Queen as usize] - EG_VALUES[Role::Rook as usize]
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fen::Fen;
+
+    fn position(fen: &str) -> Position {
+        let fen: Fen = fen.parse().unwrap();
+        Position::from_fen(&fen).unwrap()
+    }
+
+    #[test]
+    fn keys() {
+        let pos = position("8/8/8/3k4/8/8/2BN4/4K3 w - - 0 1");
+        assert_eq!(material_key(pos.board(), Color::White), parse_key("KBNvK"));
+        assert_eq!(material_key(pos.board(), Color::Black), parse_key("KvKBN"));
+        assert_eq!(TABLE.len(), ENDGAMES.len());
+    }
+
+    #[test]
+    fn kpk() {
+        // The same positions with the colors swapped
+        let won = ["8/4k3/8/4K3/4P3/8/8/8 b - - 0 1", "8/8/8/4p3/4k3/8/4K3/8 w - - 0 1"];
+        for fen in won.iter() {
+            assert!(evaluate(&position(fen)).unwrap() < -KNOWN_WIN, "{}", fen);
+        }
+        let drawn = ["8/4k3/8/4K3/4P3/8/8/8 w - - 0 1", "k7/8/1K6/P7/8/8/8/8 w - - 0 1"];
+        for fen in drawn.iter() {
+            assert_eq!(evaluate(&position(fen)), Some(0), "{}", fen);
+        }
+        // Mirrored onto the queen side
+        assert!(evaluate(&position("4k3/6K1/8/7P/8/8/8/8 w - - 0 1")).unwrap() > KNOWN_WIN);
+    }
+
+    #[test]
+    fn kbnk() {
+        // The bishop is on a light square, so the king is driven to A8 or H1
+        let near = evaluate(&position("k7/8/8/8/8/8/8/4KBN1 w - - 0 1")).unwrap();
+        let far = evaluate(&position("7k/8/8/8/8/8/8/4KBN1 w - - 0 1")).unwrap();
+        assert!(near > far);
+        assert!(far > KNOWN_WIN);
+    }
+
+    #[test]
+    fn kxk() {
+        assert!(evaluate(&position("8/8/8/3k4/8/8/8/R3K3 w - - 0 1")).unwrap() > KNOWN_WIN);
+        assert!(evaluate(&position("8/8/8/3k4/8/8/8/R3K3 b - - 0 1")).unwrap() < -KNOWN_WIN);
+        // Stalemate
+        assert_eq!(evaluate(&position("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")), Some(0));
+        // Bishops on the same color cannot mate
+        let same = evaluate(&position("8/8/8/3k4/8/8/8/2B1K1B1 w - - 0 1")).unwrap();
+        assert!(same > 0 && same < KNOWN_WIN);
+    }
+
+    #[test]
+    fn drawn() {
+        for fen in ["8/8/8/3k4/8/8/8/2N1KN2 w - - 0 1", "8/8/3b4/3k4/8/8/8/R3K3 w - - 0 1"].iter() {
+            assert!(evaluate(&position(fen)).unwrap().abs() < 200, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn krkp() {
+        let won = evaluate(&position("8/8/8/8/8/1p6/1K6/4k2R w - - 0 1")).unwrap();
+        let drawish = evaluate(&position("8/8/K7/8/8/2k5/1p6/7R w - - 0 1")).unwrap();
+        assert!(won > drawish);
+        assert!(drawish < 100);
+    }
+
+    #[test]
+    fn scaling() {
+        let scale_of = |fen| {
+            let pos = position(fen);
+            let strong = if pos.board().count(Color::White) > pos.board().count(Color::Black) {
+                Color::White
+            } else {
+                Color::Black
+            };
+            scale(&pos, strong)
+        };
+        // Wrong bishop for the rook pawn
+        assert_eq!(scale_of("k7/8/8/P7/8/8/8/2B1K3 w - - 0 1"), SCALE_DRAW);
+        assert_eq!(scale_of("k7/8/8/P7/8/8/8/1B2K3 w - - 0 1"), SCALE_NORMAL);
+        // Rook pawns with the king in front of them
+        assert_eq!(scale_of("8/k7/8/P7/P7/8/8/4K3 w - - 0 1"), SCALE_DRAW);
+        // Opposite colored bishops
+        assert_eq!(scale_of("8/4k1b1/8/4P3/8/3B4/4K3/8 w - - 0 1"), 16);
+        assert_eq!(scale_of("8/4k3/8/4P3/3P4/3B4/4K3/6b1 w - - 0 1"), 32);
+        // A minor piece up without pawns
+        assert_eq!(scale_of("8/8/3k4/8/8/3NK3/8/8 w - - 0 1"), SCALE_DRAW);
+        assert_eq!(scale_of("8/8/3k4/8/2P5/3NK3/8/8 w - - 0 1"), SCALE_NORMAL);
+    }
+}
@@ -0,0 +1,177 @@
+//! A bitbase for king and pawn versus king.
+//!
+//! Positions are stored with the strong side as White and the pawn on files A
+//! through D; other positions are mirrored into these by the caller. The
+//! bitbase is generated by retrograde analysis the first time it is probed.
+
+use prelude::*;
+
+/// The number of positions: the side to move, both kings and the 24 squares
+/// the pawn can be on.
+const SIZE: usize = 2 * 64 * 64 * 24;
+
+// Results are bit flags so that the results of all moves can be combined.
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW:    u8 = 2;
+const WIN:     u8 = 4;
+
+lazy_static! {
+    static ref BITBASE: Vec<u64> = generate();
+}
+
+/// Returns whether White wins with `player` to move.
+///
+/// The pawn must be on files A through D and ranks 2 through 7.
+pub fn probe(strong_king: Square, pawn: Square, weak_king: Square, player: Color) -> bool {
+    debug_assert!(pawn.file() <= File::D);
+    debug_assert!(pawn.rank() >= Rank::Two && pawn.rank() <= Rank::Seven);
+
+    let idx = index(player, strong_king, weak_king, pawn);
+    (BITBASE[idx / 64] >> (idx % 64)) & 1 == 1
+}
+
+#[inline]
+fn index(player: Color, strong_king: Square, weak_king: Square, pawn: Square) -> usize {
+    strong_king as usize
+        | (weak_king as usize) << 6
+        | (player as usize) << 12
+        | (pawn.file() as usize) << 13
+        | (Rank::Seven as usize - pawn.rank() as usize) << 15
+}
+
+fn decode(idx: usize) -> (Color, Square, Square, Square) {
+    let strong_king = Square::from(idx & 63);
+    let weak_king = Square::from((idx >> 6) & 63);
+    let player = if (idx >> 12) & 1 == 0 { Color::White } else { Color::Black };
+    let file = File::from((idx >> 13) & 3);
+    let rank = Rank::from(Rank::Seven as usize - (idx >> 15));
+    (player, strong_king, weak_king, Square::new(file, rank))
+}
+
+/// Returns the result of a position that is known without looking at moves.
+fn initial(idx: usize) -> u8 {
+    let (player, wk, bk, pawn) = decode(idx);
+    let push = pawn.wrapping_up();
+
+    if wk.distance(bk) <= 1 || wk == pawn || bk == pawn {
+        return INVALID;
+    }
+    match player {
+        Color::White => if pawn.pawn_attacks(Color::White).contains(bk) {
+            INVALID
+        } else if pawn.rank() == Rank::Seven && wk != push
+               && (bk.distance(push) > 1 || wk.distance(push) == 1)
+        {
+            // The pawn promotes without being taken
+            WIN
+        } else {
+            UNKNOWN
+        },
+        Color::Black => {
+            let moves = bk.king_attacks() - wk.king_attacks();
+            if (moves - pawn.pawn_attacks(Color::White)).is_empty() || moves.contains(pawn) {
+                // Stalemate, or the pawn is taken
+                DRAW
+            } else {
+                UNKNOWN
+            }
+        },
+    }
+}
+
+/// Returns the result of a position from the results of its moves.
+fn classify(db: &[u8], idx: usize) -> u8 {
+    let (player, wk, bk, pawn) = decode(idx);
+    let mut result = INVALID;
+
+    match player {
+        Color::White => {
+            for sq in wk.king_attacks() {
+                result |= db[index(Color::Black, sq, bk, pawn)];
+            }
+            if pawn.rank() < Rank::Seven {
+                let push = pawn.wrapping_up();
+                result |= db[index(Color::Black, wk, bk, push)];
+                if pawn.rank() == Rank::Two && push != wk && push != bk {
+                    result |= db[index(Color::Black, wk, bk, push.wrapping_up())];
+                }
+            }
+            if result & WIN != 0 {
+                WIN
+            } else if result & UNKNOWN != 0 {
+                UNKNOWN
+            } else {
+                DRAW
+            }
+        },
+        Color::Black => {
+            for sq in bk.king_attacks() {
+                result |= db[index(Color::White, wk, sq, pawn)];
+            }
+            if result & DRAW != 0 {
+                DRAW
+            } else if result & UNKNOWN != 0 {
+                UNKNOWN
+            } else {
+                WIN
+            }
+        },
+    }
+}
+
+fn generate() -> Vec<u64> {
+    let mut db: Vec<u8> = (0..SIZE).map(initial).collect();
+
+    // Iterate until every reachable position is resolved; those left unknown
+    // can never be won
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..SIZE {
+            if db[idx] == UNKNOWN {
+                let result = classify(&db, idx);
+                if result != UNKNOWN {
+                    db[idx] = result;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut bits = vec![0u64; SIZE / 64];
+    for (idx, &result) in db.iter().enumerate() {
+        if result == WIN {
+            bits[idx / 64] |= 1 << (idx % 64);
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_known() {
+        use self::Square::*;
+        let cases = [
+            // The king in front of the pawn on the sixth rank
+            (D6, D5, D8, Color::White, true),
+            (D6, D5, D8, Color::Black, true),
+            // The defending king holds the opposition
+            (D5, D4, D7, Color::White, false),
+            (D5, D4, D7, Color::Black, true),
+            // The rook pawn is drawn with the king in the corner
+            (B6, A5, A8, Color::White, false),
+            // The pawn is taken
+            (H1, D4, D5, Color::White, false),
+            // The pawn outruns the king
+            (H1, A5, E6, Color::White, true),
+            (H1, A5, E6, Color::Black, false),
+        ];
+        for &(wk, pawn, bk, player, win) in cases.iter() {
+            assert_eq!(probe(wk, pawn, bk, player), win, "{:?} {:?} {:?} {:?}", wk, pawn, bk, player);
+        }
+    }
+}
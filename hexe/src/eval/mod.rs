@@ -0,0 +1,287 @@
+//! Static position evaluation.
+//!
+//! Scores are measured in centipawns from the perspective of the player to
+//! move. Material and piece-square values are tapered between the middle game
+//! and the end game by the amount of non-pawn material left on the board.
+//! Positions may instead be evaluated by a network; see [`nnue`].
+//!
+//! [`nnue`]: ../nnue/index.html
+
+use std::cmp;
+
+use core::board::Variant;
+use prelude::*;
+
+pub mod endgame;
+mod kpk;
+
+mod params;
+pub use self::params::Params;
+
+mod trace;
+pub use self::trace::{trace, Term, Trace};
+
+#[cfg(test)]
+mod tests;
+
+/// The value of each role in the middle game, indexed by `Role`.
+pub const MG_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+
+/// The value of each role in the end game, indexed by `Role`.
+pub const EG_VALUES: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+/// The game phase contributed by each role, indexed by `Role`.
+const PHASE_INC: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+/// The game phase with all non-pawn material on the board.
+pub(crate) const MAX_PHASE: i32 = 24;
+
+/// The bonus given to the player to move.
+const TEMPO: i32 = 10;
+
+/// The bonus for the number of checks given in Three-check, indexed by that
+/// number. A third check wins the game.
+const CHECKS_GIVEN: [i32; 3] = [0, 150, 400];
+
+/// The bonus for a king in King of the Hill, indexed by its distance to the
+/// center. Reaching the center wins the game.
+const KING_CENTER: [i32; 4] = [0, 120, 40, 0];
+
+// Piece-square tables from White's perspective, laid out visually with A8 as
+// the first entry.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const MG_PST: [[i32; 64]; 6] = [
+    [ // Pawn
+          0,   0,   0,   0,   0,   0,  0,   0,
+         98, 134,  61,  95,  68, 126, 34, -11,
+         -6,   7,  26,  31,  65,  56, 25, -20,
+        -14,  13,   6,  21,  23,  12, 17, -23,
+        -27,  -2,  -5,  12,  17,   6, 10, -25,
+        -26,  -4,  -4, -10,   3,   3, 33, -12,
+        -35,  -1, -20, -23, -15,  24, 38, -22,
+          0,   0,   0,   0,   0,   0,  0,   0,
+    ],
+    [ // Knight
+        -167, -89, -34, -49,  61, -97, -15, -107,
+         -73, -41,  72,  36,  23,  62,   7,  -17,
+         -47,  60,  37,  65,  84, 129,  73,   44,
+          -9,  17,  19,  53,  37,  69,  18,   22,
+         -13,   4,  16,  13,  28,  19,  21,   -8,
+         -23,  -9,  12,  10,  19,  17,  25,  -16,
+         -29, -53, -12,  -3,  -1,  18, -14,  -19,
+        -105, -21, -58, -33, -17, -28, -19,  -23,
+    ],
+    [ // Bishop
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    [ // Rook
+         32,  42,  32,  51, 63,  9,  31,  43,
+         27,  32,  58,  62, 80, 67,  26,  44,
+         -5,  19,  26,  36, 17, 45,  61,  16,
+        -24, -11,   7,  26, 24, 35,  -8, -20,
+        -36, -26, -12,  -1,  9, -7,   6, -23,
+        -45, -25, -16, -17,  3,  0,  -5, -33,
+        -44, -16, -20,  -9, -1, 11,  -6, -71,
+        -19, -13,   1,  17, 16,  7, -37, -26,
+    ],
+    [ // Queen
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    [ // King
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const EG_PST: [[i32; 64]; 6] = [
+    [ // Pawn
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [ // Knight
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    [ // Bishop
+        -14, -21, -11,  -8, -7,  -9, -17, -24,
+         -8,  -4,   7, -12, -3, -13,  -4, -14,
+          2,  -8,   0,  -1, -2,   6,   0,   4,
+         -3,   9,  12,   9, 14,  10,   3,   2,
+         -6,   3,  13,  19,  7,  10,  -3,  -9,
+        -12,  -3,   8,  10, 13,   3,  -7, -15,
+        -14, -18,  -7,  -1,  4,  -9, -15, -27,
+        -23,  -9, -23,  -5, -9, -16,  -5, -17,
+    ],
+    [ // Rook
+        13, 10, 18, 15, 12,  12,   8,   5,
+        11, 13, 13, 11, -3,   3,   8,   3,
+         7,  7,  7,  5,  4,  -3,  -5,  -3,
+         4,  3, 13,  1,  2,   1,  -1,   2,
+         3,  5,  8,  4, -5,  -6,  -8, -11,
+        -4,  0, -5, -1, -7, -12,  -8, -16,
+        -6, -6,  0,  2, -9,  -9, -11,  -3,
+        -9,  2,  3, -1, -5, -13,   4, -20,
+    ],
+    [ // Queen
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    [ // King
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+/// Returns the index into a piece-square table for `piece` on `square`.
+#[inline]
+pub(crate) fn pst_index(piece: Piece, square: Square) -> usize {
+    match piece.color() {
+        Color::White => square as usize ^ 56,
+        Color::Black => square as usize,
+    }
+}
+
+/// Returns the middle game and end game values of `piece` on `square` from
+/// White's perspective.
+#[inline]
+fn piece_value(params: &Params, piece: Piece, square: Square) -> (i32, i32) {
+    let role = piece.role() as usize;
+    let index = pst_index(piece, square);
+    let mg = params.mg_values[role] + params.mg_pst[role][index];
+    let eg = params.eg_values[role] + params.eg_pst[role][index];
+    match piece.color() {
+        Color::White => (mg, eg),
+        Color::Black => (-mg, -eg),
+    }
+}
+
+/// Returns the game phase of `pos`, ranging from 0 (end game) to 24 (opening).
+pub fn phase(pos: &Position) -> i32 {
+    let phase = pos.pieces().iter().fold(0, |phase, (_, &piece)| {
+        phase + PHASE_INC[piece.role() as usize]
+    });
+    phase.min(MAX_PHASE)
+}
+
+/// Blends the middle game and end game scores by `phase`.
+#[inline]
+pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// Evaluates `pos` from the perspective of the player to move.
+///
+/// Endgames with a specialized evaluation in `endgame` are scored by it.
+/// Otherwise, the network set on `pos` is used if any, falling back to the
+/// [`classical`](fn.classical.html) evaluation. The progress of each player
+/// toward the goal of the variant is then added by
+/// [`variant`](fn.variant.html).
+pub fn evaluate(pos: &Position) -> i32 {
+    let score = if let Some(score) = endgame::evaluate(pos) {
+        score
+    } else if let Some(nnue) = pos.accumulators() {
+        nnue.evaluate(pos.player())
+    } else {
+        classical(pos)
+    };
+    score + variant(pos)
+}
+
+/// Evaluates the progress of each player toward the goal of the variant of
+/// `pos` from the perspective of the player to move.
+///
+/// In Three-check, this is a bonus for the checks given. In King of the Hill,
+/// it is a bonus for each king by its distance to the center. Variants won by
+/// checkmate alone score 0.
+pub fn variant(pos: &Position) -> i32 {
+    let score = match pos.variant() {
+        Variant::ThreeCheck => {
+            let given = |color| 3 - pos.remaining_checks(color) as usize;
+            // A third check ends the game before it is evaluated
+            CHECKS_GIVEN[cmp::min(given(Color::White), 2)]
+                - CHECKS_GIVEN[cmp::min(given(Color::Black), 2)]
+        },
+        Variant::KingOfTheHill => {
+            let king = |color| KING_CENTER[pos.king_square(color).center_distance()];
+            king(Color::White) - king(Color::Black)
+        },
+        _ => 0,
+    };
+    match pos.player() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Evaluates `pos` from the perspective of the player to move using tapered
+/// material and piece-square values.
+#[inline]
+pub fn classical(pos: &Position) -> i32 {
+    classical_with(pos, &Params::DEFAULT)
+}
+
+/// Evaluates `pos` like [`classical`](fn.classical.html) with the weights
+/// of `params`.
+pub fn classical_with(pos: &Position, params: &Params) -> i32 {
+    let mut mg = 0;
+    let mut eg = 0;
+    for (square, &piece) in pos.pieces() {
+        let (m, e) = piece_value(params, piece, square);
+        mg += m;
+        eg += e;
+    }
+
+    let strong = if eg > 0 { Color::White } else { Color::Black };
+    let eg = eg * endgame::scale(pos, strong) / endgame::SCALE_NORMAL;
+
+    let score = taper(mg, eg, phase(pos));
+    match pos.player() {
+        Color::White => score + params.tempo,
+        Color::Black => params.tempo - score,
+    }
+}
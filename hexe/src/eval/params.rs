@@ -0,0 +1,134 @@
+use std::fmt;
+
+use super::{EG_PST, EG_VALUES, MG_PST, MG_VALUES, TEMPO};
+
+/// The weights of the classical evaluation.
+///
+/// Weights can be converted to and from a flat vector, laid out as the fields
+/// in order, for tuning. Formatting with `Display` writes them as Rust
+/// constants in the layout used by this module.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Params {
+    /// The value of each role in the middle game.
+    pub mg_values: [i32; 6],
+    /// The value of each role in the end game.
+    pub eg_values: [i32; 6],
+    /// The middle game piece-square tables from White's perspective, with A8
+    /// as the first entry.
+    pub mg_pst: [[i32; 64]; 6],
+    /// The end game piece-square tables from White's perspective, with A8 as
+    /// the first entry.
+    pub eg_pst: [[i32; 64]; 6],
+    /// The bonus given to the player to move.
+    pub tempo: i32,
+}
+
+impl Default for Params {
+    #[inline]
+    fn default() -> Params {
+        Params::DEFAULT
+    }
+}
+
+impl fmt::Debug for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Params")
+            .field("mg_values", &self.mg_values)
+            .field("eg_values", &self.eg_values)
+            .field("tempo", &self.tempo)
+            .finish()
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn values(f: &mut fmt::Formatter, name: &str, values: &[i32; 6]) -> fmt::Result {
+            write!(f, "pub const {}: [i32; 6] = [", name)?;
+            for (i, value) in values.iter().enumerate() {
+                let sep = if i == 0 { "" } else { ", " };
+                write!(f, "{}{}", sep, value)?;
+            }
+            writeln!(f, "];")
+        }
+
+        fn pst(f: &mut fmt::Formatter, name: &str, tables: &[[i32; 64]; 6]) -> fmt::Result {
+            const ROLES: [&str; 6] = ["Pawn", "Knight", "Bishop", "Rook", "Queen", "King"];
+            writeln!(f, "const {}: [[i32; 64]; 6] = [", name)?;
+            for (table, role) in tables.iter().zip(ROLES.iter()) {
+                writeln!(f, "    [ // {}", role)?;
+                for row in table.chunks(8) {
+                    write!(f, "       ")?;
+                    for value in row {
+                        write!(f, " {:4},", value)?;
+                    }
+                    writeln!(f)?;
+                }
+                writeln!(f, "    ],")?;
+            }
+            writeln!(f, "];")
+        }
+
+        values(f, "MG_VALUES", &self.mg_values)?;
+        values(f, "EG_VALUES", &self.eg_values)?;
+        writeln!(f, "const TEMPO: i32 = {};", self.tempo)?;
+        pst(f, "MG_PST", &self.mg_pst)?;
+        pst(f, "EG_PST", &self.eg_pst)
+    }
+}
+
+impl Params {
+    /// The weights used by [`classical`](fn.classical.html).
+    pub const DEFAULT: Params = Params {
+        mg_values: MG_VALUES,
+        eg_values: EG_VALUES,
+        mg_pst: MG_PST,
+        eg_pst: EG_PST,
+        tempo: TEMPO,
+    };
+
+    /// The number of weights.
+    pub const LEN: usize = Params::TEMPO + 1;
+
+    /// The offset of `mg_values` within the flat vector.
+    pub const MG_VALUES: usize = 0;
+    /// The offset of `eg_values` within the flat vector.
+    pub const EG_VALUES: usize = Params::MG_VALUES + 6;
+    /// The offset of `mg_pst` within the flat vector.
+    pub const MG_PST: usize = Params::EG_VALUES + 6;
+    /// The offset of `eg_pst` within the flat vector.
+    pub const EG_PST: usize = Params::MG_PST + 6 * 64;
+    /// The offset of `tempo` within the flat vector.
+    pub const TEMPO: usize = Params::EG_PST + 6 * 64;
+
+    /// Returns the weights as a flat vector.
+    pub fn to_vec(&self) -> Vec<i32> {
+        let mut vec = Vec::with_capacity(Params::LEN);
+        vec.extend_from_slice(&self.mg_values);
+        vec.extend_from_slice(&self.eg_values);
+        vec.extend(self.mg_pst.iter().flat_map(|table| table.iter()));
+        vec.extend(self.eg_pst.iter().flat_map(|table| table.iter()));
+        vec.push(self.tempo);
+        vec
+    }
+
+    /// Creates weights from a flat vector, returning `None` if its length is
+    /// not `Params::LEN`.
+    pub fn from_slice(slice: &[i32]) -> Option<Params> {
+        if slice.len() != Params::LEN {
+            return None;
+        }
+        let mut params = Params::DEFAULT;
+        params.mg_values.copy_from_slice(&slice[Params::MG_VALUES..Params::EG_VALUES]);
+        params.eg_values.copy_from_slice(&slice[Params::EG_VALUES..Params::MG_PST]);
+        for (i, table) in params.mg_pst.iter_mut().enumerate() {
+            let start = Params::MG_PST + i * 64;
+            table.copy_from_slice(&slice[start..start + 64]);
+        }
+        for (i, table) in params.eg_pst.iter_mut().enumerate() {
+            let start = Params::EG_PST + i * 64;
+            table.copy_from_slice(&slice[start..start + 64]);
+        }
+        params.tempo = slice[Params::TEMPO];
+        Some(params)
+    }
+}
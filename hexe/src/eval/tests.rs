@@ -0,0 +1,84 @@
+use super::*;
+use core::board::Variant;
+use core::fen::Fen;
+
+fn position(fen: &str) -> Position {
+    let fen: Fen = fen.parse().unwrap();
+    Position::from_fen(&fen).unwrap()
+}
+
+#[test]
+fn standard_is_tempo() {
+    assert_eq!(evaluate(&Position::default()), TEMPO);
+    assert_eq!(phase(&Position::default()), MAX_PHASE);
+}
+
+#[test]
+fn symmetric() {
+    let pairs = [
+        ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+         "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3"),
+        ("8/5k2/8/3p4/8/2N5/5K2/8 w - - 0 1",
+         "8/5k2/2n5/8/3P4/8/5K2/8 b - - 0 1"),
+    ];
+    for &(a, b) in pairs.iter() {
+        assert_eq!(evaluate(&position(a)), evaluate(&position(b)), "{}", a);
+    }
+}
+
+#[test]
+fn material() {
+    let up = position("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+    let down = position("4k3/8/8/8/8/8/8/R3K3 b - - 0 1");
+    assert!(evaluate(&up) > 400);
+    assert!(evaluate(&down) < -400);
+}
+
+#[test]
+#[allow(deprecated)] // `trim_start` requires Rust 1.30
+fn trace_matches_evaluate() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 3",
+        "8/5k2/8/3p4/8/2N5/5K2/8 w - - 0 1",
+        "8/8/8/4k3/8/8/3KP3/8 b - - 0 1",
+        "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let pos = position(fen);
+        let trace = trace(&pos);
+        assert_eq!(trace.classical, classical(&pos), "{}", fen);
+        assert_eq!(trace.score, evaluate(&pos), "{}", fen);
+        assert_eq!(trace.endgame, endgame::evaluate(&pos), "{}", fen);
+        assert_eq!(trace.network, None);
+    }
+
+    let trace = trace(&Position::default());
+    assert_eq!(trace.total(), (0, 0));
+    assert_eq!(trace.terms[0].mg[0], trace.terms[0].mg[1]);
+    let text = trace.to_string();
+    assert!(text.lines().any(|line| line.trim_left().starts_with("Material |")));
+    assert!(text.ends_with("Final:          +10 (White)"));
+}
+
+#[test]
+fn three_check() {
+    let fen: Fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +2+0".parse().unwrap();
+    let pos = Position::from_fen_variant(&fen, Variant::ThreeCheck).unwrap();
+    assert_eq!(variant(&pos), CHECKS_GIVEN[2]);
+    assert_eq!(evaluate(&pos), TEMPO + CHECKS_GIVEN[2]);
+    assert_eq!(variant(&Position::from_fen(&fen).unwrap()), 0);
+
+    let trace = trace(&pos);
+    assert_eq!(trace.score, evaluate(&pos));
+    assert!(trace.to_string().contains("Variant:        +400 (White)"));
+}
+
+#[test]
+fn king_of_the_hill() {
+    let fen: Fen = "4k3/8/8/8/8/4K3/8/8 b - - 0 1".parse().unwrap();
+    let pos = Position::from_fen_variant(&fen, Variant::KingOfTheHill).unwrap();
+    assert_eq!(variant(&pos), -KING_CENTER[1]);
+    assert!(evaluate(&pos) < -100);
+    assert_eq!(variant(&Position::from_fen(&fen).unwrap()), 0);
+}
@@ -0,0 +1,179 @@
+//! Breaking down the evaluation of a position into its terms.
+
+use std::fmt;
+
+use super::*;
+
+/// The middle game and end game values of an evaluation term for each color.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Term {
+    /// The name of the term.
+    pub name: &'static str,
+    /// The middle game value for each color from its own perspective,
+    /// indexed by `Color`.
+    pub mg: [i32; 2],
+    /// The end game value for each color from its own perspective, indexed
+    /// by `Color`.
+    pub eg: [i32; 2],
+}
+
+impl Term {
+    /// Returns the middle game and end game values from White's perspective.
+    #[inline]
+    pub fn total(&self) -> (i32, i32) {
+        (self.mg[0] - self.mg[1], self.eg[0] - self.eg[1])
+    }
+}
+
+/// The evaluation of a position broken down into its terms, returned by
+/// [`trace`](fn.trace.html).
+///
+/// Formatting with `Display` writes a table of the terms followed by how
+/// they combine into the final score.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    /// The terms of the classical evaluation.
+    pub terms: Vec<Term>,
+    /// The factor out of [`SCALE_NORMAL`](endgame/constant.SCALE_NORMAL.html)
+    /// applied to the end game total for drawish endgames.
+    pub scale: i32,
+    /// The game phase, from 0 (end game) to 24 (opening).
+    pub phase: i32,
+    /// The middle game and end game totals blended by `phase`, from White's
+    /// perspective.
+    pub tapered: i32,
+    /// The bonus given to the player to move.
+    pub tempo: i32,
+    /// The classical evaluation from the perspective of the player to move.
+    pub classical: i32,
+    /// The score of a specialized endgame evaluation, if any, from the
+    /// perspective of the player to move.
+    pub endgame: Option<i32>,
+    /// The score of the network set on the position, if any, from the
+    /// perspective of the player to move.
+    pub network: Option<i32>,
+    /// The progress toward the goal of the variant, as returned by
+    /// [`variant`](fn.variant.html), from the perspective of the player to
+    /// move.
+    pub variant: i32,
+    /// The final evaluation, as returned by [`evaluate`](fn.evaluate.html).
+    pub score: i32,
+    /// The player to move.
+    pub player: Color,
+}
+
+impl Trace {
+    /// Returns the middle game and end game totals of all terms from White's
+    /// perspective, before scaling.
+    pub fn total(&self) -> (i32, i32) {
+        self.terms.iter().fold((0, 0), |(mg, eg), term| {
+            let (m, e) = term.total();
+            (mg + m, eg + e)
+        })
+    }
+
+    /// Returns `score` from White's perspective.
+    #[inline]
+    fn white(&self, score: i32) -> i32 {
+        match self.player {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const RULE: &str = "-------------+-------------+-------------+-------------";
+
+        fn row(f: &mut fmt::Formatter, name: &str, values: [i32; 6]) -> fmt::Result {
+            writeln!(f, "{:>12} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}",
+                     name, values[0], values[1], values[2], values[3], values[4], values[5])
+        }
+
+        writeln!(f, "        Term |    White    |    Black    |    Total")?;
+        writeln!(f, "             |   MG    EG  |   MG    EG  |   MG    EG")?;
+        writeln!(f, "{}", RULE)?;
+        for term in self.terms.iter() {
+            let (mg, eg) = term.total();
+            row(f, term.name, [term.mg[0], term.eg[0], term.mg[1], term.eg[1], mg, eg])?;
+        }
+        writeln!(f, "{}", RULE)?;
+
+        let (mg, eg) = self.total();
+        let (white, black) = self.terms.iter().fold(([0; 2], [0; 2]), |(w, b), term| {
+            ([w[0] + term.mg[0], w[1] + term.eg[0]], [b[0] + term.mg[1], b[1] + term.eg[1]])
+        });
+        row(f, "Total", [white[0], white[1], black[0], black[1], mg, eg])?;
+        writeln!(f)?;
+
+        writeln!(f, "Endgame scale:  {}/{}", self.scale, endgame::SCALE_NORMAL)?;
+        writeln!(f, "Phase:          {}/{}", self.phase, MAX_PHASE)?;
+        writeln!(f, "Tapered:        {:+} (White)", self.tapered)?;
+        writeln!(f, "Tempo:          {:+} ({})", self.tempo, self.player)?;
+        writeln!(f, "Classical:      {:+} (White)", self.white(self.classical))?;
+        if let Some(score) = self.endgame {
+            writeln!(f, "Endgame:        {:+} (White)", self.white(score))?;
+        }
+        if let Some(score) = self.network {
+            writeln!(f, "Network:        {:+} (White)", self.white(score))?;
+        }
+        if self.variant != 0 {
+            writeln!(f, "Variant:        {:+} (White)", self.white(self.variant))?;
+        }
+        write!(f, "Final:          {:+} (White)", self.white(self.score))
+    }
+}
+
+/// Evaluates `pos` like [`evaluate`](fn.evaluate.html), recording each term
+/// of the classical evaluation along with the scores that take precedence
+/// over it.
+///
+/// # Examples
+///
+/// ```
+/// use hexe::eval;
+/// use hexe::position::Position;
+///
+/// let trace = eval::trace(&Position::default());
+/// assert_eq!(trace.score, eval::evaluate(&Position::default()));
+/// println!("{}", trace);
+/// ```
+pub fn trace(pos: &Position) -> Trace {
+    let params = &Params::DEFAULT;
+    let mut material = Term { name: "Material", ..Default::default() };
+    let mut pst = Term { name: "Piece-square", ..Default::default() };
+    for (square, &piece) in pos.pieces() {
+        let role = piece.role() as usize;
+        let color = piece.color() as usize;
+        let index = pst_index(piece, square);
+        material.mg[color] += params.mg_values[role];
+        material.eg[color] += params.eg_values[role];
+        pst.mg[color] += params.mg_pst[role][index];
+        pst.eg[color] += params.eg_pst[role][index];
+    }
+
+    let mut trace = Trace {
+        terms: vec![material, pst],
+        scale: endgame::SCALE_NORMAL,
+        phase: phase(pos),
+        tapered: 0,
+        tempo: params.tempo,
+        classical: 0,
+        endgame: endgame::evaluate(pos),
+        network: pos.accumulators().map(|nnue| nnue.evaluate(pos.player())),
+        variant: variant(pos),
+        score: evaluate(pos),
+        player: pos.player(),
+    };
+
+    let (mg, eg) = trace.total();
+    let strong = if eg > 0 { Color::White } else { Color::Black };
+    trace.scale = endgame::scale(pos, strong);
+    trace.tapered = taper(mg, eg * trace.scale / endgame::SCALE_NORMAL, trace.phase);
+    trace.classical = match trace.player {
+        Color::White => trace.tapered + trace.tempo,
+        Color::Black => trace.tempo - trace.tapered,
+    };
+    trace
+}
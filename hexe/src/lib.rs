@@ -96,11 +96,12 @@ mod log {
     macro_rules! trace { ($($t:tt)*) => {} }
     macro_rules! debug { ($($t:tt)*) => {} }
     macro_rules! info  { ($($t:tt)*) => {} }
-    macro_rules! warn  { ($($t:tt)*) => {} }
     macro_rules! error { ($($t:tt)*) => {} }
 }
 
 extern crate crossbeam_deque;
+#[macro_use]
+extern crate lazy_static;
 extern crate libc;
 extern crate num_cpus;
 extern crate parking_lot;
@@ -109,6 +110,9 @@ extern crate uncon;
 #[cfg(any(test, feature = "rand"))]
 extern crate rand;
 
+#[cfg(feature = "simd")]
+extern crate packed_simd;
+
 #[cfg(test)]
 #[macro_use]
 extern crate static_assertions;
@@ -126,6 +130,10 @@ macro_rules! authors {
 #[doc(inline)]
 pub use core::{board, castle, color, fen, iter, misc, mv, piece, square};
 
+#[cfg(feature = "svg")]
+#[doc(inline)]
+pub use core::svg;
+
 #[allow(unused_imports)]
 use core::_shared::*;
 
@@ -136,8 +144,13 @@ mod util;
 mod zero;
 
 pub mod engine;
+pub mod datagen;
+pub mod eval;
+pub mod nnue;
 pub mod position;
 pub mod prelude;
+pub mod syzygy;
+pub mod tune;
 pub mod zobrist;
 
 #[doc(inline)] pub use self::engine::Engine;
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use board::MultiBoard;
+use prelude::*;
+
+use super::{simd, Network};
+
+/// The pieces removed and added by a move.
+#[derive(Default)]
+pub(crate) struct Delta {
+    removed: [Option<(Piece, Square)>; 2],
+    added: [Option<(Piece, Square)>; 2],
+}
+
+impl Delta {
+    #[inline]
+    pub fn remove(&mut self, piece: Piece, square: Square) {
+        Delta::insert(&mut self.removed, piece, square);
+    }
+
+    #[inline]
+    pub fn add(&mut self, piece: Piece, square: Square) {
+        Delta::insert(&mut self.added, piece, square);
+    }
+
+    #[inline]
+    fn insert(slots: &mut [Option<(Piece, Square)>; 2], piece: Piece, square: Square) {
+        let slot = if slots[0].is_none() { 0 } else { 1 };
+        debug_assert!(slots[slot].is_none());
+        slots[slot] = Some((piece, square));
+    }
+}
+
+/// The hidden layer inputs of a network for each position reached.
+///
+/// Each entry holds the values for both perspectives, White's first. Moves
+/// push an entry updated from the previous one, rather than from scratch,
+/// unless the king of a perspective moved.
+#[derive(Clone)]
+pub struct Accumulators {
+    network: Arc<Network>,
+    stack: Vec<i16>,
+}
+
+impl Accumulators {
+    /// Creates accumulators for the pieces on `board`.
+    pub fn new(network: Arc<Network>, board: &MultiBoard) -> Accumulators {
+        let mut stack = vec![0; 2 * network.hidden];
+        {
+            let (white, black) = stack.split_at_mut(network.hidden);
+            network.refresh(white, board, Color::White);
+            network.refresh(black, board, Color::Black);
+        }
+        Accumulators { network, stack }
+    }
+
+    /// Returns the network being evaluated.
+    #[inline]
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Returns the values for `perspective` in the current position.
+    #[inline]
+    pub fn values(&self, perspective: Color) -> &[i16] {
+        let hidden = self.network.hidden;
+        let start = self.stack.len() - (2 - perspective as usize) * hidden;
+        &self.stack[start..start + hidden]
+    }
+
+    /// Evaluates the current position from the perspective of `player`.
+    #[inline]
+    pub fn evaluate(&self, player: Color) -> i32 {
+        self.network.output(self.values(player), self.values(!player))
+    }
+
+    /// Pushes the values after `delta` was applied to `board`.
+    pub(crate) fn push(&mut self, board: &MultiBoard, delta: &Delta) {
+        let hidden = self.network.hidden;
+        let start = self.stack.len();
+        self.stack.resize(start + 2 * hidden, 0);
+
+        let (previous, next) = self.stack.split_at_mut(start);
+        next.copy_from_slice(&previous[start - 2 * hidden..]);
+        let (white, black) = next.split_at_mut(hidden);
+        update(&self.network, white, board, Color::White, delta);
+        update(&self.network, black, board, Color::Black, delta);
+    }
+
+    /// Pushes the same values for a null move.
+    pub(crate) fn push_null(&mut self) {
+        let len = 2 * self.network.hidden;
+        let start = self.stack.len();
+        self.stack.resize(start + len, 0);
+
+        let (previous, next) = self.stack.split_at_mut(start);
+        next.copy_from_slice(&previous[start - len..]);
+    }
+
+    /// Pops the values pushed by the last move, recomputing them from `board`
+    /// if the move was made before the accumulators were created.
+    pub(crate) fn pop(&mut self, board: &MultiBoard) {
+        let len = self.stack.len() - 2 * self.network.hidden;
+        if len > 0 {
+            self.stack.truncate(len);
+        } else {
+            *self = Accumulators::new(self.network.clone(), board);
+        }
+    }
+}
+
+/// Applies `delta` to the values of `perspective`.
+fn update(network: &Network, values: &mut [i16], board: &MultiBoard, perspective: Color, delta: &Delta) {
+    let king = Piece::new(Role::King, perspective);
+    let moved = delta.removed.iter().any(|&entry| {
+        entry.map(|(piece, _)| piece) == Some(king)
+    });
+    if moved {
+        return network.refresh(values, board, perspective);
+    }
+
+    let king = board.first(king).expect("missing king");
+    for &(piece, square) in delta.removed.iter().flat_map(|entry| entry) {
+        if let Some(idx) = network.features.index(perspective, king, piece, square) {
+            simd::sub(values, network.column(idx));
+        }
+    }
+    for &(piece, square) in delta.added.iter().flat_map(|entry| entry) {
+        if let Some(idx) = network.features.index(perspective, king, piece, square) {
+            simd::add(values, network.column(idx));
+        }
+    }
+}
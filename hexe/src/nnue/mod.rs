@@ -0,0 +1,288 @@
+//! Efficiently updatable neural network (NNUE) evaluation.
+//!
+//! A network has a single hidden layer for each perspective, fed by features
+//! of the pieces relative to the king of that perspective. The layer's inputs
+//! are kept in [`Accumulators`] that are updated as moves are made, so that
+//! evaluating a position only costs the output layer.
+//!
+//! # Format
+//!
+//! Networks are stored as little-endian values:
+//!
+//! | Field          | Type                            |
+//! | :------------- | :------------------------------ |
+//! | Magic          | `b"HXNN"`                       |
+//! | Version        | `u32`, currently 1              |
+//! | Feature set    | `u32`: 0 for HalfKP, 1 for HalfKAv2 |
+//! | Hidden size    | `u32`, a multiple of 16         |
+//! | Hidden biases  | `i16` for each hidden value     |
+//! | Hidden weights | `i16` for each hidden value, for each input feature |
+//! | Output weights | `i16` for each hidden value of the player to move, then of the opponent |
+//! | Output bias    | `i32`                           |
+//!
+//! Hidden values are quantized by [`QA`] and output weights by [`QB`]; the
+//! output is scaled by [`SCALE`] to centipawns.
+//!
+//! [`Accumulators`]: struct.Accumulators.html
+//! [`QA`]: constant.QA.html
+//! [`QB`]: constant.QB.html
+//! [`SCALE`]: constant.SCALE.html
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use board::MultiBoard;
+use prelude::*;
+
+mod accumulator;
+pub use self::accumulator::*;
+
+mod simd;
+
+#[cfg(test)]
+mod tests;
+
+/// The magic bytes at the start of a network file.
+pub const MAGIC: [u8; 4] = *b"HXNN";
+
+/// The network file version supported.
+pub const VERSION: u32 = 1;
+
+/// The quantization of hidden values, which are clamped to `0...QA`.
+pub const QA: i32 = 255;
+
+/// The quantization of output weights.
+pub const QB: i32 = 64;
+
+/// The factor from the network output to centipawns.
+pub const SCALE: i32 = 400;
+
+/// The input features of a network.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FeatureSet {
+    /// Each non-king piece on each square, for each square of the king.
+    HalfKP,
+    /// Each piece on each square, for each square of the king, with both
+    /// kings sharing a piece index.
+    HalfKAv2,
+}
+
+impl FeatureSet {
+    /// Returns the number of input features.
+    #[inline]
+    pub fn inputs(self) -> usize {
+        64 * 64 * self.pieces()
+    }
+
+    #[inline]
+    fn pieces(self) -> usize {
+        match self {
+            FeatureSet::HalfKP => 10,
+            FeatureSet::HalfKAv2 => 11,
+        }
+    }
+
+    /// Returns the index of the feature for `piece` on `square` from the
+    /// perspective of `perspective`, whose king is on `king`.
+    #[inline]
+    pub fn index(self, perspective: Color, king: Square, piece: Piece, square: Square) -> Option<usize> {
+        let piece_idx = match (self, piece.role()) {
+            (FeatureSet::HalfKP, Role::King) => return None,
+            (FeatureSet::HalfKAv2, Role::King) => 10,
+            (_, role) => 2 * role as usize + (piece.color() != perspective) as usize,
+        };
+        let (king, square) = match perspective {
+            Color::White => (king, square),
+            Color::Black => (king.rev_rank(), square.rev_rank()),
+        };
+        Some((king as usize * self.pieces() + piece_idx) * 64 + square as usize)
+    }
+
+    fn from_u32(n: u32) -> Option<FeatureSet> {
+        match n {
+            0 => Some(FeatureSet::HalfKP),
+            1 => Some(FeatureSet::HalfKAv2),
+            _ => None,
+        }
+    }
+}
+
+/// A quantized network.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Network {
+    features: FeatureSet,
+    hidden: usize,
+    hidden_biases: Vec<i16>,
+    hidden_weights: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("features", &self.features)
+            .field("hidden", &self.hidden)
+            .finish()
+    }
+}
+
+impl Network {
+    /// Creates a network from its parameters, or `None` if their lengths do
+    /// not match the feature set and hidden size.
+    pub fn new(
+        features: FeatureSet,
+        hidden_biases: Vec<i16>,
+        hidden_weights: Vec<i16>,
+        output_weights: Vec<i16>,
+        output_bias: i32,
+    ) -> Option<Network> {
+        let hidden = hidden_biases.len();
+        if hidden == 0
+            || hidden % simd::LANES != 0
+            || hidden_weights.len() != features.inputs() * hidden
+            || output_weights.len() != 2 * hidden
+        {
+            return None;
+        }
+        Some(Network {
+            features,
+            hidden,
+            hidden_biases,
+            hidden_weights,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// Loads the network in the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Network> {
+        Network::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a network from `reader`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Network> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a network file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid("unsupported network version"));
+        }
+        let features = FeatureSet::from_u32(read_u32(&mut reader)?)
+            .ok_or_else(|| invalid("unknown feature set"))?;
+        let hidden = read_u32(&mut reader)? as usize;
+        if hidden == 0 || hidden % simd::LANES != 0 || hidden > 1 << 16 {
+            return Err(invalid("invalid hidden size"));
+        }
+
+        let hidden_biases = read_i16s(&mut reader, hidden)?;
+        let hidden_weights = read_i16s(&mut reader, features.inputs() * hidden)?;
+        let output_weights = read_i16s(&mut reader, 2 * hidden)?;
+        let output_bias = read_u32(&mut reader)? as i32;
+
+        Network::new(features, hidden_biases, hidden_weights, output_weights, output_bias)
+            .ok_or_else(|| invalid("mismatched network parameters"))
+    }
+
+    /// Saves `self` to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes `self` to `writer` in the format read by
+    /// [`read`](#method.read).
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        write_u32(&mut writer, VERSION)?;
+        let features = match self.features {
+            FeatureSet::HalfKP => 0u32,
+            FeatureSet::HalfKAv2 => 1,
+        };
+        write_u32(&mut writer, features)?;
+        write_u32(&mut writer, self.hidden as u32)?;
+        for values in [&self.hidden_biases, &self.hidden_weights, &self.output_weights].iter() {
+            for &value in values.iter() {
+                writer.write_all(&[value as u8, (value >> 8) as u8])?;
+            }
+        }
+        write_u32(&mut writer, self.output_bias as u32)
+    }
+
+    /// Returns the input features of `self`.
+    #[inline]
+    pub fn features(&self) -> FeatureSet {
+        self.features
+    }
+
+    /// Returns the number of hidden values for each perspective.
+    #[inline]
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// Evaluates the position on `board` from the perspective of `player`,
+    /// computing the hidden layer from scratch.
+    pub fn evaluate(&self, board: &MultiBoard, player: Color) -> i32 {
+        let mut us = vec![0; self.hidden];
+        let mut them = vec![0; self.hidden];
+        self.refresh(&mut us, board, player);
+        self.refresh(&mut them, board, !player);
+        self.output(&us, &them)
+    }
+
+    /// Returns the hidden weights of the input feature `idx`.
+    #[inline]
+    fn column(&self, idx: usize) -> &[i16] {
+        &self.hidden_weights[idx * self.hidden..(idx + 1) * self.hidden]
+    }
+
+    /// Computes the hidden values of `perspective` for `board`.
+    fn refresh(&self, values: &mut [i16], board: &MultiBoard, perspective: Color) {
+        values.copy_from_slice(&self.hidden_biases);
+        let king = board.first(Piece::new(Role::King, perspective)).expect("missing king");
+        for piece in Piece::ALL {
+            for square in board.bits(piece) {
+                if let Some(idx) = self.features.index(perspective, king, piece, square) {
+                    simd::add(values, self.column(idx));
+                }
+            }
+        }
+    }
+
+    /// Returns the score in centipawns given the hidden values of the player
+    /// to move and of the opponent.
+    fn output(&self, us: &[i16], them: &[i16]) -> i32 {
+        let (ours, theirs) = self.output_weights.split_at(self.hidden);
+        let sum = simd::crelu_dot(us, ours, QA as i16)
+            .wrapping_add(simd::crelu_dot(them, theirs, QA as i16))
+            .wrapping_add(self.output_bias);
+        (sum as i64 * SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.iter().rev().fold(0, |n, &b| n << 8 | b as u32))
+}
+
+fn write_u32<W: Write>(writer: &mut W, n: u32) -> io::Result<()> {
+    writer.write_all(&[n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8])
+}
+
+fn read_i16s<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<i16>> {
+    let mut buf = vec![0; 2 * len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.chunks(2).map(|b| (b[0] as u16 | (b[1] as u16) << 8) as i16).collect())
+}
@@ -0,0 +1,73 @@
+//! Kernels for updating accumulators and computing the network output.
+//!
+//! Slices must be of equal length, a multiple of `LANES`.
+
+/// The number of values processed at once.
+pub const LANES: usize = 16;
+
+#[cfg(feature = "simd")]
+mod imp {
+    use packed_simd::{i16x16, i32x16, FromCast};
+    use super::LANES;
+
+    /// Adds `weights` to `acc`.
+    #[inline]
+    pub fn add(acc: &mut [i16], weights: &[i16]) {
+        for (a, w) in acc.chunks_exact_mut(LANES).zip(weights.chunks_exact(LANES)) {
+            let sum = i16x16::from_slice_unaligned(a) + i16x16::from_slice_unaligned(w);
+            sum.write_to_slice_unaligned(a);
+        }
+    }
+
+    /// Subtracts `weights` from `acc`.
+    #[inline]
+    pub fn sub(acc: &mut [i16], weights: &[i16]) {
+        for (a, w) in acc.chunks_exact_mut(LANES).zip(weights.chunks_exact(LANES)) {
+            let diff = i16x16::from_slice_unaligned(a) - i16x16::from_slice_unaligned(w);
+            diff.write_to_slice_unaligned(a);
+        }
+    }
+
+    /// Returns the dot product of `weights` and `acc` clamped to `0...max`.
+    #[inline]
+    pub fn crelu_dot(acc: &[i16], weights: &[i16], max: i16) -> i32 {
+        let zero = i16x16::splat(0);
+        let max = i16x16::splat(max);
+        let mut sum = i32x16::splat(0);
+        for (a, w) in acc.chunks_exact(LANES).zip(weights.chunks_exact(LANES)) {
+            let a = i16x16::from_slice_unaligned(a).max(zero).min(max);
+            let w = i16x16::from_slice_unaligned(w);
+            sum += i32x16::from_cast(a) * i32x16::from_cast(w);
+        }
+        sum.wrapping_sum()
+    }
+}
+
+#[cfg(not(feature = "simd"))]
+mod imp {
+    /// Adds `weights` to `acc`.
+    #[inline]
+    pub fn add(acc: &mut [i16], weights: &[i16]) {
+        for (a, &w) in acc.iter_mut().zip(weights) {
+            *a = a.wrapping_add(w);
+        }
+    }
+
+    /// Subtracts `weights` from `acc`.
+    #[inline]
+    pub fn sub(acc: &mut [i16], weights: &[i16]) {
+        for (a, &w) in acc.iter_mut().zip(weights) {
+            *a = a.wrapping_sub(w);
+        }
+    }
+
+    /// Returns the dot product of `weights` and `acc` clamped to `0...max`.
+    #[inline]
+    pub fn crelu_dot(acc: &[i16], weights: &[i16], max: i16) -> i32 {
+        acc.iter().zip(weights).fold(0i32, |sum, (&a, &w)| {
+            sum.wrapping_add(a.max(0).min(max) as i32 * w as i32)
+        })
+    }
+}
+
+pub use self::imp::*;
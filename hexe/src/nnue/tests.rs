@@ -0,0 +1,127 @@
+use super::*;
+use std::sync::Arc;
+use core::fen::Fen;
+use core::mv::MoveVec;
+use position::Position;
+
+fn position(fen: &str) -> Position {
+    let fen: Fen = fen.parse().unwrap();
+    Position::from_fen(&fen).unwrap()
+}
+
+/// Returns a network with small pseudo-random parameters.
+fn network(features: FeatureSet, hidden: usize) -> Network {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut next = |range: i16| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % (2 * range as u64 + 1)) as i16 - range
+    };
+    let biases = (0..hidden).map(|_| next(64)).collect();
+    let weights = (0..features.inputs() * hidden).map(|_| next(16)).collect();
+    let output = (0..2 * hidden).map(|_| next(64)).collect();
+    Network::new(features, biases, weights, output, 1000).unwrap()
+}
+
+/// Checks that the incrementally updated values match those computed from
+/// scratch at every node up to `depth`.
+fn check_tree(pos: &mut Position, depth: usize) {
+    let fresh = Accumulators::new(pos.accumulators().unwrap().network().clone(), pos.board());
+    let nnue = pos.accumulators().unwrap();
+    for &color in [Color::White, Color::Black].iter() {
+        assert_eq!(nnue.values(color), fresh.values(color));
+    }
+    assert_eq!(nnue.evaluate(pos.player()),
+               nnue.network().evaluate(pos.board(), pos.player()));
+
+    if depth == 0 {
+        return;
+    }
+    let mut moves = MoveVec::new();
+    pos.gen(&mut moves).legal();
+    for &mv in moves.iter() {
+        pos.make_move(mv);
+        check_tree(pos, depth - 1);
+        pos.unmake_move(mv);
+    }
+    if !pos.in_check() {
+        pos.make_null();
+        check_tree(pos, 0);
+        pos.unmake_null();
+    }
+}
+
+#[test]
+fn incremental() {
+    let fens = [
+        // Castling, promotions and en passant
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "8/8/8/K7/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ];
+    for &features in [FeatureSet::HalfKP, FeatureSet::HalfKAv2].iter() {
+        let network = Arc::new(network(features, 16));
+        for fen in fens.iter() {
+            let mut pos = position(fen);
+            pos.set_network(Some(network.clone()));
+            check_tree(&mut pos, 2);
+        }
+    }
+}
+
+#[test]
+fn unmake_before_set() {
+    let network = Arc::new(network(FeatureSet::HalfKP, 16));
+    let mut pos = Position::default();
+    let mv = Move::normal(Square::E2, Square::E4);
+    pos.make_move(mv);
+    pos.set_network(Some(network.clone()));
+    pos.unmake_move(mv);
+
+    let fresh = Accumulators::new(network, pos.board());
+    assert_eq!(pos.accumulators().unwrap().values(Color::White), fresh.values(Color::White));
+}
+
+#[test]
+fn symmetric_features() {
+    let features = FeatureSet::HalfKAv2;
+    let white = features.index(Color::White, Square::E1, Piece::WhitePawn, Square::D2);
+    let black = features.index(Color::Black, Square::E8, Piece::BlackPawn, Square::D7);
+    assert_eq!(white, black);
+    assert_eq!(FeatureSet::HalfKP.index(Color::White, Square::E1, Piece::BlackKing, Square::E8), None);
+    assert!(features.index(Color::White, Square::H8, Piece::WhiteKing, Square::H8).unwrap()
+            < features.inputs());
+}
+
+#[test]
+fn read_write() {
+    let network = network(FeatureSet::HalfKAv2, 32);
+    let mut buf = Vec::new();
+    network.write(&mut buf).unwrap();
+    assert_eq!(buf.len(), 16 + 2 * (32 + FeatureSet::HalfKAv2.inputs() * 32 + 64) + 4);
+    assert!(Network::read(&buf[..]).unwrap() == network);
+
+    let mut bad = buf.clone();
+    bad[0] = b'X';
+    assert_eq!(Network::read(&bad[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    // Hidden sizes must fill whole vectors
+    let mut bad = buf.clone();
+    bad[12] = 20;
+    assert_eq!(Network::read(&bad[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    assert!(Network::read(&buf[..buf.len() - 1]).is_err());
+}
+
+#[test]
+fn output_bias() {
+    let features = FeatureSet::HalfKP;
+    let hidden = 16;
+    let network = Network::new(features,
+                               vec![0; hidden],
+                               vec![0; features.inputs() * hidden],
+                               vec![0; 2 * hidden],
+                               QA * QB).unwrap();
+    assert_eq!(network.evaluate(Position::default().board(), Color::White), SCALE);
+}
@@ -0,0 +1,72 @@
+//! Printing positions for debugging.
+
+use std::fmt;
+
+use prelude::*;
+use super::Position;
+
+/// Writes `square` in lowercase, as in FEN and moves, after a space.
+fn write_square(f: &mut fmt::Formatter, square: Square) -> fmt::Result {
+    square.map_str(|s| {
+        s.make_ascii_lowercase();
+        write!(f, " {}", s)
+    })
+}
+
+impl fmt::Display for Position {
+    /// Formats a diagram of the board from White's side followed by the FEN,
+    /// Zobrist and Polyglot keys, player to move, castle rights, en passant
+    /// square and checkers.
+    ///
+    /// The alternate flag (`{:#}`) draws pieces as Unicode figurines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::position::Position;
+    ///
+    /// let text = Position::default().to_string();
+    /// assert!(text.contains("| r | n | b | q | k | b | n | r | 8"));
+    /// assert!(text.contains("Side to move: White"));
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const RULE: &str = " +---+---+---+---+---+---+---+---+";
+
+        writeln!(f, "{}", RULE)?;
+        for rank in (0..8u8).rev() {
+            for file in 0..8u8 {
+                let square = Square::new(File::from(file), Rank::from(rank));
+                let ch = match self.pieces().get(square) {
+                    Some(&pc) if f.alternate() => pc.figurine(),
+                    Some(&pc) => char::from(pc),
+                    None => ' ',
+                };
+                write!(f, " | {}", ch)?;
+            }
+            writeln!(f, " | {}", rank + 1)?;
+            writeln!(f, "{}", RULE)?;
+        }
+        writeln!(f, "   a   b   c   d   e   f   g   h")?;
+        writeln!(f)?;
+
+        writeln!(f, "Fen: {}", self.to_fen())?;
+        writeln!(f, "Key: {:016X}", self.key())?;
+        writeln!(f, "Polyglot key: {:016X}", self.polyglot_key())?;
+        writeln!(f, "Side to move: {}", self.player())?;
+        writeln!(f, "Castling: {}", self.rights())?;
+        write!(f, "En passant:")?;
+        match self.en_passant() {
+            Some(ep) => write_square(f, ep)?,
+            None => write!(f, " -")?,
+        }
+        write!(f, "\nCheckers:")?;
+        let checkers = self.checkers();
+        if checkers.is_empty() {
+            write!(f, " -")?;
+        }
+        for square in checkers {
+            write_square(f, square)?;
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,18 @@
 //! A chess game state position.
 
-use core::board::{MultiBoard, PieceMap};
+use std::mem;
+use std::sync::Arc;
+
+use core::board::{MultiBoard, PieceMap, Variant};
+use core::castle::Files;
+use core::fen::Fen;
 use core::misc::Contained;
-use core::mv::{self, MoveVec};
+use core::mv::{Kind, MoveVec};
+use core::mv::kind::{Castle, EnPassant, Promotion};
+use nnue::{Accumulators, Delta, Network};
 use prelude::*;
+use uncon::*;
+use zobrist::{KEYS, POLYGLOT};
 
 mod state;
 pub use self::state::*;
@@ -11,9 +20,22 @@ pub use self::state::*;
 mod mv_gen;
 pub use self::mv_gen::*;
 
+mod outcome;
+pub use self::outcome::*;
+
+mod san;
+
+mod display;
+
 #[cfg(all(test, nightly))]
 mod benches;
 
+#[cfg(test)]
+mod tests;
+
+/// The Zobrist key of `Position::STANDARD`.
+const STANDARD_KEY: u64 = 0xC3C9_112B_17D1_B1C4;
+
 /// A representation of the current game state.
 #[derive(Clone)]
 pub struct Position {
@@ -28,14 +50,28 @@ pub struct Position {
 
     /// The color for the player whose turn it is.
     player: Color,
+
+    /// The rules being played by.
+    variant: Variant,
+
+    /// The files on which the kings and castling rooks start.
+    castle_files: Files,
+
+    /// The fullmove number, incremented after each move by Black.
+    fullmoves: u32,
+
+    /// The inputs of the network evaluating this position, if any.
+    nnue: Option<Accumulators>,
 }
 
 impl PartialEq for Position {
     fn eq(&self, other: &Position) -> bool {
         // Skip checking `board`; it represents the same data as `pieces`.
-        self.pieces == other.pieces &&
-        self.player == other.player &&
-        self.state  == other.state
+        // The fullmove number does not affect the game.
+        self.pieces  == other.pieces  &&
+        self.player  == other.player  &&
+        self.variant == other.variant &&
+        self.state   == other.state
     }
 }
 
@@ -55,8 +91,146 @@ impl Position {
         pieces: PieceMap::STANDARD,
         board: MultiBoard::STANDARD,
         player: Color::White,
+        variant: Variant::Standard,
+        castle_files: Files::STANDARD,
+        fullmoves: 1,
+        nnue: None,
     };
 
+    /// Creates a position from `fen`, returning `None` if either player does
+    /// not have exactly one king.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::fen::Fen;
+    /// use hexe::position::Position;
+    ///
+    /// let pos = Position::from_fen(&Fen::STANDARD).unwrap();
+    /// assert!(pos == Position::default());
+    /// ```
+    pub fn from_fen(fen: &Fen) -> Option<Position> {
+        Position::from_fen_variant(fen, Variant::Standard)
+    }
+
+    /// Creates a position from `fen` played by the rules of `variant`,
+    /// returning `None` if either player does not have exactly one king.
+    ///
+    /// Castle rights whose king or rook is not on its starting square are
+    /// dropped. Outside of Chess960, this includes rights whose king is not
+    /// on the e-file or whose rook is not on the a or h file. The checks
+    /// given in Three-check are taken from `fen`, with none given if it has
+    /// no record of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::board::Variant;
+    /// use hexe::castle::Rights;
+    /// use hexe::fen::Fen;
+    /// use hexe::position::Position;
+    ///
+    /// let fen = "nrkbbqrn/pppppppp/8/8/8/8/PPPPPPPP/NRKBBQRN w KQkq - 0 1".parse().unwrap();
+    /// assert_eq!(Position::from_fen(&fen).unwrap().rights(), Rights::EMPTY);
+    /// assert_eq!(Position::from_fen_variant(&fen, Variant::Chess960).unwrap().rights(), Rights::FULL);
+    /// ```
+    pub fn from_fen_variant(fen: &Fen, variant: Variant) -> Option<Position> {
+        let board = MultiBoard::from(&fen.pieces);
+        for color in Color::ALL {
+            if board.count(Piece::new(Role::King, color)) != 1 {
+                return None;
+            }
+        }
+
+        let player = fen.color;
+
+        // Only keep the en passant square if it can actually be captured on so
+        // that equivalent positions hash the same
+        let pawns = board.bits(Piece::new(Role::Pawn, player));
+        let en_passant = fen.en_passant.filter(|ep| {
+            ep.pawn_attacks(!player).intersects(pawns)
+        });
+
+        let files = match variant {
+            Variant::Chess960 => fen.castle_files,
+            _ => Files::STANDARD,
+        };
+        let rights = fen.castling.into_iter().filter(|&right| {
+            let color = right.color();
+            let king = files.king_square(color);
+            let rook = files.rook_square(right);
+            king == fen.castle_files.king_square(color) &&
+            rook == fen.castle_files.rook_square(right) &&
+            fen.pieces.get(king) == Some(&Piece::new(Role::King, color)) &&
+            fen.pieces.get(rook) == Some(&Piece::new(Role::Rook, color))
+        }).collect();
+
+        let remaining_checks = match (variant, fen.checks) {
+            (Variant::ThreeCheck, Some([white, black])) => {
+                [3u8.saturating_sub(white), 3u8.saturating_sub(black)]
+            },
+            _ => [3; 2],
+        };
+
+        let mut pos = Position {
+            state: State {
+                prev: None,
+                en_passant,
+                rights,
+                key: 0,
+                captured: None,
+                halfmoves: fen.halfmoves,
+                remaining_checks,
+            },
+            pieces: fen.pieces.clone(),
+            board,
+            player,
+            variant,
+            castle_files: files,
+            fullmoves: fen.fullmoves,
+            nnue: None,
+        };
+        pos.state.key = pos.compute_key();
+        Some(pos)
+    }
+
+    /// Returns the starting position of Chess960 with the Scharnagl index
+    /// `index`, or `None` if it is not less than 960.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::board::Variant;
+    /// use hexe::position::Position;
+    ///
+    /// let pos = Position::chess960(518).unwrap();
+    /// assert_eq!(pos.variant(), Variant::Chess960);
+    /// assert_eq!(pos.pieces(), Position::default().pieces());
+    /// ```
+    pub fn chess960(index: u32) -> Option<Position> {
+        Position::from_fen_variant(&Fen::chess960(index)?, Variant::Chess960)
+    }
+
+    /// Computes the Zobrist key for `self` from scratch.
+    fn compute_key(&self) -> u64 {
+        let mut key = KEYS.castle(self.rights());
+        for (sq, &pc) in self.pieces() {
+            key ^= KEYS.piece(pc, sq);
+        }
+        if let Some(ep) = self.en_passant() {
+            key ^= KEYS.en_passant(ep.file());
+        }
+        if self.player() == Color::Black {
+            key ^= KEYS.color;
+        }
+        if self.variant == Variant::ThreeCheck {
+            for color in Color::ALL {
+                key ^= KEYS.checks(color, self.remaining_checks(color));
+            }
+        }
+        key
+    }
+
     /// Returns the inner piece map.
     #[inline]
     pub fn pieces(&self) -> &PieceMap {
@@ -69,6 +243,82 @@ impl Position {
         &self.board
     }
 
+    /// Returns the current state.
+    #[inline]
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the variant whose rules `self` is played by.
+    #[inline]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Returns the files on which the kings and castling rooks start.
+    #[inline]
+    pub fn castle_files(&self) -> &Files {
+        &self.castle_files
+    }
+
+    /// Sets the network that evaluates `self`, whose inputs are then updated
+    /// with each move made.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| Accumulators::new(network, &self.board));
+    }
+
+    /// Returns the inputs of the network evaluating `self`, if any.
+    #[inline]
+    pub fn accumulators(&self) -> Option<&Accumulators> {
+        self.nnue.as_ref()
+    }
+
+    /// Returns the Zobrist hash key of `self`.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.state.key
+    }
+
+    /// Returns the key of `self` in [Polyglot] opening books.
+    ///
+    /// The en passant file is only hashed if a pawn of the player to move can
+    /// capture en passant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::position::Position;
+    ///
+    /// let pos = Position::default();
+    /// assert_eq!(pos.polyglot_key(), 0x463B96181691FC9C);
+    /// ```
+    ///
+    /// [Polyglot]: http://hgm.nubati.net/book_format.html
+    pub fn polyglot_key(&self) -> u64 {
+        let board = self.board();
+        let mut key = 0;
+
+        for piece in Piece::ALL {
+            let kind = 2 * piece.role() as usize + (piece.color() == Color::White) as usize;
+            for square in board.bits(piece) {
+                key ^= POLYGLOT[64 * kind + square as usize];
+            }
+        }
+        for right in self.rights() {
+            key ^= POLYGLOT[768 + right as usize];
+        }
+        if let Some(ep) = self.en_passant() {
+            let pawns = board.bits(Piece::new(Role::Pawn, self.player));
+            if ep.pawn_attacks(!self.player).intersects(pawns) {
+                key ^= POLYGLOT[772 + ep.file() as usize];
+            }
+        }
+        if self.player == Color::White {
+            key ^= POLYGLOT[780];
+        }
+        key
+    }
+
     /// Creates a move generator for this position and `moves`.
     ///
     /// # Examples
@@ -81,6 +331,7 @@ impl Position {
     /// let pos = Position::default();
     ///
     /// pos.gen(&mut moves).legal();
+    /// assert_eq!(moves.len(), 20);
     /// ```
     #[inline]
     pub fn gen<'a, 'b>(&'a self, moves: &'b mut MoveVec) -> MoveGen<'a, 'b> {
@@ -94,56 +345,19 @@ impl Position {
     }
 
     fn _is_legal(&self, mv: Move) -> bool {
-        use self::mv::Matches;
-
-        let src = mv.src();
-        let dst = mv.dst();
-
-        let player  = self.player();
-        let king    = self.king_square(player);
-        let pieces  = self.pieces();
-        let board   = self.board();
-        let checked = board.is_attacked(king, player);
-
-        match mv.matches() {
-            // TODO: is normal legal?
-            Matches::Normal(mv) => {
-
-            },
-            Matches::Castle(mv) => {
-                // Cannot castle out of check
-                if checked {
-                    return false;
-                }
-
-                let right = mv.right();
-
-                // Castling is for current player and
-                // whether it would be allowed if so
-                if player != right.color() || !self.rights().contains(right) {
-                    return false;
-                }
-
-                // Cannot castle through or into check and no
-                // piece can sit in between the rook and king
-                for sq in right.path_iter() {
-                    if pieces.contains(sq) || board.is_attacked(sq, player) {
-                        return false;
-                    }
-                }
-
-                return true;
-            },
-            // TODO: is promotion legal?
-            Matches::Promotion(mv) => {
-
-            },
-            // TODO: is en passant legal?
-            Matches::EnPassant(mv) => {
+        let mut moves = MoveVec::new();
+        self.gen(&mut moves).legal();
+        moves.contains(&mv)
+    }
 
-            },
+    /// Returns whether `mv` captures a piece in this position.
+    #[inline]
+    pub fn is_capture(&self, mv: Move) -> bool {
+        match mv.kind() {
+            Kind::EnPassant => true,
+            Kind::Castle => false,
+            _ => self.pieces().contains(mv.dst()),
         }
-        false
     }
 
     /// Returns whether `self` contains the value.
@@ -188,6 +402,187 @@ impl Position {
         self.state.rights()
     }
 
+    /// Returns the number of halfmoves since the last capture or pawn move.
+    #[inline]
+    pub fn halfmoves(&self) -> u32 {
+        self.state.halfmoves()
+    }
+
+    /// Returns the number of checks left for `color` to give in Three-check,
+    /// which is always 3 in other variants.
+    #[inline]
+    pub fn remaining_checks(&self, color: Color) -> u8 {
+        self.state.remaining_checks(color)
+    }
+
+    /// Returns the fullmove number, which starts at the one given by the FEN
+    /// and is incremented after each move by Black.
+    #[inline]
+    pub fn fullmoves(&self) -> u32 {
+        self.fullmoves
+    }
+
+    /// Returns the FEN of `self`.
+    ///
+    /// The en passant square is only kept if a pawn can capture on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::fen::Fen;
+    /// use hexe::position::Position;
+    ///
+    /// assert!(Position::default().to_fen() == Fen::STANDARD);
+    /// ```
+    pub fn to_fen(&self) -> Fen {
+        Fen {
+            pieces: self.pieces.clone(),
+            color: self.player,
+            castling: self.rights(),
+            castle_files: self.castle_files,
+            en_passant: self.en_passant(),
+            halfmoves: self.halfmoves(),
+            fullmoves: self.fullmoves,
+            checks: match self.variant {
+                Variant::ThreeCheck => Some([
+                    3 - self.remaining_checks(Color::White),
+                    3 - self.remaining_checks(Color::Black),
+                ]),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns whether the current position has occurred at least `count`
+    /// times, including the current occurrence.
+    ///
+    /// Only positions since the last capture or pawn move are considered,
+    /// since earlier ones cannot be reached again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::prelude::*;
+    ///
+    /// let mut pos = Position::default();
+    /// let moves = [
+    ///     Move::normal(Square::G1, Square::F3),
+    ///     Move::normal(Square::G8, Square::F6),
+    ///     Move::normal(Square::F3, Square::G1),
+    ///     Move::normal(Square::F6, Square::G8),
+    /// ];
+    ///
+    /// for &mv in moves.iter() {
+    ///     pos.make_move(mv);
+    /// }
+    /// assert!(pos.is_repetition(2));
+    /// assert!(!pos.is_repetition(3));
+    /// ```
+    pub fn is_repetition(&self, count: usize) -> bool {
+        let key = self.key();
+        let mut seen = 1;
+        let mut state = &self.state;
+        let mut plies = state.halfmoves;
+
+        // The same player is to move every other ply
+        while seen < count && plies >= 2 {
+            state = match state.prev().and_then(State::prev) {
+                Some(state) => state,
+                None => break,
+            };
+            plies -= 2;
+            if state.key == key {
+                seen += 1;
+            }
+        }
+        seen >= count
+    }
+
+    /// Returns whether fifty moves by each player have passed without a
+    /// capture or pawn move.
+    #[inline]
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmoves() >= 100
+    }
+
+    /// Returns whether neither player has enough material to checkmate.
+    ///
+    /// This is the case for king versus king with at most one minor piece, or
+    /// when all remaining minor pieces are bishops on same-colored squares.
+    /// In Three-check, only bare kings cannot win since any other piece can
+    /// give check. In King of the Hill, either king can still reach the
+    /// center.
+    pub fn has_insufficient_material(&self) -> bool {
+        let board = self.board();
+        match self.variant {
+            Variant::ThreeCheck => return board.len() == 2,
+            Variant::KingOfTheHill => return false,
+            _ => {},
+        }
+        let heavy = board.bits(Role::Pawn)
+                  | board.bits(Role::Rook)
+                  | board.bits(Role::Queen);
+        if !heavy.is_empty() {
+            return false;
+        }
+
+        let bishops = board.bits(Role::Bishop);
+        let minors  = board.bits(Role::Knight) | bishops;
+
+        !minors.has_multiple() || (minors == bishops && (
+            (bishops & BitBoard::WHITE).is_empty() ||
+            (bishops & BitBoard::BLACK).is_empty()
+        ))
+    }
+
+    /// Returns the player that has won by the rules of the variant of `self`
+    /// other than checkmate, if any.
+    ///
+    /// Such a win always goes to the player that made the last move.
+    pub fn variant_winner(&self) -> Option<Color> {
+        let winner = self.opponent();
+        match self.variant {
+            Variant::ThreeCheck if self.remaining_checks(winner) == 0 => Some(winner),
+            Variant::KingOfTheHill if self.king_square(winner).center_distance() == 0 => {
+                Some(winner)
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns how the game has ended, if it has.
+    ///
+    /// Wins by the rules of the variant take precedence over checkmate, which
+    /// takes precedence over draws by the fifty-move rule.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(winner) = self.variant_winner() {
+            return Some(match self.variant {
+                Variant::ThreeCheck => Outcome::ThreeChecks { winner },
+                Variant::KingOfTheHill => Outcome::KingOfTheHill { winner },
+                _ => Outcome::Checkmate { winner },
+            });
+        }
+
+        let mut moves = MoveVec::new();
+        self.gen(&mut moves).legal();
+
+        if moves.is_empty() {
+            Some(if self.in_check() {
+                Outcome::Checkmate { winner: self.opponent() }
+            } else {
+                Outcome::Stalemate
+            })
+        } else if self.is_fifty_move_draw() {
+            Some(Outcome::Draw(Draw::FiftyMoves))
+        } else if self.is_repetition(3) {
+            Some(Outcome::Draw(Draw::Repetition))
+        } else if self.has_insufficient_material() {
+            Some(Outcome::Draw(Draw::InsufficientMaterial))
+        } else {
+            None
+        }
+    }
+
     /// Returns the square where the color's king lies on.
     #[inline]
     pub fn king_square(&self, color: Color) -> Square {
@@ -199,6 +594,333 @@ impl Position {
 
         unsafe { board.lsb_unchecked() }
     }
+
+    /// Returns the pieces of both colors that attack `sq`, given the
+    /// `occupied` squares of the board.
+    pub fn attackers(&self, sq: Square, occupied: BitBoard) -> BitBoard {
+        let board = self.board();
+        let white = board.bits(Color::White);
+        let black = board.bits(Color::Black);
+        let pawns = board.bits(Role::Pawn);
+        let queens = board.bits(Role::Queen);
+
+        (sq.pawn_attacks(Color::White) & pawns & black)
+            | (sq.pawn_attacks(Color::Black) & pawns & white)
+            | (sq.knight_attacks() & board.bits(Role::Knight))
+            | (sq.king_attacks() & board.bits(Role::King))
+            | (sq.bishop_attacks(occupied) & (board.bits(Role::Bishop) | queens))
+            | (sq.rook_attacks(occupied) & (board.bits(Role::Rook) | queens))
+    }
+
+    /// Returns the opponent pieces that give check to the current player.
+    #[inline]
+    pub fn checkers(&self) -> BitBoard {
+        let king = self.king_square(self.player());
+        self.attackers(king, self.board().all_bits()) & self.opponent_bits()
+    }
+
+    /// Returns whether the current player is in check.
+    #[inline]
+    pub fn in_check(&self) -> bool {
+        let king = self.king_square(self.player());
+        self.board().is_attacked(king, self.player())
+    }
+
+    /// Returns the pieces of either color that block an attack by an opponent
+    /// slider on the king of `color`.
+    pub fn blockers(&self, color: Color) -> BitBoard {
+        let board = self.board();
+        let king = self.king_square(color);
+        let opp = board.bits(!color);
+        let all = board.all_bits();
+        let queens = board.bits(Role::Queen);
+
+        let snipers = opp & (
+            (king.rook_attacks(BitBoard::EMPTY) & (board.bits(Role::Rook) | queens)) |
+            (king.bishop_attacks(BitBoard::EMPTY) & (board.bits(Role::Bishop) | queens))
+        );
+
+        let mut blockers = BitBoard::EMPTY;
+        for sniper in snipers {
+            let between = BitBoard::between(king, sniper) & all;
+            if !between.has_multiple() {
+                blockers |= between;
+            }
+        }
+        blockers
+    }
+
+    /// Performs `mv` on `self`, which must be legal for the current player.
+    ///
+    /// The previous state is retained and can be restored with
+    /// [`unmake_move`](#method.unmake_move).
+    pub fn make_move(&mut self, mv: Move) {
+        let player = self.player();
+        let src = mv.src();
+        let dst = mv.dst();
+
+        let mut key = self.state.key ^ KEYS.color;
+        if let Some(ep) = self.state.en_passant {
+            key ^= KEYS.en_passant(ep.file());
+        }
+
+        let mut captured = None;
+        let mut en_passant = None;
+        let mut delta = Delta::default();
+        let pawn_move = self.pieces[src].role() == Role::Pawn;
+
+        match mv.kind() {
+            Kind::Normal | Kind::Promotion => {
+                let piece = self.pieces[src];
+
+                if let Some(&cap) = self.pieces.get(dst) {
+                    self.board.remove_unchecked(dst, cap);
+                    key ^= KEYS.piece(cap, dst);
+                    delta.remove(cap, dst);
+                    captured = Some(cap);
+                }
+
+                let placed = if mv.kind() == Kind::Promotion {
+                    let promotion = unsafe { Promotion::from_unchecked(mv) };
+                    Piece::new(promotion.piece().into(), player)
+                } else {
+                    piece
+                };
+
+                self.board.remove_unchecked(src, piece);
+                self.board.insert_unchecked(dst, placed);
+                self.pieces.remove(src);
+                self.pieces.insert(dst, placed);
+                key ^= KEYS.piece(piece, src) ^ KEYS.piece(placed, dst);
+                delta.remove(piece, src);
+                delta.add(placed, dst);
+
+                // Double pawn push
+                if piece.role() == Role::Pawn && src.rank().distance(dst.rank()) == 2 {
+                    let rank = match player {
+                        Color::White => Rank::Three,
+                        Color::Black => Rank::Six,
+                    };
+                    let ep = Square::new(src.file(), rank);
+                    let pawns = self.board.bits(Piece::new(Role::Pawn, !player));
+                    if ep.pawn_attacks(player).intersects(pawns) {
+                        key ^= KEYS.en_passant(ep.file());
+                        en_passant = Some(ep);
+                    }
+                }
+            },
+            Kind::EnPassant => {
+                let ep = unsafe { EnPassant::from_unchecked(mv) };
+                let cap_sq = ep.capture();
+                let pawn = Piece::new(Role::Pawn, player);
+                let cap = Piece::new(Role::Pawn, !player);
+
+                self.board.remove_unchecked(cap_sq, cap);
+                self.board.remove_unchecked(src, pawn);
+                self.board.insert_unchecked(dst, pawn);
+                self.pieces.en_passant(src, dst);
+                key ^= KEYS.piece(cap, cap_sq)
+                     ^ KEYS.piece(pawn, src)
+                     ^ KEYS.piece(pawn, dst);
+                delta.remove(cap, cap_sq);
+                delta.remove(pawn, src);
+                delta.add(pawn, dst);
+                captured = Some(cap);
+            },
+            Kind::Castle => {
+                let right = unsafe { Castle::from_unchecked(mv) }.right();
+                let (king_src, king_dst, rook_src, rook_dst) = self.castle_squares(right);
+                let king = Piece::new(Role::King, player);
+                let rook = Piece::new(Role::Rook, player);
+
+                if self.castle_files.is_standard() {
+                    self.board.castle(right);
+                    self.pieces.castle(right);
+                } else {
+                    // The king or rook may land where the other started
+                    self.board.remove_unchecked(king_src, king);
+                    self.board.remove_unchecked(rook_src, rook);
+                    self.board.insert_unchecked(king_dst, king);
+                    self.board.insert_unchecked(rook_dst, rook);
+                    self.pieces.remove(king_src);
+                    self.pieces.remove(rook_src);
+                    self.pieces.insert(king_dst, king);
+                    self.pieces.insert(rook_dst, rook);
+                }
+                key ^= KEYS.piece(king, king_src) ^ KEYS.piece(king, king_dst)
+                     ^ KEYS.piece(rook, rook_src) ^ KEYS.piece(rook, rook_dst);
+                delta.remove(king, king_src);
+                delta.remove(rook, rook_src);
+                delta.add(king, king_dst);
+                delta.add(rook, rook_dst);
+            },
+        }
+
+        let lost = self.castle_files.rights_lost(src) | self.castle_files.rights_lost(dst);
+        let rights = self.state.rights - lost;
+        if rights != self.state.rights {
+            key ^= KEYS.castle(self.state.rights) ^ KEYS.castle(rights);
+        }
+
+        let halfmoves = if pawn_move || captured.is_some() {
+            0
+        } else {
+            self.state.halfmoves + 1
+        };
+
+        let mut remaining_checks = self.state.remaining_checks;
+        if self.variant == Variant::ThreeCheck {
+            let king = self.king_square(!player);
+            let remaining = &mut remaining_checks[player as usize];
+            if *remaining > 0 && self.board.is_attacked(king, !player) {
+                key ^= KEYS.checks(player, *remaining) ^ KEYS.checks(player, *remaining - 1);
+                *remaining -= 1;
+            }
+        }
+
+        let prev = mem::replace(&mut self.state, State {
+            prev: None,
+            en_passant,
+            rights,
+            key,
+            captured,
+            halfmoves,
+            remaining_checks,
+        });
+        self.state.prev = Some(Arc::new(prev));
+        self.player = !player;
+        if player == Color::Black {
+            self.fullmoves += 1;
+        }
+
+        if let Some(ref mut nnue) = self.nnue {
+            nnue.push(&self.board, &delta);
+        }
+    }
+
+    /// Reverts `mv`, which must have been the last move made on `self`.
+    pub fn unmake_move(&mut self, mv: Move) {
+        let player = !self.player();
+        let src = mv.src();
+        let dst = mv.dst();
+        let captured = self.state.captured;
+
+        match mv.kind() {
+            Kind::Normal | Kind::Promotion => {
+                let placed = self.pieces[dst];
+                let piece = if mv.kind() == Kind::Promotion {
+                    Piece::new(Role::Pawn, player)
+                } else {
+                    placed
+                };
+
+                self.board.remove_unchecked(dst, placed);
+                self.board.insert_unchecked(src, piece);
+                self.pieces.remove(dst);
+                self.pieces.insert(src, piece);
+
+                if let Some(cap) = captured {
+                    self.board.insert_unchecked(dst, cap);
+                    self.pieces.insert(dst, cap);
+                }
+            },
+            Kind::EnPassant => {
+                let cap_sq = unsafe { EnPassant::from_unchecked(mv) }.capture();
+                let pawn = Piece::new(Role::Pawn, player);
+                let cap = Piece::new(Role::Pawn, !player);
+
+                self.board.remove_unchecked(dst, pawn);
+                self.board.insert_unchecked(src, pawn);
+                self.board.insert_unchecked(cap_sq, cap);
+                self.pieces.relocate(dst, src);
+                self.pieces.insert(cap_sq, cap);
+            },
+            Kind::Castle => {
+                let right = unsafe { Castle::from_unchecked(mv) }.right();
+                let (king_src, king_dst, rook_src, rook_dst) = self.castle_squares(right);
+                let king = Piece::new(Role::King, player);
+                let rook = Piece::new(Role::Rook, player);
+
+                if self.castle_files.is_standard() {
+                    self.board.castle(right);
+                    self.pieces.relocate(king_dst, king_src);
+                    self.pieces.relocate(rook_dst, rook_src);
+                } else {
+                    self.board.remove_unchecked(king_dst, king);
+                    self.board.remove_unchecked(rook_dst, rook);
+                    self.board.insert_unchecked(king_src, king);
+                    self.board.insert_unchecked(rook_src, rook);
+                    self.pieces.remove(king_dst);
+                    self.pieces.remove(rook_dst);
+                    self.pieces.insert(king_src, king);
+                    self.pieces.insert(rook_src, rook);
+                }
+            },
+        }
+
+        self.restore_prev();
+        self.player = player;
+        if player == Color::Black {
+            self.fullmoves -= 1;
+        }
+
+        if let Some(ref mut nnue) = self.nnue {
+            nnue.pop(&self.board);
+        }
+    }
+
+    /// Passes the turn to the opponent without moving a piece.
+    ///
+    /// This must not be called when the current player is in check.
+    pub fn make_null(&mut self) {
+        let mut key = self.state.key ^ KEYS.color;
+        if let Some(ep) = self.state.en_passant {
+            key ^= KEYS.en_passant(ep.file());
+        }
+
+        let rights = self.state.rights;
+        let halfmoves = self.state.halfmoves + 1;
+        let remaining_checks = self.state.remaining_checks;
+        let prev = mem::replace(&mut self.state, State {
+            prev: None,
+            en_passant: None,
+            rights,
+            key,
+            captured: None,
+            halfmoves,
+            remaining_checks,
+        });
+        self.state.prev = Some(Arc::new(prev));
+        self.player = !self.player;
+
+        if let Some(ref mut nnue) = self.nnue {
+            nnue.push_null();
+        }
+    }
+
+    /// Reverts the null move made by [`make_null`](#method.make_null).
+    pub fn unmake_null(&mut self) {
+        self.restore_prev();
+        self.player = !self.player;
+
+        if let Some(ref mut nnue) = self.nnue {
+            nnue.pop(&self.board);
+        }
+    }
+
+    /// Returns the source and destination squares of the king and then the
+    /// rook when castling with `right`.
+    #[inline]
+    fn castle_squares(&self, right: Right) -> (Square, Square, Square, Square) {
+        let files = &self.castle_files;
+        (files.king_square(right.color()), right.king_dst(),
+         files.rook_square(right), right.rook_dst())
+    }
+
+    fn restore_prev(&mut self) {
+        let prev = self.state.prev.take().expect("no previous state");
+        self.state = Arc::try_unwrap(prev).unwrap_or_else(|prev| (*prev).clone());
+    }
 }
 
 impl<'a> Contained<&'a Position> for Square {
@@ -220,32 +942,3 @@ macro_rules! impl_contained {
 }
 
 impl_contained! { Piece, Role, Color }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn initial_pieces() {
-        let pos = Position::default();
-        let all = pos.board().all_bits();
-
-        for square in Square::ALL {
-            if let Some(&piece) = pos.pieces().get(square) {
-                assert!(all.contains(square));
-
-                let board = pos.board();
-                assert!(board.contains(square, piece));
-                assert!(board.contains(square, piece.role()));
-                assert!(board.contains(square, piece.color()));
-            } else {
-                let (a, b) = pos.board.split();
-                for &slice in &[&a[..], &b[..]] {
-                    for &bit_board in slice {
-                        assert!(!bit_board.contains(square));
-                    }
-                }
-            }
-        }
-    }
-}
@@ -1,9 +1,15 @@
 //! A move generator and options.
 
-use core::mv::kind::*;
-use core::mv::MoveVec;
+use core::board::Variant;
+use core::mv::{Kind, MoveVec};
+use core::mv::kind::{Castle, EnPassant, Promotion};
+use core::piece::Promotion as Prom;
+use prelude::*;
 use super::Position;
 
+/// Promotion pieces in the order in which they are generated.
+const PROMOTIONS: [Prom; 4] = [Prom::Queen, Prom::Knight, Prom::Rook, Prom::Bishop];
+
 /// A type that can be used to generate a series of moves.
 pub struct MoveGen<'pos, 'buf> {
     pub(super) pos: &'pos Position,
@@ -13,11 +19,203 @@ pub struct MoveGen<'pos, 'buf> {
 impl<'a, 'b> MoveGen<'a, 'b> {
     /// Generates all legal moves.
     pub fn legal(&mut self) -> &mut Self {
+        let start = self.buf.len();
+        let targets = !self.pos.player_bits();
+        self.pseudo(targets, true);
+        self.retain_legal(start);
+        self.castle()
+    }
+
+    /// Generates all legal captures and promotions.
+    ///
+    /// In King of the Hill, king moves into the center are included too since
+    /// they win the game.
+    pub fn noisy(&mut self) -> &mut Self {
+        let start = self.buf.len();
+        let targets = self.pos.opponent_bits();
+        self.pseudo(targets, false);
+        if self.pos.variant() == Variant::KingOfTheHill {
+            let king = self.pos.king_square(self.pos.player());
+            let quiets = king.king_attacks() - self.pos.board().all_bits();
+            for dst in quiets {
+                if dst.center_distance() == 0 {
+                    self.push(Move::normal(king, dst));
+                }
+            }
+        }
+        self.retain_legal(start);
         self
     }
 
-    /// Generates all pseudo-legal castling moves.
+    /// Generates all legal castling moves.
+    ///
+    /// In Chess960, these are written as the king capturing its own rook.
     pub fn castle(&mut self) -> &mut Self {
+        let pos    = self.pos;
+        let player = pos.player();
+        let files  = pos.castle_files();
+        let all    = pos.board().all_bits();
+        let opp    = pos.opponent_bits();
+
+        if pos.in_check() {
+            return self;
+        }
+
+        for right in pos.rights() & Rights::from(player) {
+            if !(files.path(right) & all).is_empty() {
+                continue;
+            }
+
+            // The king cannot pass through or land on an attacked square,
+            // including one only shielded by the castling rook
+            let king = files.king_square(player);
+            let rook = files.rook_square(right);
+            let occupied = all - king - rook;
+            let attacked = |sq| !(pos.attackers(sq, occupied) & opp).is_empty();
+            if files.king_path(right).into_iter().any(attacked) {
+                continue;
+            }
+
+            let mv = match pos.variant() {
+                Variant::Chess960 => Castle::chess960(right, files),
+                _ => Castle::new(right),
+            };
+            self.buf.push(mv.into());
+        }
         self
     }
+
+    /// Generates pseudo-legal non-castling moves to `targets`. Promotions are
+    /// always generated, whereas quiet pawn pushes require `quiets`.
+    fn pseudo(&mut self, targets: BitBoard, quiets: bool) {
+        let pos    = self.pos;
+        let board  = pos.board();
+        let player = pos.player();
+        let own    = pos.player_bits();
+        let opp    = pos.opponent_bits();
+        let all    = own | opp;
+        let last   = BitBoard::from(Rank::last(player));
+
+        // Pawns
+        let pawns = board.bits(Piece::new(Role::Pawn, player));
+        let third = BitBoard::from(match player {
+            Color::White => Rank::Three,
+            Color::Black => Rank::Six,
+        });
+        let back = |sq: Square| match player {
+            Color::White => sq.wrapping_down(),
+            Color::Black => sq.wrapping_up(),
+        };
+
+        let single = pawns.advance(player) - all;
+        let double = (single & third).advance(player) - all;
+
+        for dst in single & last {
+            self.push_promotions(back(dst), dst);
+        }
+        if quiets {
+            for dst in single - last {
+                self.push(Move::normal(back(dst), dst));
+            }
+            for dst in double {
+                self.push(Move::normal(back(back(dst)), dst));
+            }
+        }
+
+        for src in pawns {
+            for dst in src.pawn_attacks(player) & opp {
+                if last.contains(dst) {
+                    self.push_promotions(src, dst);
+                } else {
+                    self.push(Move::normal(src, dst));
+                }
+            }
+        }
+
+        if let Some(ep) = pos.en_passant() {
+            for src in ep.pawn_attacks(!player) & pawns {
+                if let Some(mv) = Move::en_passant(src, ep) {
+                    self.push(mv);
+                }
+            }
+        }
+
+        // Pieces
+        for role in [Role::Knight, Role::Bishop, Role::Rook, Role::Queen, Role::King].iter() {
+            for src in board.bits(Piece::new(*role, player)) {
+                let attacks = match *role {
+                    Role::Knight => src.knight_attacks(),
+                    Role::Bishop => src.bishop_attacks(all),
+                    Role::Rook   => src.rook_attacks(all),
+                    Role::Queen  => src.queen_attacks(all),
+                    _            => src.king_attacks(),
+                };
+                for dst in attacks & targets {
+                    self.push(Move::normal(src, dst));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, mv: Move) {
+        self.buf.push(mv);
+    }
+
+    #[inline]
+    fn push_promotions(&mut self, src: Square, dst: Square) {
+        for &piece in PROMOTIONS.iter() {
+            self.push(Promotion::with_squares(src, dst, piece).into());
+        }
+    }
+
+    /// Removes all moves after `start` that leave the player's king in check.
+    fn retain_legal(&mut self, start: usize) {
+        let pos      = self.pos;
+        let player   = pos.player();
+        let king     = pos.king_square(player);
+        let checked  = pos.in_check();
+        let blockers = pos.blockers(player) & pos.player_bits();
+
+        let mut len = start;
+        for i in start..self.buf.len() {
+            let mv  = self.buf[i];
+            let src = mv.src();
+
+            let legal = if checked || src == king || mv.kind() == Kind::EnPassant {
+                leaves_king_safe(pos, mv, king)
+            } else {
+                // A pinned piece may only move along the pin
+                !blockers.contains(src) || mv.dst().is_aligned(king, src)
+            };
+
+            if legal {
+                self.buf[len] = mv;
+                len += 1;
+            }
+        }
+        self.buf.truncate(len);
+    }
+}
+
+/// Returns whether performing the pseudo-legal `mv` leaves the player's king,
+/// currently at `king`, out of check.
+fn leaves_king_safe(pos: &Position, mv: Move, king: Square) -> bool {
+    let player = pos.player();
+    let src = mv.src();
+    let dst = mv.dst();
+    let piece = pos.pieces()[src];
+
+    let mut board = pos.board().clone();
+    if mv.kind() == Kind::EnPassant {
+        let ep = EnPassant::try_new(src, dst).unwrap();
+        board.remove_unchecked(ep.capture(), Piece::new(Role::Pawn, !player));
+    } else if let Some(&cap) = pos.pieces().get(dst) {
+        board.remove_unchecked(dst, cap);
+    }
+    board.remove_unchecked(src, piece);
+    board.insert_unchecked(dst, piece);
+
+    let king = if src == king { dst } else { king };
+    !board.is_attacked(king, player)
 }
@@ -0,0 +1,56 @@
+use super::*;
+
+/// The result of a game that has ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// The player to move is checkmated by `winner`.
+    Checkmate {
+        /// The color of the player that delivered checkmate.
+        winner: Color,
+    },
+    /// The player to move has been given check for the third time in
+    /// Three-check.
+    ThreeChecks {
+        /// The color of the player that gave the checks.
+        winner: Color,
+    },
+    /// The king of `winner` has reached the center in King of the Hill.
+    KingOfTheHill {
+        /// The color of the player whose king reached the center.
+        winner: Color,
+    },
+    /// The player to move has no legal moves but is not in check.
+    Stalemate,
+    /// The game is drawn for the given reason.
+    Draw(Draw),
+}
+
+impl Outcome {
+    /// Returns the winning color, if any.
+    #[inline]
+    pub fn winner(&self) -> Option<Color> {
+        match *self {
+            Outcome::Checkmate { winner } |
+            Outcome::ThreeChecks { winner } |
+            Outcome::KingOfTheHill { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the game ended without a winner.
+    #[inline]
+    pub fn is_draw(&self) -> bool {
+        self.winner().is_none()
+    }
+}
+
+/// A reason for a game being drawn that is not stalemate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Draw {
+    /// The same position occurred three times.
+    Repetition,
+    /// No capture or pawn move occurred in the last fifty moves.
+    FiftyMoves,
+    /// Neither player can possibly deliver checkmate.
+    InsufficientMaterial,
+}
@@ -0,0 +1,151 @@
+//! Standard algebraic notation (SAN).
+
+use core::castle::Side;
+use core::mv::{Kind, Matches, MoveVec};
+use prelude::*;
+use super::Position;
+
+impl Position {
+    /// Returns `mv` in standard algebraic notation (e.g. `Nf3`, `exd5`, `O-O`
+    /// or `e8=Q+`).
+    ///
+    /// `mv` must be legal in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::prelude::*;
+    ///
+    /// let pos = Position::default();
+    /// assert_eq!(pos.san(Move::normal(Square::G1, Square::F3)), "Nf3");
+    /// ```
+    pub fn san(&self, mv: Move) -> String {
+        let mut moves = MoveVec::new();
+        self.gen(&mut moves).legal();
+
+        let mut san = String::with_capacity(8);
+        let (file, rank) = self.disambiguation(mv, &moves);
+        self.write_san(&mut san, mv, file, rank);
+
+        let mut next = self.clone();
+        next.nnue = None;
+        next.make_move(mv);
+        if next.in_check() {
+            moves.clear();
+            next.gen(&mut moves).legal();
+            san.push(if moves.is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+
+    /// Parses `s` as a legal move in standard algebraic notation.
+    ///
+    /// Check and mate markers and annotations such as `!?` are ignored, as
+    /// are redundant source squares and a missing `=` before a promotion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe::prelude::*;
+    ///
+    /// let pos = Position::default();
+    /// assert_eq!(pos.parse_san("e4"), Some(Move::normal(Square::E2, Square::E4)));
+    /// assert_eq!(pos.parse_san("Ng1f3"), Some(Move::normal(Square::G1, Square::F3)));
+    /// assert_eq!(pos.parse_san("Ke2"), None);
+    /// ```
+    pub fn parse_san(&self, s: &str) -> Option<Move> {
+        // `trim_end_matches` requires Rust 1.30
+        #[allow(deprecated)]
+        let s = s.trim_right_matches(|c| "+#!?".contains(c));
+        if s.is_empty() {
+            return None;
+        }
+        let s = s.replace('0', "O");
+        let s = s.as_str();
+
+        let mut moves = MoveVec::new();
+        self.gen(&mut moves).legal();
+
+        let mut buf = String::with_capacity(8);
+        for &mv in moves.iter() {
+            let (file, rank) = self.disambiguation(mv, &moves);
+            let forms = [(file, rank), (true, false), (false, true), (true, true)];
+            for &(file, rank) in forms.iter() {
+                buf.clear();
+                self.write_san(&mut buf, mv, file, rank);
+                if buf == s || (mv.kind() == Kind::Promotion && buf.replace('=', "") == s) {
+                    return Some(mv);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns whether the file and rank of the source square of `mv` are
+    /// needed to tell it apart from the other moves in `moves`.
+    fn disambiguation(&self, mv: Move, moves: &MoveVec) -> (bool, bool) {
+        let (src, dst) = (mv.src(), mv.dst());
+        let role = match self.pieces().get(src) {
+            Some(piece) => piece.role(),
+            None => return (false, false),
+        };
+        match (mv.kind(), role) {
+            (Kind::Castle, _) => return (false, false),
+            (_, Role::Pawn) => return (self.is_capture(mv), false),
+            (_, Role::King) => return (false, false),
+            _ => {},
+        }
+
+        let others = moves.iter().filter(|other| {
+            other.dst() == dst &&
+            other.src() != src &&
+            self.pieces().get(other.src()).map(|p| p.role()) == Some(role)
+        });
+
+        let (mut ambiguous, mut file, mut rank) = (false, false, false);
+        for other in others {
+            ambiguous = true;
+            file |= other.src().file() == src.file();
+            rank |= other.src().rank() == src.rank();
+        }
+        match (ambiguous, file, rank) {
+            (false, _, _) => (false, false),
+            (true, false, _) => (true, false),
+            (true, true, false) => (false, true),
+            (true, true, true) => (true, true),
+        }
+    }
+
+    /// Writes `mv` to `buf` without a check marker, with the file and rank of
+    /// the source square as given.
+    fn write_san(&self, buf: &mut String, mv: Move, file: bool, rank: bool) {
+        if let Matches::Castle(castle) = mv.matches() {
+            buf.push_str(match castle.right().side() {
+                Side::King => "O-O",
+                Side::Queen => "O-O-O",
+            });
+            return;
+        }
+
+        let (src, dst) = (mv.src(), mv.dst());
+        let role = self.pieces().get(src).map_or(Role::Pawn, |p| p.role());
+        if role != Role::Pawn {
+            buf.push(char::from(role));
+        }
+        if file {
+            buf.push(char::from(src.file()).to_ascii_lowercase());
+        }
+        if rank {
+            buf.push(char::from(src.rank()));
+        }
+        if self.is_capture(mv) {
+            buf.push('x');
+        }
+        buf.push(char::from(dst.file()).to_ascii_lowercase());
+        buf.push(char::from(dst.rank()));
+        if let Matches::Promotion(promotion) = mv.matches() {
+            buf.push('=');
+            buf.push(char::from(promotion.piece()));
+        }
+    }
+}
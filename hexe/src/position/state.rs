@@ -16,6 +16,18 @@ pub struct State {
 
     /// The castle rights for both players.
     pub(super) rights: Rights,
+
+    /// The Zobrist hash key of the position.
+    pub(super) key: u64,
+
+    /// The piece captured by the move that led to this state, if any.
+    pub(super) captured: Option<Piece>,
+
+    /// The number of halfmoves since the last capture or pawn move.
+    pub(super) halfmoves: u32,
+
+    /// The number of checks left for each color to give in Three-check.
+    pub(super) remaining_checks: [u8; 2],
 }
 
 impl PartialEq for State {
@@ -25,8 +37,11 @@ impl PartialEq for State {
         let mut that = other;
 
         loop {
-            if this.rights     == that.rights
-            && this.en_passant == that.en_passant {
+            if this.key        == that.key
+            && this.rights     == that.rights
+            && this.en_passant == that.en_passant
+            && this.halfmoves  == that.halfmoves
+            && this.remaining_checks == that.remaining_checks {
                 match (&this.prev, &that.prev) {
                     (&Some(ref a), &Some(ref b)) => {
                         // Short circuit if same history
@@ -59,6 +74,10 @@ impl fmt::Debug for State {
             .field("prev",       &self.prev())
             .field("en_passant", &self.en_passant())
             .field("rights",     &self.rights())
+            .field("key",        &self.key())
+            .field("captured",   &self.captured())
+            .field("halfmoves",  &self.halfmoves())
+            .field("remaining_checks", &self.remaining_checks)
             .finish()
     }
 }
@@ -68,6 +87,10 @@ impl State {
         prev: None,
         en_passant: None,
         rights: Rights::FULL,
+        key: STANDARD_KEY,
+        captured: None,
+        halfmoves: 0,
+        remaining_checks: [3; 2],
     };
 
     /// Returns the previous state.
@@ -87,4 +110,28 @@ impl State {
     pub fn rights(&self) -> Rights {
         self.rights
     }
+
+    /// Returns the Zobrist hash key of the position.
+    #[inline]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// Returns the piece captured by the move that led to this state, if any.
+    #[inline]
+    pub fn captured(&self) -> Option<Piece> {
+        self.captured
+    }
+
+    /// Returns the number of halfmoves since the last capture or pawn move.
+    #[inline]
+    pub fn halfmoves(&self) -> u32 {
+        self.halfmoves
+    }
+
+    /// Returns the number of checks left for `color` to give in Three-check.
+    #[inline]
+    pub fn remaining_checks(&self, color: Color) -> u8 {
+        self.remaining_checks[color as usize]
+    }
 }
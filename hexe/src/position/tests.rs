@@ -0,0 +1,411 @@
+use super::*;
+use core::board::Variant;
+use core::fen::Fen;
+
+fn position(fen: &str) -> Position {
+    let fen: Fen = fen.parse().unwrap();
+    Position::from_fen(&fen).unwrap()
+}
+
+fn perft(pos: &mut Position, depth: usize) -> u64 {
+    let mut moves = MoveVec::new();
+    pos.gen(&mut moves).legal();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for &mv in moves.iter() {
+        let key = pos.key();
+        pos.make_move(mv);
+        assert_eq!(pos.key(), pos.compute_key(), "{}", mv);
+        nodes += perft(pos, depth - 1);
+        pos.unmake_move(mv);
+        assert_eq!(pos.key(), key, "{}", mv);
+    }
+    nodes
+}
+
+#[test]
+fn initial_pieces() {
+    let pos = Position::default();
+    let all = pos.board().all_bits();
+
+    for square in Square::ALL {
+        if let Some(&piece) = pos.pieces().get(square) {
+            assert!(all.contains(square));
+
+            let board = pos.board();
+            assert!(board.contains(square, piece));
+            assert!(board.contains(square, piece.role()));
+            assert!(board.contains(square, piece.color()));
+        } else {
+            let (a, b) = pos.board.split();
+            for &slice in &[&a[..], &b[..]] {
+                for &bit_board in slice {
+                    assert!(!bit_board.contains(square));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn standard_key() {
+    assert_eq!(Position::STANDARD.compute_key(), STANDARD_KEY);
+}
+
+#[test]
+fn perft_standard() {
+    let mut pos = Position::default();
+    for &(depth, nodes) in &[(1, 20), (2, 400), (3, 8902), (4, 197281)] {
+        assert_eq!(perft(&mut pos, depth), nodes);
+    }
+}
+
+#[test]
+fn perft_positions() {
+    let cases = [
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97862),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9467),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379),
+    ];
+    for &(fen, depth, nodes) in cases.iter() {
+        let mut pos = position(fen);
+        assert_eq!(perft(&mut pos, depth), nodes, "{}", fen);
+    }
+}
+
+#[test]
+fn perft_chess960() {
+    let cases = [
+        // Standard positions play the same with Chess960 castling
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 4, 197281),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97862),
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 4, 326672),
+        ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", 4, 667366),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", 4, 273318),
+        ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", 4, 382958),
+        ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", 4, 1171749),
+    ];
+    for &(fen, depth, nodes) in cases.iter() {
+        let fen: Fen = fen.parse().unwrap();
+        let mut pos = Position::from_fen_variant(&fen, Variant::Chess960).unwrap();
+        assert_eq!(perft(&mut pos, depth), nodes, "{}", fen);
+    }
+}
+
+#[test]
+fn castle_chess960() {
+    let fen: Fen = "1r4kr/8/8/8/8/8/8/r1RK4 w Ch - 0 1".parse().unwrap();
+    let mut pos = Position::from_fen_variant(&fen, Variant::Chess960).unwrap();
+
+    // Once the castling rook moves, the king's destination is attacked from A1
+    let mut moves = MoveVec::new();
+    pos.gen(&mut moves).castle();
+    assert!(moves.is_empty());
+
+    pos = Position::from_fen_variant(&"1r4kr/8/8/8/8/8/8/2RK4 b Ch - 0 1".parse().unwrap(),
+                                     Variant::Chess960).unwrap();
+    let castle = Move::from(Castle::chess960(Right::BlackKing, pos.castle_files()));
+    assert_eq!(castle.to_string(), "g8h8");
+    assert!(pos.is_legal(castle));
+
+    let key = pos.key();
+    pos.make_move(castle);
+    assert_eq!(pos.to_fen().to_string(), "1r3rk1/8/8/8/8/8/8/2RK4 w Q - 1 2");
+    assert_eq!(pos.key(), pos.compute_key());
+    pos.unmake_move(castle);
+    assert_eq!(pos.key(), key);
+    assert_eq!(pos.to_fen().to_string(), "1r4kr/8/8/8/8/8/8/2RK4 b Qk - 0 1");
+}
+
+#[test]
+fn standard_bogus_rights() {
+    // Rights needing Chess960 are dropped rather than rejecting the position
+    let fens = [
+        ("4k3/8/8/8/8/8/8/R3K1R1 w GA - 0 1",  "4k3/8/8/8/8/8/8/R3K1R1 w Q - 0 1"),
+        ("4k3/8/8/8/8/8/8/R2K3R w HA - 0 1",   "4k3/8/8/8/8/8/8/R2K3R w - - 0 1"),
+        ("r3k1r1/8/8/8/8/8/8/4K3 b kq - 0 1",  "r3k1r1/8/8/8/8/8/8/4K3 b q - 0 1"),
+        ("nrkbbqrn/pppppppp/8/8/8/8/PPPPPPPP/NRKBBQRN w KQkq - 0 1",
+         "nrkbbqrn/pppppppp/8/8/8/8/PPPPPPPP/NRKBBQRN w - - 0 1"),
+    ];
+    for &(fen, exp) in fens.iter() {
+        let pos = position(fen);
+        assert_eq!(pos.to_fen().to_string(), exp);
+        assert_eq!(pos.castle_files(), &Files::STANDARD);
+    }
+}
+
+#[test]
+fn chess960_positions() {
+    for index in 0..960 {
+        let pos = Position::chess960(index).unwrap();
+        assert_eq!(pos.rights(), Rights::FULL);
+        assert!(Position::from_fen_variant(&pos.to_fen(), Variant::Chess960) == Some(pos.clone()));
+    }
+    assert!(Position::chess960(960).is_none());
+
+    // BQNNRKRB can castle kingside on the first move
+    let mut pos = Position::chess960(3).unwrap();
+    assert_eq!(perft(&mut pos, 2), 21 * 21);
+}
+
+#[test]
+fn noisy_subset_of_legal() {
+    let pos = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let mut legal = MoveVec::new();
+    let mut noisy = MoveVec::new();
+    pos.gen(&mut legal).legal();
+    pos.gen(&mut noisy).noisy();
+
+    assert!(!noisy.is_empty());
+    for mv in noisy.iter() {
+        assert!(legal.contains(mv));
+        assert!(pos.is_capture(*mv) || mv.kind() == Kind::Promotion);
+    }
+}
+
+#[test]
+fn halfmoves() {
+    let mut pos = position("4k3/8/8/8/8/8/4P3/4K1N1 w - - 7 30");
+    assert_eq!(pos.halfmoves(), 7);
+
+    let knight = Move::normal(Square::G1, Square::F3);
+    pos.make_move(knight);
+    assert_eq!(pos.halfmoves(), 8);
+    pos.unmake_move(knight);
+
+    let pawn = Move::normal(Square::E2, Square::E4);
+    pos.make_move(pawn);
+    assert_eq!(pos.halfmoves(), 0);
+    pos.unmake_move(pawn);
+    assert_eq!(pos.halfmoves(), 7);
+}
+
+#[test]
+fn repetition() {
+    let mut pos = Position::default();
+    let cycle = [
+        Move::normal(Square::G1, Square::F3),
+        Move::normal(Square::G8, Square::F6),
+        Move::normal(Square::F3, Square::G1),
+        Move::normal(Square::F6, Square::G8),
+    ];
+
+    assert!(pos.is_repetition(1));
+    assert!(!pos.is_repetition(2));
+
+    for _ in 0..2 {
+        for &mv in cycle.iter() {
+            pos.make_move(mv);
+        }
+    }
+    assert!(pos.is_repetition(3));
+    assert!(!pos.is_repetition(4));
+    assert_eq!(pos.outcome(), Some(Outcome::Draw(Draw::Repetition)));
+
+    // An irreversible move hides earlier positions
+    pos.make_move(Move::normal(Square::E2, Square::E4));
+    assert!(!pos.is_repetition(2));
+}
+
+#[test]
+fn fifty_moves() {
+    let pos = position("4k3/8/8/8/8/8/8/R3K3 w - - 100 80");
+    assert!(pos.is_fifty_move_draw());
+    assert_eq!(pos.outcome(), Some(Outcome::Draw(Draw::FiftyMoves)));
+
+    // Checkmate takes precedence
+    let pos = position("R3k3/8/4K3/8/8/8/8/8 b - - 100 80");
+    assert_eq!(pos.outcome(), Some(Outcome::Checkmate { winner: Color::White }));
+}
+
+#[test]
+fn insufficient_material() {
+    let drawn = [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+    ];
+    let playable = [
+        "4k3/8/8/8/8/8/8/4KNN1 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+    ];
+    for fen in drawn.iter() {
+        assert!(position(fen).has_insufficient_material(), "{}", fen);
+    }
+    for fen in playable.iter() {
+        assert!(!position(fen).has_insufficient_material(), "{}", fen);
+    }
+}
+
+#[test]
+fn outcome() {
+    assert_eq!(Position::default().outcome(), None);
+
+    let mate = position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+    assert_eq!(mate.outcome(), Some(Outcome::Checkmate { winner: Color::White }));
+
+    let stalemate = position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert_eq!(stalemate.outcome(), Some(Outcome::Stalemate));
+}
+
+#[test]
+fn three_check() {
+    let fen: Fen = "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3 +1+0".parse().unwrap();
+    let mut pos = Position::from_fen_variant(&fen, Variant::ThreeCheck).unwrap();
+    assert_eq!(pos.remaining_checks(Color::White), 2);
+    assert_eq!(pos.remaining_checks(Color::Black), 3);
+    assert_eq!(pos.key(), pos.compute_key());
+    assert!(pos.to_fen() == fen);
+
+    // The checks given are part of the key
+    let standard = Position::from_fen(&fen).unwrap();
+    assert_ne!(pos.key(), standard.key());
+    assert!(standard.to_fen().checks.is_none());
+
+    let moves = [
+        Move::normal(Square::F1, Square::B5),
+        Move::normal(Square::C7, Square::C6),
+        Move::normal(Square::B5, Square::C6),
+    ];
+    let key = pos.key();
+    pos.make_move(moves[0]);
+    assert_eq!(pos.remaining_checks(Color::White), 1);
+    assert_eq!(pos.key(), pos.compute_key());
+    assert_eq!(pos.outcome(), None);
+
+    pos.make_move(moves[1]);
+    pos.make_move(moves[2]);
+    assert_eq!(pos.remaining_checks(Color::White), 0);
+    assert_eq!(pos.key(), pos.compute_key());
+    assert_eq!(pos.variant_winner(), Some(Color::White));
+    assert_eq!(pos.outcome(), Some(Outcome::ThreeChecks { winner: Color::White }));
+    assert_eq!(pos.to_fen().to_string(),
+               "rnbqkbnr/pp3ppp/2B5/3pp3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 0 4 +3+0");
+
+    for &mv in moves.iter().rev() {
+        pos.unmake_move(mv);
+    }
+    assert_eq!(pos.key(), key);
+    assert_eq!(pos.remaining_checks(Color::White), 2);
+
+    // Any piece besides the king can give check
+    let fen: Fen = "4k3/8/8/8/8/8/8/4KN2 w - - 0 1".parse().unwrap();
+    let pos = Position::from_fen_variant(&fen, Variant::ThreeCheck).unwrap();
+    assert!(!pos.has_insufficient_material());
+}
+
+#[test]
+fn king_of_the_hill() {
+    let fen: Fen = "4k3/8/8/8/8/4K3/8/8 w - - 0 1".parse().unwrap();
+    let mut pos = Position::from_fen_variant(&fen, Variant::KingOfTheHill).unwrap();
+    assert!(!pos.has_insufficient_material());
+    assert_eq!(pos.outcome(), None);
+
+    // Quiet king moves into the center are generated with the noisy moves
+    let mut moves = MoveVec::new();
+    pos.gen(&mut moves).noisy();
+    let mut moves: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+    moves.sort();
+    assert_eq!(moves, ["e3d4", "e3e4"]);
+
+    pos.make_move(Move::normal(Square::E3, Square::E4));
+    assert_eq!(pos.variant_winner(), Some(Color::White));
+    assert_eq!(pos.outcome(), Some(Outcome::KingOfTheHill { winner: Color::White }));
+
+    // Only the king wins by reaching the center
+    let standard = position("4k3/8/8/8/4K3/8/8/8 b - - 0 1");
+    assert_eq!(standard.variant_winner(), None);
+    let mut moves = MoveVec::new();
+    Position::default().gen(&mut moves).noisy();
+    assert!(moves.is_empty());
+}
+
+#[test]
+fn san() {
+    let cases = [
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+         &["O-O", "O-O-O", "Nxd7", "Qxf6", "dxe6", "Nb1", "Rb1", "Kf1"][..]),
+        // Rooks and knights needing a file, rank or square
+        ("6k1/8/8/1N3N2/R6R/1N6/8/4K3 w - - 0 1", &["Rae4", "Rhe4", "Nb5d4", "Nfd4", "N3d4", "Nbd6"]),
+        ("6k1/8/8/8/R7/8/R7/4K3 w - - 0 1", &["R4a3", "R2a3", "Rb2"]),
+        ("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", &["gxf1=N", "gxh1=Q", "g1=R"]),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", &["exd6"]),
+        ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &["Ra8#"]),
+    ];
+    for &(fen, sans) in cases.iter() {
+        let pos = position(fen);
+        let mut moves = MoveVec::new();
+        pos.gen(&mut moves).legal();
+        for &san in sans.iter() {
+            let mv = pos.parse_san(san).expect(san);
+            assert_eq!(pos.san(mv), san);
+        }
+        for &mv in moves.iter() {
+            assert_eq!(pos.parse_san(&pos.san(mv)), Some(mv), "{}", mv);
+        }
+    }
+
+    let pos = Position::default();
+    assert_eq!(pos.parse_san("Nf3!?"), pos.parse_san("Nf3"));
+    assert_eq!(pos.parse_san("Nbd2"), None);
+    assert_eq!(pos.parse_san("e5"), None);
+}
+
+#[test]
+fn to_fen() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 3 17",
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 40",
+    ];
+    for fen in fens.iter() {
+        assert_eq!(position(fen).to_fen().to_string(), *fen);
+    }
+
+    let mut pos = Position::default();
+    let moves = [Move::normal(Square::E2, Square::E4), Move::normal(Square::E7, Square::E5)];
+    for &mv in moves.iter() {
+        pos.make_move(mv);
+    }
+    assert_eq!(pos.fullmoves(), 2);
+    assert_eq!(pos.to_fen().to_string(),
+               "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+    pos.unmake_move(moves[1]);
+    assert_eq!(pos.fullmoves(), 1);
+}
+
+#[test]
+fn polyglot_key() {
+    // Reference keys from the Polyglot book format specification
+    let keys = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",      0x463B96181691FC9C),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",   0x823C9B50FD114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756B94461C50FB0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",   0x662FAFB965DB29D4),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22A48B5A8E47FF78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 1 3",    0x652A607CA3F242C1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 2 4",     0x00FDD303C946BDD9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3C8123EA7B067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 1 4",  0x5C3F9B829B279560),
+    ];
+    for &(fen, key) in keys.iter() {
+        assert_eq!(position(fen).polyglot_key(), key, "{}", fen);
+    }
+
+    // The en passant square left by a move must not count unless capturable
+    let moves = [
+        (Square::E2, Square::E4), (Square::D7, Square::D5), (Square::E4, Square::E5),
+        (Square::F7, Square::F5), (Square::E1, Square::E2), (Square::E8, Square::F7),
+    ];
+    let mut pos = Position::default();
+    for (&(from, to), &(_, key)) in moves.iter().zip(keys[1..].iter()) {
+        pos.make_move(Move::normal(from, to));
+        assert_eq!(pos.polyglot_key(), key);
+    }
+}
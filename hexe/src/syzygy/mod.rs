@@ -0,0 +1,476 @@
+//! Probing of [Syzygy] endgame tablebases.
+//!
+//! Syzygy tables come in two kinds, each a file per set of pieces:
+//!
+//! - **WDL** (`.rtbw`) tables store whether the player to move wins, draws or
+//!   loses, accounting for the fifty-move rule.
+//!
+//! - **DTZ** (`.rtbz`) tables store the number of plies until the fifty-move
+//!   counter is zeroed by a capture or pawn move along an optimal line.
+//!
+//! Tables are opened upon their first probe and read from disk as needed, so
+//! only the headers of the tables in use and a few of their most recently read
+//! blocks are kept in memory.
+//!
+//! [Syzygy]: https://syzygy-tables.info
+
+// Errors are only read when logging
+#![cfg_attr(not(feature = "log"), allow(unused_variables))]
+
+use std::cmp;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::cell::UnsafeCell;
+use std::sync::Once;
+
+use board::MultiBoard;
+use core::mv::MoveVec;
+use prelude::*;
+
+mod table;
+use self::table::{Kind, Material, Table};
+pub use self::table::MAX_PIECES;
+
+#[cfg(test)]
+mod tests;
+
+/// The rank of a root move that wins before the fifty-move counter runs out,
+/// less its distance to zeroing.
+const MAX_DTZ: i32 = 1 << 18;
+
+/// The outcome of a position for the player to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    /// A loss.
+    Loss = -2,
+    /// A loss that is saved by the fifty-move rule.
+    BlessedLoss = -1,
+    /// A draw.
+    Draw = 0,
+    /// A win that is spoiled by the fifty-move rule.
+    CursedWin = 1,
+    /// A win.
+    Win = 2,
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    #[inline]
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss        => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw        => Wdl::Draw,
+            Wdl::CursedWin   => Wdl::BlessedLoss,
+            Wdl::Win         => Wdl::Loss,
+        }
+    }
+}
+
+impl Wdl {
+    #[inline]
+    fn from_i32(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0  => Some(Wdl::Draw),
+            1  => Some(Wdl::CursedWin),
+            2  => Some(Wdl::Win),
+            _  => None,
+        }
+    }
+
+    #[inline]
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+
+    /// Returns the distance to zeroing of a position whose best move zeroes
+    /// the fifty-move counter.
+    #[inline]
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Loss        => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw        => 0,
+            Wdl::CursedWin   => 101,
+            Wdl::Win         => 1,
+        }
+    }
+}
+
+/// A table file that is opened upon its first probe.
+struct Entry {
+    path: PathBuf,
+    material: Material,
+    opened: Once,
+    table: UnsafeCell<Option<Table>>,
+}
+
+// The table is only written within `opened`, before it may be read
+unsafe impl Sync for Entry {}
+
+impl Entry {
+    fn new(path: PathBuf, material: Material) -> Entry {
+        Entry { path, material, opened: Once::new(), table: UnsafeCell::new(None) }
+    }
+
+    fn get(&self, kind: Kind) -> Option<&Table> {
+        self.opened.call_once(|| {
+            match Table::open(&self.path, &self.material, kind) {
+                Ok(table) => unsafe { *self.table.get() = Some(table) },
+                Err(err) => {
+                    error!("Could not open {}: {}", self.path.display(), err);
+                },
+            }
+        });
+        unsafe { (*self.table.get()).as_ref() }
+    }
+}
+
+/// A set of Syzygy tables.
+///
+/// # Examples
+///
+/// ```
+/// use hexe::syzygy::Tablebase;
+/// use hexe::prelude::*;
+///
+/// # return;
+/// let tablebase = Tablebase::open("/path/to/syzygy").unwrap();
+///
+/// let fen = "8/8/8/8/8/2k5/8/KQ6 w - - 0 1".parse().unwrap();
+/// let mut position = Position::from_fen(&fen).unwrap();
+/// println!("{:?}", tablebase.probe_wdl(&mut position));
+/// ```
+#[derive(Default)]
+pub struct Tablebase {
+    wdl: HashMap<Material, Entry>,
+    dtz: HashMap<Material, Entry>,
+    max_pieces: usize,
+}
+
+impl fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tablebase")
+         .field("wdl", &self.wdl.len())
+         .field("dtz", &self.dtz.len())
+         .field("max_pieces", &self.max_pieces)
+         .finish()
+    }
+}
+
+impl Tablebase {
+    /// Creates a tablebase without any tables.
+    #[inline]
+    pub fn new() -> Tablebase {
+        Tablebase::default()
+    }
+
+    /// Finds the tables within `paths`, a list of directories separated as in
+    /// the `PATH` environment variable.
+    pub fn open(paths: &str) -> io::Result<Tablebase> {
+        let mut tablebase = Tablebase::new();
+        for dir in env::split_paths(paths) {
+            if !dir.as_os_str().is_empty() {
+                tablebase.add_directory(dir)?;
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// Adds the tables found directly within `dir`, returning how many were
+    /// added.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<usize> {
+        let mut added = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let kind = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("rtbw") => Kind::Wdl,
+                Some("rtbz") => Kind::Dtz,
+                _ => continue,
+            };
+            let material = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => match Material::parse(name) {
+                    Some(material) => material,
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            let tables = match kind {
+                Kind::Wdl => &mut self.wdl,
+                Kind::Dtz => &mut self.dtz,
+            };
+            trace!("Found table {}", path.display());
+            self.max_pieces = cmp::max(self.max_pieces, material.len());
+            tables.insert(material, Entry::new(path, material));
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// Returns the number of table files found.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.wdl.len() + self.dtz.len()
+    }
+
+    /// Returns whether no tables were found.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the greatest number of pieces, kings included, in any table.
+    #[inline]
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Returns whether `position` may be found within the tables, which only
+    /// hold the results of standard chess.
+    #[inline]
+    fn covers(&self, position: &Position) -> bool {
+        position.variant().has_standard_goal()
+            && position.board().len() <= self.max_pieces && position.rights().is_empty()
+    }
+
+    /// Returns the outcome of `position` for the player to move, or `None` if
+    /// a table needed is missing.
+    ///
+    /// `position` is left as it was given.
+    pub fn probe_wdl(&self, position: &mut Position) -> Option<Wdl> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.search(position, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the number of plies until the fifty-move counter is zeroed
+    /// with optimal play, or `None` if a table needed is missing.
+    ///
+    /// The value is positive if the player to move wins, negative if they
+    /// lose and 0 if the position is drawn. Wins and losses spoiled by the
+    /// fifty-move rule are offset by 100.
+    ///
+    /// `position` is left as it was given.
+    pub fn probe_dtz(&self, position: &mut Position) -> Option<i32> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.dtz(position)
+    }
+
+    /// Ranks `moves` by the outcome that they lead to, returning `None` if a
+    /// table needed is missing.
+    ///
+    /// Greater ranks are better. Among wins within reach of the fifty-move
+    /// rule, those that zero the counter soonest rank highest, and the
+    /// reverse for losses. Distances to zeroing are used when available,
+    /// falling back to outcomes otherwise.
+    pub fn rank_root_moves(&self, position: &mut Position, moves: &[Move]) -> Option<Vec<i32>> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.dtz_ranks(position, moves).or_else(|| self.wdl_ranks(position, moves))
+    }
+
+    fn dtz_ranks(&self, position: &mut Position, moves: &[Move]) -> Option<Vec<i32>> {
+        let halfmoves = position.halfmoves() as i32;
+        let mut ranks = Vec::with_capacity(moves.len());
+
+        for &mv in moves {
+            position.make_move(mv);
+
+            // Count from the root, including the move itself
+            let dtz = if position.halfmoves() == 0 {
+                self.search(position, false).map(|(wdl, _)| (-wdl).dtz_before_zeroing())
+            } else if position.is_repetition(3) || position.is_fifty_move_draw() {
+                Some(0)
+            } else {
+                self.dtz(position).map(|dtz| -dtz + (-dtz).signum())
+            };
+            let dtz = dtz.map(|dtz| {
+                if dtz == 2 && position.in_check() && !has_legal_moves(position) {
+                    1
+                } else {
+                    dtz
+                }
+            });
+
+            position.unmake_move(mv);
+            let dtz = dtz?;
+
+            ranks.push(if dtz > 0 {
+                if dtz + halfmoves <= 99 { MAX_DTZ - dtz } else { 1 }
+            } else if dtz < 0 {
+                if -dtz + halfmoves <= 99 { -MAX_DTZ - dtz } else { -1 }
+            } else {
+                0
+            });
+        }
+        Some(ranks)
+    }
+
+    fn wdl_ranks(&self, position: &mut Position, moves: &[Move]) -> Option<Vec<i32>> {
+        let mut ranks = Vec::with_capacity(moves.len());
+        for &mv in moves {
+            position.make_move(mv);
+            let wdl = self.search(position, false);
+            position.unmake_move(mv);
+            ranks.push(-(wdl?.0 as i32));
+        }
+        Some(ranks)
+    }
+
+    /// Returns the table holding the pieces on `board` and whether the colors
+    /// are swapped.
+    fn find<'a>(tables: &'a HashMap<Material, Entry>, board: &MultiBoard, kind: Kind)
+        -> Option<(&'a Table, bool)>
+    {
+        let material = Material::from_board(board);
+        if let Some(entry) = tables.get(&material) {
+            return entry.get(kind).map(|table| (table, false));
+        }
+        let entry = tables.get(&material.flipped())?;
+        entry.get(kind).map(|table| (table, true))
+    }
+
+    fn probe_wdl_table(&self, position: &Position) -> Option<Wdl> {
+        let board = position.board();
+        if board.len() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let (table, flipped) = Tablebase::find(&self.wdl, board, Kind::Wdl)?;
+        match table.probe_wdl(board, position.player(), flipped) {
+            Ok(wdl) => Some(wdl),
+            Err(err) => {
+                error!("Could not probe WDL table: {}", err);
+                None
+            },
+        }
+    }
+
+    /// Returns the value of the DTZ table, which is `Some(None)` if the table
+    /// only holds the other player to move.
+    fn probe_dtz_table(&self, position: &Position, wdl: Wdl) -> Option<Option<i32>> {
+        let board = position.board();
+        let (table, flipped) = Tablebase::find(&self.dtz, board, Kind::Dtz)?;
+        match table.probe_dtz(board, position.player(), flipped, wdl) {
+            Ok(dtz) => Some(dtz),
+            Err(err) => {
+                error!("Could not probe DTZ table: {}", err);
+                None
+            },
+        }
+    }
+
+    /// Returns the outcome of `position` along with whether the best move
+    /// zeroes the fifty-move counter.
+    ///
+    /// Captures are searched since tables hold unreliable values where
+    /// en passant is possible. With `pawn_moves`, pawn moves are searched too.
+    fn search(&self, position: &mut Position, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let mut moves = MoveVec::new();
+        position.gen(&mut moves).legal();
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in moves.iter() {
+            if !position.is_capture(mv) && !(pawn_moves && is_pawn_move(position, mv)) {
+                continue;
+            }
+            searched += 1;
+
+            position.make_move(mv);
+            let value = self.search(position, false);
+            position.unmake_move(mv);
+
+            let value = -value?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // The table cannot be trusted when only zeroing moves are available
+        let searched_all = searched != 0 && searched == moves.len();
+        let value = if searched_all { best } else { self.probe_wdl_table(position)? };
+
+        // Tables hold arbitrary values when the best move is a winning capture
+        if best >= value {
+            Some((best, best > Wdl::Draw || searched_all))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, position: &mut Position) -> Option<i32> {
+        let (wdl, zeroing) = self.search(position, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        if let Some(dtz) = self.probe_dtz_table(position, wdl)? {
+            let spoiled = wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss;
+            return Some((dtz + if spoiled { 100 } else { 0 }) * wdl.signum());
+        }
+
+        // The table holds the other player to move, so take the best reply
+        let mut moves = MoveVec::new();
+        position.gen(&mut moves).legal();
+
+        let mut min = None;
+        for &mv in moves.iter() {
+            let zeroing = position.is_capture(mv) || is_pawn_move(position, mv);
+            position.make_move(mv);
+
+            // The distance of a zeroing move is known before making it
+            let dtz = if zeroing {
+                self.search(position, false).map(|(wdl, _)| -wdl.dtz_before_zeroing())
+            } else {
+                self.dtz(position).map(|dtz| -dtz)
+            };
+            let mates = dtz == Some(1) && position.in_check() && !has_legal_moves(position);
+            position.unmake_move(mv);
+
+            let mut dtz = dtz?;
+            if mates {
+                min = Some(1);
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz.signum() == wdl.signum() && min.map_or(true, |min| dtz < min) {
+                min = Some(dtz);
+            }
+        }
+
+        // Without any moves, the player to move is mated
+        Some(min.unwrap_or(-1))
+    }
+}
+
+#[inline]
+fn is_pawn_move(position: &Position, mv: Move) -> bool {
+    position.pieces().get(mv.src()).map(|p| p.role()) == Some(Role::Pawn)
+}
+
+#[inline]
+fn has_legal_moves(position: &Position) -> bool {
+    let mut moves = MoveVec::new();
+    position.gen(&mut moves).legal();
+    !moves.is_empty()
+}
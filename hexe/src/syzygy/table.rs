@@ -0,0 +1,1083 @@
+//! Decoding of Syzygy table files.
+//!
+//! Each table stores a value for every placement of its pieces, compressed
+//! with canonical Huffman codes over symbols built by recursive pairing.
+//! Placements are first reduced by symmetry and then mapped to an index, much
+//! like a mixed radix number.
+
+use std::cmp;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::u64;
+
+use parking_lot::Mutex;
+
+use board::MultiBoard;
+use prelude::*;
+use super::Wdl;
+
+/// The greatest number of pieces, kings included, that a table may hold.
+pub const MAX_PIECES: usize = 7;
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// Flags stored per file of a table.
+mod flag {
+    pub const STM:          u8 = 1;
+    pub const MAPPED:       u8 = 2;
+    pub const WIN_PLIES:    u8 = 4;
+    pub const LOSS_PLIES:   u8 = 8;
+    pub const WIDE:         u8 = 16;
+    pub const SINGLE_VALUE: u8 = 128;
+}
+
+/// Roles in the order they appear in table names.
+static NAME_ORDER: [(Role, char); 6] = [
+    (Role::King,   'K'),
+    (Role::Queen,  'Q'),
+    (Role::Rook,   'R'),
+    (Role::Bishop, 'B'),
+    (Role::Knight, 'N'),
+    (Role::Pawn,   'P'),
+];
+
+/// The pieces of each color, by which tables are named and looked up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Material {
+    counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    /// Parses a table name such as `KRPvKR`, where the pieces before the `v`
+    /// belong to white.
+    pub fn parse(name: &str) -> Option<Material> {
+        if name.len() > MAX_PIECES + 1 {
+            return None;
+        }
+        let mut sides = name.split('v');
+        let (white, black) = (sides.next()?, sides.next()?);
+        if sides.next().is_some() {
+            return None;
+        }
+
+        let mut counts = [[0; 6]; 2];
+        for (side, counts) in [white, black].iter().zip(counts.iter_mut()) {
+            for ch in side.chars() {
+                let &(role, _) = NAME_ORDER.iter().find(|&&(_, c)| c == ch)?;
+                counts[role as usize] += 1;
+            }
+            if counts[Role::King as usize] != 1 {
+                return None;
+            }
+        }
+
+        let material = Material { counts };
+        if material.len() > MAX_PIECES { None } else { Some(material) }
+    }
+
+    /// Returns the pieces on `board`.
+    pub fn from_board(board: &MultiBoard) -> Material {
+        let mut counts = [[0; 6]; 2];
+        for (color, counts) in [Color::White, Color::Black].iter().zip(counts.iter_mut()) {
+            for &(role, _) in NAME_ORDER.iter() {
+                counts[role as usize] = board.count(Piece::new(role, *color)) as u8;
+            }
+        }
+        Material { counts }
+    }
+
+    /// Returns the material with the colors swapped.
+    #[inline]
+    pub fn flipped(&self) -> Material {
+        Material { counts: [self.counts[1], self.counts[0]] }
+    }
+
+    /// Returns the name of the table holding `self`.
+    pub fn name(&self) -> String {
+        let mut name = String::with_capacity(MAX_PIECES + 1);
+        for (index, counts) in self.counts.iter().enumerate() {
+            if index == 1 {
+                name.push('v');
+            }
+            for &(role, ch) in NAME_ORDER.iter() {
+                for _ in 0..counts[role as usize] {
+                    name.push(ch);
+                }
+            }
+        }
+        name
+    }
+
+    /// Returns the total number of pieces.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.counts.iter().flat_map(|c| c.iter()).map(|&n| n as usize).sum()
+    }
+
+    #[inline]
+    fn count(&self, color: Color, role: Role) -> usize {
+        self.counts[color as usize][role as usize] as usize
+    }
+}
+
+/// Squares as indexed by the tables, which match `Square`.
+type Sq = usize;
+
+#[inline]
+fn file_of(sq: Sq) -> usize { sq & 7 }
+
+#[inline]
+fn rank_of(sq: Sq) -> usize { sq >> 3 }
+
+/// Returns how far `sq` lies above the A1-H8 diagonal, negative if below.
+#[inline]
+fn off_diagonal(sq: Sq) -> isize {
+    rank_of(sq) as isize - file_of(sq) as isize
+}
+
+/// Lookup tables for encoding piece placements.
+struct Consts {
+    /// The number of ways to choose `k` of `n` squares, as `[k][n]`.
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// Pawn squares A2-H7 ordered such that the leading pawn has the greatest
+    /// value: nearest the edge, then lowest rank.
+    map_pawns: [usize; 64],
+    /// The index of the leading pawn placement, as `[count][square]`.
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES - 1],
+    /// The number of leading pawn placements, as `[count][file]`.
+    lead_pawns_size: [[u64; 4]; MAX_PIECES - 1],
+    /// Squares below the A1-H8 diagonal mapped to 0...27.
+    map_b1h1h7: [u64; 64],
+    /// Squares of the A1-D1-D4 triangle mapped to 0...9, diagonal last.
+    map_a1d1d4: [u64; 64],
+    /// The 462 placements of two kings, the first within A1-D1-D4.
+    map_kk: [[u64; 64]; 10],
+}
+
+impl Consts {
+    fn new() -> Consts {
+        let mut consts = Consts {
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES - 1],
+            lead_pawns_size: [[0; 4]; MAX_PIECES - 1],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_diagonal(sq) < 0 {
+                consts.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = Vec::new();
+        code = 0;
+        for sq in 0..28 {
+            if off_diagonal(sq) < 0 && file_of(sq) <= 3 {
+                consts.map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_diagonal(sq) == 0 && file_of(sq) <= 3 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            consts.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        // Both kings on the diagonal are encoded last
+        let mut both_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28 {
+                // Every square outside of the triangle is mapped to 0 as well
+                if consts.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    let adjacent = (file_of(s1) as isize - file_of(s2) as isize).abs() <= 1
+                                && (rank_of(s1) as isize - rank_of(s2) as isize).abs() <= 1;
+                    if adjacent || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0) {
+                        continue;
+                    } else if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_diagonal.push((idx as usize, s2));
+                    } else {
+                        consts.map_kk[idx as usize][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_diagonal {
+            consts.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        consts.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..cmp::min(MAX_PIECES, n + 1) {
+                let left  = if k > 0 { consts.binomial[k - 1][n - 1] } else { 0 };
+                let right = if k < n { consts.binomial[k][n - 1] } else { 0 };
+                consts.binomial[k][n] = left + right;
+            }
+        }
+
+        let mut available = 47;
+        for count in 1..(MAX_PIECES - 1) {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = rank * 8 + file;
+                    if count == 1 {
+                        consts.map_pawns[sq] = available;
+                        consts.map_pawns[sq ^ 7] = available.saturating_sub(1);
+                        available = available.saturating_sub(2);
+                    }
+                    consts.lead_pawn_idx[count][sq] = idx;
+                    idx += consts.binomial[count - 1][consts.map_pawns[sq]];
+                }
+                consts.lead_pawns_size[count][file] = idx;
+            }
+        }
+
+        consts
+    }
+
+    #[inline]
+    fn get() -> &'static Consts {
+        lazy_static! {
+            static ref CONSTS: Consts = Consts::new();
+        }
+        &CONSTS
+    }
+}
+
+#[inline]
+fn le_u16(b: &[u8]) -> u16 {
+    b[0] as u16 | (b[1] as u16) << 8
+}
+
+#[inline]
+fn le_u32(b: &[u8]) -> u32 {
+    le_u16(b) as u32 | (le_u16(&b[2..]) as u32) << 16
+}
+
+#[inline]
+fn be_u32(b: &[u8]) -> u32 {
+    (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+}
+
+#[inline]
+fn be_u64(b: &[u8]) -> u64 {
+    (be_u32(b) as u64) << 32 | be_u32(&b[4..]) as u64
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A file read at arbitrary offsets from any thread.
+#[derive(Debug)]
+struct RandomAccessFile(File);
+
+impl RandomAccessFile {
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        use std::os::unix::fs::FileExt;
+        self.0.read_at(buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        use std::os::windows::fs::FileExt;
+        self.0.seek_read(buf, offset)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn read_at(&self, _: &mut [u8], _: u64) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "positional reads are unsupported"))
+    }
+
+    /// Fills `buf` from `offset`, leaving the bytes past the end of the file
+    /// as they are.
+    fn read_some_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.read_at(&mut buf[read..], offset + read as u64) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(read)
+    }
+
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        if self.read_some_at(buf, offset)? == buf.len() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "table file is truncated"))
+        }
+    }
+}
+
+/// The number of bits of a block's offset that select its cache slot.
+const CACHE_BITS: u32 = 6;
+
+/// A block read from a table file.
+#[derive(Debug)]
+struct CachedBlock {
+    /// The file offset of the block, or `u64::MAX` if none was read.
+    offset: u64,
+    /// The bytes of the block followed by 8 zeros, to be read past its end.
+    data: Vec<u8>,
+}
+
+/// The blocks of a table read most recently, so that probes of related
+/// positions do not each read from disk.
+#[derive(Debug)]
+struct BlockCache {
+    slots: Vec<Mutex<CachedBlock>>,
+}
+
+impl BlockCache {
+    fn new() -> BlockCache {
+        let slots = (0..(1 << CACHE_BITS)).map(|_| {
+            Mutex::new(CachedBlock { offset: u64::MAX, data: Vec::new() })
+        }).collect();
+        BlockCache { slots }
+    }
+
+    /// Calls `f` with the block of `len` bytes at `offset`, reading it from
+    /// `file` unless it is cached.
+    fn with_block<T, F>(&self, file: &RandomAccessFile, offset: u64, len: usize, f: F)
+        -> io::Result<T>
+        where F: FnOnce(&[u8]) -> io::Result<T>
+    {
+        let hash = offset.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - CACHE_BITS);
+        let mut slot = self.slots[hash as usize].lock();
+        if slot.offset != offset {
+            slot.offset = u64::MAX;
+            slot.data.clear();
+            slot.data.resize(len + 8, 0);
+            file.read_some_at(&mut slot.data[..len], offset)?;
+            slot.offset = offset;
+        }
+        f(&slot.data)
+    }
+}
+
+/// Reads the header of a table file in sequence.
+struct Reader<'a> {
+    file: &'a RandomAccessFile,
+    pos: u64,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.file.read_exact_at(&mut buf, self.pos)?;
+        self.pos += len as u64;
+        Ok(buf)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.file.read_exact_at(&mut buf, self.pos)?;
+        self.pos += 1;
+        Ok(buf[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let mut buf = [0; 2];
+        self.file.read_exact_at(&mut buf, self.pos)?;
+        self.pos += 2;
+        Ok(le_u16(&buf))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.file.read_exact_at(&mut buf, self.pos)?;
+        self.pos += 4;
+        Ok(le_u32(&buf))
+    }
+
+    /// Skips to the next multiple of `n`, which is a power of two.
+    #[inline]
+    fn align(&mut self, n: u64) {
+        self.pos = (self.pos + n - 1) & !(n - 1);
+    }
+}
+
+/// A sparse index entry: the block holding value `k * span + span / 2` and
+/// that value's offset within the block.
+#[derive(Copy, Clone, Debug)]
+struct SparseEntry {
+    block: u32,
+    offset: u16,
+}
+
+/// The compressed values of one side to move and leading pawn file.
+#[derive(Clone, Debug, Default)]
+struct Pairs {
+    flags: u8,
+    /// The table's piece codes in encoding order.
+    pieces: [u8; MAX_PIECES],
+    /// The number of pieces per group, terminated by 0.
+    group_len: [usize; MAX_PIECES + 1],
+    /// The index multiplier of each group, followed by the table size.
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: u64,
+    span: u64,
+    /// The number of sparse index entries.
+    sparse_size: usize,
+    /// The number of block lengths, including padding.
+    blocks: usize,
+    min_sym_len: u8,
+    lowest_sym: Vec<u16>,
+    base64: Vec<u64>,
+    /// The number of values that each symbol expands to, minus one.
+    sym_len: Vec<u8>,
+    /// The pair of symbols that each symbol expands to.
+    btree: Vec<[u8; 3]>,
+    sparse_index: Vec<SparseEntry>,
+    /// The number of values in each block, minus one.
+    block_length: Vec<u16>,
+    /// The file offset of the first block.
+    data: u64,
+    /// The offsets within the DTZ value map per WDL outcome.
+    map_idx: [usize; 4],
+}
+
+impl Pairs {
+    #[inline]
+    fn left(&self, sym: usize) -> usize {
+        let lr = self.btree[sym];
+        (((lr[1] & 0xF) as usize) << 8) | lr[0] as usize
+    }
+
+    #[inline]
+    fn right(&self, sym: usize) -> usize {
+        let lr = self.btree[sym];
+        ((lr[2] as usize) << 4) | (lr[1] >> 4) as usize
+    }
+
+    /// Reads the block sizes and Huffman code of `self`.
+    fn read_sizes(&mut self, r: &mut Reader) -> io::Result<()> {
+        self.flags = r.u8()?;
+        if self.flags & flag::SINGLE_VALUE != 0 {
+            // The value of every position
+            self.min_sym_len = r.u8()?;
+            return Ok(());
+        }
+
+        let groups = self.group_len.iter().position(|&len| len == 0).unwrap_or(0);
+        let tb_size = self.group_idx[groups];
+
+        self.block_size = 1 << r.u8()?;
+        self.span = 1 << r.u8()?;
+        self.sparse_size = ((tb_size + self.span - 1) / self.span) as usize;
+        let padding = r.u8()? as usize;
+        self.blocks = r.u32()? as usize + padding;
+        let max_sym_len = r.u8()?;
+        self.min_sym_len = r.u8()?;
+        if max_sym_len < self.min_sym_len || self.min_sym_len == 0 {
+            return Err(invalid("invalid symbol lengths"));
+        }
+
+        // Symbols of each length are consecutive, the longest being lowest
+        let lengths = (max_sym_len - self.min_sym_len) as usize + 1;
+        self.lowest_sym = (0..lengths).map(|_| r.u16()).collect::<io::Result<_>>()?;
+        self.base64 = vec![0; lengths];
+        for i in (0..(lengths - 1)).rev() {
+            self.base64[i] = self.base64[i + 1]
+                .wrapping_add(self.lowest_sym[i] as u64)
+                .wrapping_sub(self.lowest_sym[i + 1] as u64) / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            let shift = 64 - i as u32 - self.min_sym_len as u32;
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+
+        let symbols = r.u16()? as usize;
+        let tree = r.bytes(symbols * 3)?;
+        self.btree = tree.chunks(3).map(|lr| [lr[0], lr[1], lr[2]]).collect();
+        r.pos += (symbols & 1) as u64;
+
+        for sym in 0..symbols {
+            if self.right(sym) != 0xFFF
+                && (self.left(sym) >= symbols || self.right(sym) >= symbols)
+            {
+                return Err(invalid("invalid symbol tree"));
+            }
+        }
+        self.sym_len = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.sym_len[sym] = self.expand_len(sym, &mut visited);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the length of `sym`, computing those of its children first.
+    fn expand_len(&mut self, sym: usize, visited: &mut [bool]) -> u8 {
+        // The tree is acyclic
+        visited[sym] = true;
+        let right = self.right(sym);
+        if right == 0xFFF {
+            return 0;
+        }
+        let left = self.left(sym);
+        if !visited[left] {
+            self.sym_len[left] = self.expand_len(left, visited);
+        }
+        if !visited[right] {
+            self.sym_len[right] = self.expand_len(right, visited);
+        }
+        self.sym_len[left].wrapping_add(self.sym_len[right]).wrapping_add(1)
+    }
+
+    /// Returns the value stored at `idx`.
+    fn decompress(&self, file: &RandomAccessFile, cache: &BlockCache, idx: u64)
+        -> io::Result<usize>
+    {
+        if self.flags & flag::SINGLE_VALUE != 0 {
+            return Ok(self.min_sym_len as usize);
+        }
+
+        // Start from the nearest known value and walk to the block holding
+        // the one at `idx`
+        let entry = *self.sparse_index.get((idx / self.span) as usize)
+                                      .ok_or_else(|| invalid("index out of range"))?;
+        let mut block = entry.block as usize;
+        let mut offset = entry.offset as i64
+                       + (idx % self.span) as i64 - (self.span / 2) as i64;
+
+        let length = |block: usize| -> io::Result<i64> {
+            self.block_length.get(block)
+                .map(|&len| len as i64)
+                .ok_or_else(|| invalid("block out of range"))
+        };
+        while offset < 0 {
+            block = block.checked_sub(1).ok_or_else(|| invalid("block out of range"))?;
+            offset += length(block)? + 1;
+        }
+        while offset > length(block)? {
+            offset -= length(block)? + 1;
+            block += 1;
+        }
+
+        let start = self.data + block as u64 * self.block_size;
+        let len = self.block_size as usize;
+        cache.with_block(file, start, len, |buf| self.decode(buf, offset))
+    }
+
+    /// Returns the value `offset` values into the block `buf`.
+    fn decode(&self, buf: &[u8], mut offset: i64) -> io::Result<usize> {
+        let mut ptr = 8;
+        let mut buf64 = be_u64(buf);
+        let mut buf64_size = 64;
+        let min_len = self.min_sym_len as u32;
+
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf64 < self.base64[len] {
+                len += 1;
+                if len == self.base64.len() {
+                    return Err(invalid("invalid symbol"));
+                }
+            }
+            let shift = 64 - len as u32 - min_len;
+            sym = ((buf64 - self.base64[len]).checked_shr(shift).unwrap_or(0)) as usize
+                + self.lowest_sym[len] as usize;
+            let expanded = *self.sym_len.get(sym).ok_or_else(|| invalid("invalid symbol"))?;
+
+            if offset < expanded as i64 + 1 {
+                break;
+            }
+            offset -= expanded as i64 + 1;
+
+            let len = len as u32 + min_len;
+            buf64 = buf64.checked_shl(len).unwrap_or(0);
+            buf64_size -= len as i32;
+            if buf64_size <= 32 {
+                if ptr + 4 > buf.len() {
+                    return Err(invalid("block overrun"));
+                }
+                buf64_size += 32;
+                let next = be_u32(&buf[ptr..]);
+                buf64 |= (next as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // Expand the symbol into the pair holding the value
+        while self.sym_len[sym] != 0 {
+            let left = self.left(sym);
+            if offset < self.sym_len[left] as i64 + 1 {
+                sym = left;
+            } else {
+                offset -= self.sym_len[left] as i64 + 1;
+                sym = self.right(sym);
+            }
+        }
+        Ok(self.left(sym))
+    }
+}
+
+/// The properties of a table that follow from its name.
+#[derive(Copy, Clone, Debug)]
+struct Layout {
+    pieces: usize,
+    has_pawns: bool,
+    /// Whether a color has exactly one piece of some kind other than king.
+    has_unique_pieces: bool,
+    /// The pawns of the leading color, which has the fewest, then the other.
+    pawn_count: [usize; 2],
+    /// Whether both colors have the same pieces.
+    symmetric: bool,
+}
+
+impl Layout {
+    fn new(material: &Material) -> Layout {
+        let white = material.count(Color::White, Role::Pawn);
+        let black = material.count(Color::Black, Role::Pawn);
+        let unique = [Color::White, Color::Black].iter().any(|&color| {
+            NAME_ORDER[1..].iter().any(|&(role, _)| material.count(color, role) == 1)
+        });
+        let white_leads = black == 0 || (white != 0 && black >= white);
+        Layout {
+            pieces: material.len(),
+            has_pawns: white + black != 0,
+            has_unique_pieces: unique,
+            pawn_count: if white_leads { [white, black] } else { [black, white] },
+            symmetric: *material == material.flipped(),
+        }
+    }
+
+    /// Whether both colors have pawns, which are then encoded as two groups.
+    #[inline]
+    fn both_pawns(&self) -> bool {
+        self.has_pawns && self.pawn_count[1] != 0
+    }
+}
+
+/// The outcome or distance to zeroing stored by a table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Win/draw/loss.
+    Wdl,
+    /// Distance to zeroing the fifty-move counter.
+    Dtz,
+}
+
+/// A single table file.
+#[derive(Debug)]
+pub struct Table {
+    kind: Kind,
+    file: RandomAccessFile,
+    cache: BlockCache,
+    layout: Layout,
+    /// Indexed by leading pawn file and then by side to move.
+    pairs: Vec<Vec<Pairs>>,
+    /// The values of DTZ tables, per leading pawn file and WDL outcome.
+    map: Vec<u8>,
+}
+
+impl Table {
+    /// Opens the table of `material` at `path`, reading its header.
+    pub fn open(path: &Path, material: &Material, kind: Kind) -> io::Result<Table> {
+        let file = RandomAccessFile(File::open(path)?);
+        let layout = Layout::new(material);
+        let consts = Consts::get();
+
+        let mut r = Reader { file: &file, pos: 0 };
+        let magic = r.bytes(4)?;
+        let expected = match kind { Kind::Wdl => WDL_MAGIC, Kind::Dtz => DTZ_MAGIC };
+        if magic != expected {
+            return Err(invalid("invalid magic"));
+        }
+
+        let flags = r.u8()?;
+        if (flags & 2 != 0) != layout.has_pawns || (flags & 1 != 0) == layout.symmetric {
+            let msg = format!("{} does not match the table's pieces", material.name());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+
+        let sides = if kind == Kind::Wdl && !layout.symmetric { 2 } else { 1 };
+        let files = if layout.has_pawns { 4 } else { 1 };
+        let both_pawns = layout.both_pawns();
+        let mut pairs = vec![vec![Pairs::default(); sides]; files];
+
+        for (file, pairs) in pairs.iter_mut().enumerate() {
+            let first = r.u8()?;
+            let second = if both_pawns { r.u8()? } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+
+            for k in 0..layout.pieces {
+                let byte = r.u8()?;
+                for (side, pairs) in pairs.iter_mut().enumerate() {
+                    pairs.pieces[k] = if side == 0 { byte & 0xF } else { byte >> 4 };
+                }
+            }
+            for (side, pairs) in pairs.iter_mut().enumerate() {
+                set_groups(consts, &layout, pairs, order[side], file);
+            }
+        }
+
+        r.align(2);
+        for pairs in pairs.iter_mut().flat_map(|p| p.iter_mut()) {
+            pairs.read_sizes(&mut r)?;
+        }
+
+        let mut map = Vec::new();
+        if kind == Kind::Dtz {
+            let start = r.pos;
+            for pairs in pairs.iter_mut() {
+                let pairs = &mut pairs[0];
+                if pairs.flags & flag::MAPPED == 0 {
+                    continue;
+                }
+                if pairs.flags & flag::WIDE != 0 {
+                    r.align(2);
+                    for idx in pairs.map_idx.iter_mut() {
+                        *idx = (r.pos - start) as usize + 2;
+                        r.pos += 2 * r.u16()? as u64;
+                    }
+                } else {
+                    for idx in pairs.map_idx.iter_mut() {
+                        *idx = (r.pos - start) as usize + 1;
+                        r.pos += r.u8()? as u64;
+                    }
+                }
+            }
+            let len = (r.pos - start) as usize;
+            r.pos = start;
+            map = r.bytes(len)?;
+            r.align(2);
+        }
+
+        for pairs in pairs.iter_mut().flat_map(|p| p.iter_mut()) {
+            let len = pairs.sparse_size;
+            let bytes = r.bytes(len * 6)?;
+            pairs.sparse_index = bytes.chunks(6).map(|e| SparseEntry {
+                block: le_u32(e),
+                offset: le_u16(&e[4..]),
+            }).collect();
+        }
+        for pairs in pairs.iter_mut().flat_map(|p| p.iter_mut()) {
+            let len = pairs.blocks;
+            let bytes = r.bytes(len * 2)?;
+            pairs.block_length = bytes.chunks(2).map(le_u16).collect();
+        }
+        for pairs in pairs.iter_mut().flat_map(|p| p.iter_mut()) {
+            r.align(64);
+            pairs.data = r.pos;
+            r.pos += pairs.block_length.len() as u64 * pairs.block_size;
+        }
+
+        Ok(Table { kind, file, cache: BlockCache::new(), layout, pairs, map })
+    }
+
+    /// Returns the outcome for the player to move on `board`.
+    ///
+    /// `flipped` is whether `board` holds the table's material with the
+    /// colors swapped.
+    pub fn probe_wdl(&self, board: &MultiBoard, player: Color, flipped: bool) -> io::Result<Wdl> {
+        debug_assert_eq!(self.kind, Kind::Wdl);
+        let (pairs, idx, _) = match self.encode(board, player, flipped) {
+            Some(encoded) => encoded,
+            None => unreachable!("WDL tables hold both sides to move"),
+        };
+        let value = pairs.decompress(&self.file, &self.cache, idx)?;
+        Wdl::from_i32(value as i32 - 2).ok_or_else(|| invalid("invalid WDL value"))
+    }
+
+    /// Returns the number of plies until the fifty-move counter is zeroed, or
+    /// `None` if the table only holds the other side to move.
+    pub fn probe_dtz(&self, board: &MultiBoard, player: Color, flipped: bool, wdl: Wdl)
+        -> io::Result<Option<i32>>
+    {
+        debug_assert_eq!(self.kind, Kind::Dtz);
+        let (pairs, idx, file) = match self.encode(board, player, flipped) {
+            Some(encoded) => encoded,
+            None => return Ok(None),
+        };
+        let mut value = pairs.decompress(&self.file, &self.cache, idx)?;
+
+        if pairs.flags & flag::MAPPED != 0 {
+            const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+            let base = self.pairs[file][0].map_idx[WDL_MAP[(wdl as i32 + 2) as usize]];
+            let byte = |i: usize| {
+                self.map.get(i).cloned().ok_or_else(|| invalid("map out of range"))
+            };
+            value = if pairs.flags & flag::WIDE != 0 {
+                let i = base + 2 * value;
+                le_u16(&[byte(i)?, byte(i + 1)?]) as usize
+            } else {
+                byte(base + value)? as usize
+            };
+        }
+
+        // Values may be stored in moves rather than plies
+        let plies = match wdl {
+            Wdl::Win  => pairs.flags & flag::WIN_PLIES != 0,
+            Wdl::Loss => pairs.flags & flag::LOSS_PLIES != 0,
+            _ => false,
+        };
+        let value = value as i32;
+        Ok(Some(if plies { value + 1 } else { value * 2 + 1 }))
+    }
+
+    /// Maps the position on `board` to the table's index, returning `None`
+    /// if the table does not hold `player` to move.
+    fn encode(&self, board: &MultiBoard, player: Color, flipped: bool)
+        -> Option<(&Pairs, u64, usize)>
+    {
+        let consts = Consts::get();
+        let layout = &self.layout;
+
+        // Tables are stored with the first named side as white, and only with
+        // white to move when both sides have the same pieces
+        let flip = flipped || (layout.symmetric && player == Color::Black);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_sq    = if flip { 56 } else { 0 };
+        let stm = flip as usize ^ player as usize;
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces  = [0; MAX_PIECES];
+        let mut size = 0;
+
+        // With pawns, the leading pawn's file selects one of four tables
+        let mut lead_pawns = BitBoard::EMPTY;
+        let mut lead_count = 0;
+        let mut file = 0;
+        if layout.has_pawns {
+            let code = self.pairs[0][0].pieces[0] ^ flip_color;
+            let color = if code & 8 == 0 { Color::White } else { Color::Black };
+            lead_pawns = board.bits(Piece::new(Role::Pawn, color));
+            for sq in lead_pawns {
+                squares[size] = sq as usize ^ flip_sq;
+                size += 1;
+            }
+            lead_count = size;
+
+            let mut lead = 0;
+            for i in 1..lead_count {
+                if consts.map_pawns[squares[i]] > consts.map_pawns[squares[lead]] {
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            file = cmp::min(file_of(squares[0]), 7 - file_of(squares[0]));
+        }
+
+        // DTZ tables hold a single side to move
+        if self.kind == Kind::Dtz {
+            let flags = self.pairs[file][0].flags;
+            let holds = (flags & flag::STM) as usize == stm
+                     || (layout.symmetric && !layout.has_pawns);
+            if !holds {
+                return None;
+            }
+        }
+
+        for &color in [Color::White, Color::Black].iter() {
+            for &(role, _) in NAME_ORDER.iter() {
+                let piece = Piece::new(role, color);
+                for sq in board.bits(piece) - lead_pawns {
+                    squares[size] = sq as usize ^ flip_sq;
+                    pieces[size] = code_of(piece) ^ flip_color;
+                    size += 1;
+                }
+            }
+        }
+
+        let side = if self.pairs[file].len() == 2 { stm } else { 0 };
+        let pairs = &self.pairs[file][side];
+
+        // Order the pieces as encoded by the table
+        for i in lead_count..(size - 1) {
+            for j in (i + 1)..size {
+                if pairs.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // Mirror the leading piece into files A-D
+        if file_of(squares[0]) > 3 {
+            for sq in &mut squares[..size] {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if layout.has_pawns {
+            idx = consts.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|&sq| consts.map_pawns[sq]);
+            for i in 1..lead_count {
+                idx += consts.binomial[i][consts.map_pawns[squares[i]]];
+            }
+        } else {
+            // Mirror the leading piece into ranks 1-4 and then below the
+            // A1-H8 diagonal
+            if rank_of(squares[0]) > 3 {
+                for sq in &mut squares[..size] {
+                    *sq ^= 56;
+                }
+            }
+            for i in 0..pairs.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in &mut squares[i..size] {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            let (s0, s1) = (squares[0], squares[1]);
+            if layout.has_unique_pieces {
+                let s2 = squares[2];
+                let adjust1 = (s1 > s0) as u64;
+                let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                let (r0, r1, r2) = (rank_of(s0) as u64, rank_of(s1) as u64, rank_of(s2) as u64);
+
+                idx = if off_diagonal(s0) != 0 {
+                    (consts.map_a1d1d4[s0] * 63 + (s1 as u64 - adjust1)) * 62
+                        + s2 as u64 - adjust2
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + r0 * 28 + consts.map_b1h1h7[s1]) * 62 + s2 as u64 - adjust2
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62
+                        + r0 * 7 * 28
+                        + (r1 - adjust1) * 28
+                        + consts.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28
+                        + r0 * 7 * 6
+                        + (r1 - adjust1) * 6
+                        + (r2 - adjust2)
+                };
+            } else {
+                idx = consts.map_kk[consts.map_a1d1d4[s0] as usize][s1];
+            }
+        }
+        idx *= pairs.group_idx[0];
+
+        // The remaining groups in ascending order of squares, skipping those
+        // taken by previous groups
+        let mut start = pairs.group_len[0];
+        let mut remaining_pawns = layout.both_pawns();
+        let mut next = 1;
+        while pairs.group_len[next] != 0 {
+            let len = pairs.group_len[next];
+            squares[start..(start + len)].sort();
+
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| sq > s).count();
+                let skip = if remaining_pawns { 8 } else { 0 };
+                n += consts.binomial[i + 1][sq - adjust - skip];
+            }
+            remaining_pawns = false;
+            idx += n * pairs.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Some((pairs, idx, file))
+    }
+}
+
+/// Returns the code by which tables refer to `piece`.
+#[inline]
+fn code_of(piece: Piece) -> u8 {
+    (piece.role() as u8 + 1) | ((piece.color() as u8) << 3)
+}
+
+/// Splits the pieces of `pairs` into groups and computes the index
+/// multiplier of each, following the group order given by `order`.
+fn set_groups(consts: &Consts, layout: &Layout, pairs: &mut Pairs, order: [u8; 2], file: usize) {
+    // Up to three unique pieces or the two kings lead without pawns
+    let mut first_len: isize = if layout.has_pawns {
+        0
+    } else if layout.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+
+    let mut n = 0;
+    pairs.group_len[0] = 1;
+    for i in 1..layout.pieces {
+        first_len -= 1;
+        if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+            pairs.group_len[n] += 1;
+        } else {
+            n += 1;
+            pairs.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    pairs.group_len[n] = 0;
+
+    let both_pawns = layout.both_pawns();
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free = 64 - pairs.group_len[0] - if both_pawns { pairs.group_len[1] } else { 0 };
+    let mut idx = 1;
+
+    let mut k = 0;
+    while next < n || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            pairs.group_idx[0] = idx;
+            idx *= if layout.has_pawns {
+                consts.lead_pawns_size[pairs.group_len[0]][file]
+            } else if layout.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] as usize {
+            pairs.group_idx[1] = idx;
+            idx *= consts.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+        } else {
+            pairs.group_idx[next] = idx;
+            idx *= consts.binomial[pairs.group_len[next]][free];
+            free -= pairs.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    pairs.group_idx[n] = idx;
+}
+
+#[cfg(test)]
+impl Table {
+    /// Returns the raw value at `idx` for `side` to move.
+    pub(super) fn value_at(&self, side: usize, idx: u64) -> io::Result<usize> {
+        self.pairs[0][side].decompress(&self.file, &self.cache, idx)
+    }
+
+    /// Returns the index of `board` with `player` to move.
+    pub(super) fn index_of(&self, board: &MultiBoard, player: Color, flipped: bool) -> u64 {
+        self.encode(board, player, flipped).unwrap().1
+    }
+}
@@ -0,0 +1,209 @@
+use super::*;
+use core::fen::Fen;
+
+fn position(fen: &str) -> Position {
+    let fen: Fen = fen.parse().unwrap();
+    Position::from_fen(&fen).unwrap()
+}
+
+/// Creates an empty directory unique to `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hexe-syzygy-{}-{}", name, ::std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Returns a KQvK table where every position holds the same value for each
+/// side to move.
+fn kqvk(magic: [u8; 4], values: &[u8]) -> Vec<u8> {
+    let mut file = magic.to_vec();
+    // Split by side to move, without pawns
+    file.push(1);
+    // The leading group is ordered first
+    file.push(0x00);
+    // White queen, white king and black king for both sides to move
+    file.extend_from_slice(&[0x55, 0x66, 0xEE]);
+    file.push(0);
+    for &value in values {
+        file.extend_from_slice(&[0x80, value]);
+    }
+    file.resize(64, 0);
+    file
+}
+
+#[test]
+fn decompress() {
+    const SIZE: usize = 31332;
+    let value = |idx: usize| if (idx / 3) % 2 == 0 { 0 } else { 4 };
+
+    let mut file = kqvk(table::WDL_MAGIC, &[]);
+    file.truncate(10);
+    // Blocks of 8 bytes, each holding 64 single bit symbols
+    file.extend_from_slice(&[0, 3, 15, 0]);
+    let blocks = SIZE / 64 + 1;
+    file.extend_from_slice(&[blocks as u8, (blocks >> 8) as u8, 0, 0]);
+    file.extend_from_slice(&[1, 1, 0, 0, 2, 0]);
+    // Symbols expanding to losses and wins
+    file.extend_from_slice(&[0, 0xF0, 0xFF, 4, 0xF0, 0xFF]);
+    // Black to move is a draw
+    file.extend_from_slice(&[0x80, 2]);
+    // The sparse index points to the middle of the table
+    file.extend_from_slice(&[0, 1, 0, 0, 0, 0]);
+    for _ in 0..(SIZE / 64 + 1) {
+        file.extend_from_slice(&[63, 0]);
+    }
+    let len = (file.len() + 63) & !63;
+    file.resize(len, 0);
+    for block in 0..(SIZE / 64 + 1) {
+        let mut bits = 0u64;
+        for i in 0..64 {
+            if value(block * 64 + i) != 0 {
+                bits |= 1 << (63 - i);
+            }
+        }
+        for byte in 0..8 {
+            file.push((bits >> (56 - byte * 8)) as u8);
+        }
+    }
+
+    let dir = temp_dir("decompress");
+    let path = dir.join("KQvK.rtbw");
+    fs::write(&path, file).unwrap();
+    let material = Material::parse("KQvK").unwrap();
+    let table = Table::open(&path, &material, Kind::Wdl).unwrap();
+
+    for &idx in [0, 1, 2, 3, 63, 64, 100, 16383, 16384, 16385, 20000, SIZE - 1].iter() {
+        assert_eq!(table.value_at(0, idx as u64).unwrap(), value(idx), "{}", idx);
+    }
+    assert_eq!(table.value_at(1, 1234).unwrap(), 2);
+}
+
+#[test]
+fn encoding() {
+    let dir = temp_dir("encoding");
+    let path = dir.join("KQvK.rtbw");
+    fs::write(&path, kqvk(table::WDL_MAGIC, &[4, 0])).unwrap();
+    let material = Material::parse("KQvK").unwrap();
+    let table = Table::open(&path, &material, Kind::Wdl).unwrap();
+
+    // Worked out by hand from the queen, king and king squares, after
+    // mirroring the queen into the A1-D1-D4 triangle
+    let cases = [
+        // (0 * 63 + 0) * 62 + (18 - 2)
+        ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", false, 16),
+        // (2 * 63 + (36 - 1)) * 62 + (56 - 2)
+        ("k7/8/8/4K3/8/8/8/3Q4 w - - 0 1", false, 10036),
+        // H2 is mirrored to A2 and then across the diagonal to B1, taking
+        // E1 to A4 and C8 to H6: (0 * 63 + (24 - 1)) * 62 + (47 - 2)
+        ("2k5/8/8/8/8/8/7Q/4K3 w - - 0 1", false, 1471),
+        // The side to move does not change the index
+        ("8/8/8/8/8/2k5/8/KQ6 b - - 0 1", false, 16),
+        // The colors are swapped and the board is flipped vertically
+        ("kq6/8/2K5/8/8/8/8/8 b - - 0 1", true, 16),
+    ];
+    for &(fen, flipped, idx) in cases.iter() {
+        let pos = position(fen);
+        assert_eq!(table.index_of(pos.board(), pos.player(), flipped), idx, "{}", fen);
+    }
+}
+
+#[test]
+fn material() {
+    let material = Material::parse("KRPvKR").unwrap();
+    assert_eq!(material.name(), "KRPvKR");
+    assert_eq!(material.flipped().name(), "KRvKRP");
+    assert_eq!(material.len(), 5);
+
+    let board = position("8/8/4k3/8/2R5/8/4P3/4K2r w - - 0 1");
+    assert_eq!(Material::from_board(board.board()), material);
+
+    for name in &["KRvR", "KvKvK", "KQKvK", "KXvK", "KQQQQQQvK", ""] {
+        assert_eq!(Material::parse(name), None, "{}", name);
+    }
+}
+
+#[test]
+fn open() {
+    let dir = temp_dir("open");
+    fs::write(dir.join("KQvK.rtbw"), b"").unwrap();
+    fs::write(dir.join("KRPvKR.rtbz"), b"").unwrap();
+    fs::write(dir.join("README.txt"), b"").unwrap();
+
+    let tablebase = Tablebase::open(dir.to_str().unwrap()).unwrap();
+    assert_eq!(tablebase.len(), 2);
+    assert_eq!(tablebase.max_pieces(), 5);
+
+    assert!(Tablebase::open(dir.join("missing").to_str().unwrap()).is_err());
+    assert!(Tablebase::open("").unwrap().is_empty());
+}
+
+#[test]
+fn invalid_table() {
+    let dir = temp_dir("invalid");
+    fs::write(dir.join("KQvK.rtbw"), b"not a table").unwrap();
+    let tablebase = Tablebase::open(dir.to_str().unwrap()).unwrap();
+
+    let mut kqk = position("8/8/8/8/8/2k5/8/KQ6 w - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut kqk), None);
+
+    // Bare kings are drawn without a table
+    let mut kk = position("8/8/8/8/8/2k5/8/K7 w - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut kk), Some(Wdl::Draw));
+
+    // Tables do not hold castling rights
+    let mut castle = position("8/8/8/8/8/2k5/8/4K2R w K - 0 1");
+    assert_eq!(tablebase.probe_wdl(&mut castle), None);
+}
+
+#[test]
+fn probe_wdl() {
+    let dir = temp_dir("wdl");
+    // Wins with white to move, losses with black to move
+    fs::write(dir.join("KQvK.rtbw"), kqvk(table::WDL_MAGIC, &[4, 0])).unwrap();
+    let tablebase = Tablebase::open(dir.to_str().unwrap()).unwrap();
+
+    let cases = [
+        ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", Wdl::Win),
+        ("8/8/8/8/8/2k5/8/KQ6 b - - 0 1", Wdl::Loss),
+        // The colors are swapped
+        ("kq6/8/2K5/8/8/8/8/8 b - - 0 1", Wdl::Win),
+        ("kq6/8/2K5/8/8/8/8/8 w - - 0 1", Wdl::Loss),
+        // The king takes the queen
+        ("8/8/8/8/8/8/1k6/1Q5K b - - 0 1", Wdl::Draw),
+    ];
+    for &(fen, wdl) in cases.iter() {
+        let mut pos = position(fen);
+        let key = pos.key();
+        assert_eq!(tablebase.probe_wdl(&mut pos), Some(wdl), "{}", fen);
+        assert_eq!(pos.key(), key);
+    }
+}
+
+#[test]
+fn probe_dtz() {
+    let dir = temp_dir("dtz");
+    fs::write(dir.join("KQvK.rtbw"), kqvk(table::WDL_MAGIC, &[4, 0])).unwrap();
+    // Only white to move is stored, in moves
+    fs::write(dir.join("KQvK.rtbz"), kqvk(table::DTZ_MAGIC, &[5])).unwrap();
+    let tablebase = Tablebase::open(dir.to_str().unwrap()).unwrap();
+
+    let mut white = position("8/8/8/8/8/2k5/8/KQ6 w - - 0 1");
+    assert_eq!(tablebase.probe_dtz(&mut white), Some(11));
+
+    // Every reply leads to white's stored distance
+    let mut black = position("8/8/8/8/8/2k5/8/KQ6 b - - 0 1");
+    assert_eq!(tablebase.probe_dtz(&mut black), Some(-12));
+
+    let moves = {
+        let mut moves = MoveVec::new();
+        white.gen(&mut moves).legal();
+        moves.iter().cloned().collect::<Vec<_>>()
+    };
+    let ranks = tablebase.rank_root_moves(&mut white, &moves).unwrap();
+    for (mv, rank) in moves.iter().zip(ranks) {
+        // The queen is left to be taken
+        let hangs = ["b1b3", "b1b4", "b1c2", "b1d3"].contains(&&*mv.to_string());
+        assert_eq!(rank, if hangs { 0 } else { MAX_DTZ - 13 }, "{}", mv);
+    }
+}
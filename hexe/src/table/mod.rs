@@ -3,6 +3,8 @@ use std::mem;
 
 use uncon::*;
 
+use core::mv::Move;
+
 use zero::{Zero, ZeroBuffer};
 
 #[cfg(all(test, nightly))]
@@ -121,11 +123,134 @@ impl Cluster {
     }
 }
 
+/// The kind of bound that a table entry's value represents.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Bound {
+    /// The entry is unused.
+    None,
+    /// The value is at most the true score (fail-low).
+    Upper,
+    /// The value is at least the true score (fail-high).
+    Lower,
+    /// The value is the true score.
+    Exact,
+}
+
+/// A single transposition table entry.
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
-struct Entry {
-    mv:  u16,
-    val: i16,
+pub struct Entry {
+    key:   u16,
+    mv:    u16,
+    val:   i16,
+    depth: i8,
+    bound: u8,
 }
 
 unsafe impl Zero for Entry {}
+
+impl Entry {
+    /// Returns the best move found for the position, if any.
+    #[inline]
+    pub fn mv(&self) -> Option<Move> {
+        match self.mv {
+            0 => None,
+            mv => Some(unsafe { mv.into_unchecked() }),
+        }
+    }
+
+    /// Returns the stored value.
+    #[inline]
+    pub fn val(&self) -> i32 {
+        self.val as i32
+    }
+
+    /// Returns the depth to which the position was searched.
+    #[inline]
+    pub fn depth(&self) -> i32 {
+        self.depth as i32
+    }
+
+    /// Returns the kind of bound that `val` represents.
+    #[inline]
+    pub fn bound(&self) -> Bound {
+        match self.bound {
+            1 => Bound::Upper,
+            2 => Bound::Lower,
+            3 => Bound::Exact,
+            _ => Bound::None,
+        }
+    }
+}
+
+/// Returns the upper 16 bits of `key` used to verify entries.
+#[inline]
+fn entry_key(key: u64) -> u16 {
+    (key >> 48) as u16
+}
+
+impl Table {
+    /// Returns the cluster that `key` maps to.
+    ///
+    /// Threads read and write clusters without synchronization. Racy entries
+    /// are tolerated since moves are validated before use.
+    #[inline]
+    fn cluster(&self, key: u64) -> Option<&mut Cluster> {
+        let len = self.0.len();
+        if len == 0 {
+            None
+        } else {
+            // `len` is always a power of two
+            let cell = &self.0[(key as usize) & (len - 1)];
+            Some(unsafe { &mut *cell.get() })
+        }
+    }
+
+    /// Returns a copy of the entry stored for `key`, if any.
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let check = entry_key(key);
+        self.cluster(key)?.entries().iter().find(|e| {
+            e.key == check && e.bound != Bound::None as u8
+        }).cloned()
+    }
+
+    /// Stores the search result for `key`, replacing the shallowest entry of
+    /// its cluster if there is no previous entry for the same position.
+    pub fn store(&self,
+                 key: u64,
+                 mv: Option<Move>,
+                 val: i32,
+                 depth: i32,
+                 bound: Bound)
+    {
+        let check = entry_key(key);
+        let cluster = match self.cluster(key) {
+            Some(cluster) => cluster,
+            None => return,
+        };
+
+        let entries = cluster.entries_mut();
+        let index = entries.iter().position(|e| {
+            e.key == check || e.bound == Bound::None as u8
+        }).unwrap_or_else(|| {
+            (0..ENTRY_COUNT).min_by_key(|&i| entries[i].depth).unwrap_or(0)
+        });
+
+        let entry = &mut entries[index];
+        let mv = match mv {
+            Some(mv) => u16::from(mv),
+            // Keep the previous best move for the same position
+            None if entry.key == check => entry.mv,
+            None => 0,
+        };
+
+        *entry = Entry {
+            key: check,
+            mv,
+            val: val as i16,
+            depth: depth as i8,
+            bound: bound as u8,
+        };
+    }
+}
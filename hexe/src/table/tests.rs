@@ -38,3 +38,29 @@ fn is_aligned() {
         assert!(table.0.is_aligned());
     }
 }
+
+#[test]
+fn store_probe() {
+    use core::square::Square;
+
+    let table = Table::new(1);
+    let key = 0xDEAD_BEEF_1234_5678;
+    let mv = Move::normal(Square::E2, Square::E4);
+
+    assert!(table.probe(key).is_none());
+
+    table.store(key, Some(mv), -150, 7, Bound::Lower);
+    let entry = table.probe(key).unwrap();
+    assert_eq!(entry.mv(), Some(mv));
+    assert_eq!(entry.val(), -150);
+    assert_eq!(entry.depth(), 7);
+    assert_eq!(entry.bound(), Bound::Lower);
+
+    // A move-less store keeps the previous move
+    table.store(key, None, 20, 8, Bound::Upper);
+    let entry = table.probe(key).unwrap();
+    assert_eq!(entry.mv(), Some(mv));
+    assert_eq!(entry.bound(), Bound::Upper);
+
+    assert!(table.probe(key ^ (1 << 63)).is_none());
+}
@@ -0,0 +1,447 @@
+//! Texel-style tuning of the classical evaluation weights.
+//!
+//! Positions labeled with the results of their games are first resolved to
+//! quiet positions by a quiescence search. The weights are then fit so that
+//! the evaluation predicts the results, minimizing the mean squared error
+//! between each result and `sigmoid(K * eval)`, where `K` is fit to the
+//! starting weights.
+//!
+//! The classical evaluation is linear in its weights, apart from rounding,
+//! so each quiet position is reduced to the coefficient of each weight and
+//! the error is minimized by gradient descent.
+//!
+//! # Examples
+//!
+//! ```
+//! use hexe::eval::Params;
+//! use hexe::tune::Tuner;
+//!
+//! let data = "\
+//!     4k3/8/8/8/8/8/PPP5/4K3 w - - c9 \"1-0\";
+//!     4k3/ppp5/8/8/8/8/8/4K3 w - - [0.0]
+//!     4k3/ppp5/8/8/8/8/PPP5/4K3 b - - 0 1 [0.5]
+//!     4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1 | 95 | 1.0
+//! ";
+//!
+//! let mut tuner = Tuner::new(&Params::DEFAULT);
+//! assert_eq!(tuner.load(data.as_bytes()).unwrap(), 4);
+//!
+//! tuner.fit_k();
+//! let before = tuner.error();
+//! for _ in 0..10 {
+//!     tuner.step(1.0);
+//! }
+//! assert!(tuner.error() < before);
+//! let params = tuner.params();
+//! ```
+
+use std::cmp;
+use std::io::{self, BufRead};
+
+use core::fen::Fen;
+use core::mv::MoveVec;
+use eval::{self, endgame, Params};
+use prelude::*;
+
+/// The score of being mated at the root.
+const MATE: i32 = 32000;
+
+/// The deepest quiescence search used to resolve a position.
+const MAX_PLY: usize = 32;
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+/// A quiet position reduced to the coefficients of the weights it uses.
+struct Sample {
+    /// The index of each weight and its coefficient in the evaluation from
+    /// White's perspective.
+    terms: Vec<(u16, f32)>,
+    /// The result of the game for White.
+    result: f32,
+}
+
+impl Sample {
+    #[inline]
+    fn eval(&self, params: &[f64]) -> f64 {
+        self.terms.iter().map(|&(i, c)| params[i as usize] * c as f64).sum()
+    }
+}
+
+/// Fits evaluation weights to positions labeled with game results.
+pub struct Tuner {
+    start: Params,
+    params: Vec<f64>,
+    samples: Vec<Sample>,
+    k: f64,
+    momentum: Vec<f64>,
+    velocity: Vec<f64>,
+    steps: i32,
+}
+
+impl Tuner {
+    /// Creates a tuner starting from `params`.
+    pub fn new(params: &Params) -> Tuner {
+        Tuner {
+            start: params.clone(),
+            params: params.to_vec().into_iter().map(f64::from).collect(),
+            samples: Vec::new(),
+            k: 1.0,
+            momentum: vec![0.0; Params::LEN],
+            velocity: vec![0.0; Params::LEN],
+            steps: 0,
+        }
+    }
+
+    /// Returns the number of positions added.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns whether no positions have been added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the scaling constant of the sigmoid.
+    #[inline]
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    /// Returns the current weights, rounded.
+    pub fn params(&self) -> Params {
+        let values: Vec<i32> = self.params.iter().map(|&p| p.round() as i32).collect();
+        Params::from_slice(&values).expect("mismatched length")
+    }
+
+    /// Adds `pos` with `result`, the score of the game for White: 1 for a
+    /// win, 0.5 for a draw or 0 for a loss.
+    ///
+    /// Returns `false` if the quiet position reached is not evaluated by the
+    /// weights, such as when it is mate or a specialized endgame.
+    pub fn add(&mut self, pos: &Position, result: f64) -> bool {
+        let mut pos = pos.clone();
+        pos.set_network(None);
+
+        let mut pv = Vec::new();
+        let score = quiesce(&mut pos, &self.start, -MATE, MATE, 0, &mut pv);
+        if score.abs() >= MATE - MAX_PLY as i32 {
+            return false;
+        }
+        for &mv in &pv {
+            pos.make_move(mv);
+        }
+        if endgame::evaluate(&pos).is_some() {
+            return false;
+        }
+
+        self.samples.push(Sample {
+            terms: terms(&pos, &self.start),
+            result: result as f32,
+        });
+        true
+    }
+
+    /// Adds each labeled position in `reader`, one per line, returning the
+    /// number of positions added.
+    ///
+    /// Lines hold a FEN or EPD position followed by the result of its game,
+    /// either as an EPD `c9` opcode (`c9 "1-0";`) or in brackets (`[1.0]`,
+    /// `[0.5]`, `[1/2-1/2]`). The plain-text format of
+    /// [`datagen`](../datagen/index.html) is also read, ignoring its scores.
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn load<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        let mut added = 0;
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pos, result) = parse_entry(line).ok_or_else(|| {
+                let msg = format!("invalid entry on line {}: {}", n + 1, line);
+                io::Error::new(io::ErrorKind::InvalidData, msg)
+            })?;
+            if self.add(&pos, result) {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Returns the mean squared error of the current weights.
+    pub fn error(&self) -> f64 {
+        error(&self.samples, &self.params, self.k)
+    }
+
+    /// Fits the scaling constant of the sigmoid to the starting weights,
+    /// returning it.
+    pub fn fit_k(&mut self) -> f64 {
+        let params: Vec<f64> = self.start.to_vec().into_iter().map(f64::from).collect();
+        let samples = &self.samples;
+        let error = |k| error(samples, &params, k);
+
+        // Golden-section search
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut lo, mut hi) = (0.0, 10.0);
+        while hi - lo > 1e-4 {
+            let a = hi - ratio * (hi - lo);
+            let b = lo + ratio * (hi - lo);
+            if error(a) < error(b) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+        self.k = (lo + hi) / 2.0;
+        self.k
+    }
+
+    /// Takes one gradient descent step over all positions with the learning
+    /// rate `rate`, in centipawns, returning the error of the new weights.
+    pub fn step(&mut self, rate: f64) -> f64 {
+        let mut gradient = vec![0.0; Params::LEN];
+        let c = self.k * 10f64.ln() / 400.0;
+        for sample in &self.samples {
+            let s = sigmoid(self.k, sample.eval(&self.params));
+            let g = (s - sample.result as f64) * s * (1.0 - s) * c;
+            for &(i, coef) in &sample.terms {
+                gradient[i as usize] += g * coef as f64;
+            }
+        }
+
+        // Adam
+        self.steps += 1;
+        let n = cmp::max(self.samples.len(), 1) as f64;
+        let bias1 = 1.0 - ADAM_BETA1.powi(self.steps);
+        let bias2 = 1.0 - ADAM_BETA2.powi(self.steps);
+        for i in 0..Params::LEN {
+            let g = 2.0 * gradient[i] / n;
+            self.momentum[i] = ADAM_BETA1 * self.momentum[i] + (1.0 - ADAM_BETA1) * g;
+            self.velocity[i] = ADAM_BETA2 * self.velocity[i] + (1.0 - ADAM_BETA2) * g * g;
+            let m = self.momentum[i] / bias1;
+            let v = self.velocity[i] / bias2;
+            self.params[i] -= rate * m / (v.sqrt() + ADAM_EPSILON);
+        }
+        self.error()
+    }
+}
+
+/// Parses a position labeled with the result of its game for White.
+///
+/// See [`Tuner::load`](struct.Tuner.html#method.load) for the formats.
+pub fn parse_entry(line: &str) -> Option<(Position, f64)> {
+    let mut fields = line.split_whitespace();
+    let mut fen: Vec<&str> = fields.by_ref().take(4).collect();
+    let rest: Vec<&str> = fields.collect();
+
+    // Keep the move counters of FENs
+    let counters = rest.iter().take(2).take_while(|f| f.parse::<u32>().is_ok()).count();
+    fen.extend_from_slice(&rest[..counters]);
+    let label = rest[counters..].join(" ");
+
+    let fen: Fen = fen.join(" ").parse().ok()?;
+    let pos = Position::from_fen(&fen)?;
+
+    let label = label.trim();
+    // `trim_end_matches` requires Rust 1.30
+    #[allow(deprecated)]
+    let result = if label.starts_with("c9") {
+        label["c9".len()..].trim().trim_right_matches(';').trim().trim_matches('"')
+    } else if label.starts_with('[') && label.ends_with(']') {
+        &label[1..label.len() - 1]
+    } else if label.starts_with('|') {
+        // The search score of generated data is ignored
+        label.rsplit('|').next().unwrap()
+    } else {
+        return None;
+    };
+    let result = match result.trim() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        n => match n.parse::<f64>() {
+            Ok(n) if n >= 0.0 && n <= 1.0 => n,
+            _ => return None,
+        },
+    };
+    Some((pos, result))
+}
+
+#[inline]
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+fn error(samples: &[Sample], params: &[f64], k: f64) -> f64 {
+    let sum: f64 = samples.iter().map(|sample| {
+        let diff = sample.result as f64 - sigmoid(k, sample.eval(params));
+        diff * diff
+    }).sum();
+    sum / cmp::max(samples.len(), 1) as f64
+}
+
+/// Returns the coefficients of the weights in the evaluation of `pos` from
+/// White's perspective.
+///
+/// The end game scale factor depends on which side is ahead, which is taken
+/// from `params`.
+fn terms(pos: &Position, params: &Params) -> Vec<(u16, f32)> {
+    let mut coefs = vec![0.0f64; Params::LEN];
+
+    let white = eval::classical_with(pos, params);
+    let white = if pos.player() == Color::White { white } else { -white };
+    let strong = if white > 0 { Color::White } else { Color::Black };
+    let scale = endgame::scale(pos, strong) as f64 / endgame::SCALE_NORMAL as f64;
+
+    let max = eval::MAX_PHASE as f64;
+    let phase = eval::phase(pos) as f64;
+    let mg = phase / max;
+    let eg = (max - phase) / max * scale;
+
+    for (square, &piece) in pos.pieces() {
+        let sign = if piece.color() == Color::White { 1.0 } else { -1.0 };
+        let role = piece.role() as usize;
+        let pst = role * 64 + eval::pst_index(piece, square);
+        coefs[Params::MG_VALUES + role] += sign * mg;
+        coefs[Params::EG_VALUES + role] += sign * eg;
+        coefs[Params::MG_PST + pst] += sign * mg;
+        coefs[Params::EG_PST + pst] += sign * eg;
+    }
+    coefs[Params::TEMPO] = if pos.player() == Color::White { 1.0 } else { -1.0 };
+
+    coefs.iter().enumerate()
+        .filter(|&(_, &c)| c != 0.0)
+        .map(|(i, &c)| (i as u16, c as f32))
+        .collect()
+}
+
+/// Searches captures and promotions from `pos`, storing the best line in
+/// `pv` and returning its score for the player to move.
+fn quiesce(pos: &mut Position,
+           params: &Params,
+           mut alpha: i32,
+           beta: i32,
+           ply: usize,
+           pv: &mut Vec<Move>) -> i32
+{
+    pv.clear();
+    let in_check = pos.in_check();
+    if ply >= MAX_PLY {
+        return eval::classical_with(pos, params);
+    }
+
+    let mut best = -MATE + ply as i32;
+    let mut moves = MoveVec::new();
+    if in_check {
+        pos.gen(&mut moves).legal();
+    } else {
+        best = eval::classical_with(pos, params);
+        if best >= beta {
+            return best;
+        }
+        alpha = cmp::max(alpha, best);
+        pos.gen(&mut moves).noisy();
+    }
+
+    let mut child = Vec::new();
+    for &mv in moves.iter() {
+        pos.make_move(mv);
+        let value = -quiesce(pos, params, -beta, -alpha, ply + 1, &mut child);
+        pos.unmake_move(mv);
+
+        if value > best {
+            best = value;
+            if value > alpha {
+                alpha = value;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child);
+                if value >= beta {
+                    break;
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let cases = [
+            ("4k3/8/8/8/8/8/PPP5/4K3 w - - c9 \"1-0\";", 1.0),
+            ("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 12 [0.5]", 0.5),
+            ("4k3/8/8/8/8/8/PPP5/4K3 b - - [0-1]", 0.0),
+            ("4k3/8/8/8/8/8/PPP5/4K3 b - - 3 40 c9 \"1/2-1/2\";", 0.5),
+            ("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1 | -42 | 0.0", 0.0),
+        ];
+        for &(line, result) in cases.iter() {
+            let (_, parsed) = parse_entry(line).expect(line);
+            assert_eq!(parsed, result, "{}", line);
+        }
+        let (pos, _) = parse_entry(cases[3].0).unwrap();
+        assert_eq!(pos.halfmoves(), 3);
+
+        for line in ["4k3/8/8/8/8/8/PPP5/4K3 w - -", "4k3/8 w - - [1.0]", "4k3/8/8/8/8/8/PPP5/4K3 w - - [2]"].iter() {
+            assert!(parse_entry(line).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn terms_match_eval() {
+        let fens = [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/5k2/2n5/8/3P4/8/5K2/8 b - - 0 1",
+            "8/4k1b1/8/4P3/8/3B4/4K3/8 w - - 0 1",
+        ];
+        let params = Params::DEFAULT;
+        let values: Vec<f64> = params.to_vec().into_iter().map(f64::from).collect();
+        for fen in fens.iter() {
+            let pos = Position::from_fen(&fen.parse().unwrap()).unwrap();
+            let sample = Sample { terms: terms(&pos, &params), result: 0.0 };
+            let white = eval::classical_with(&pos, &params);
+            let white = if pos.player() == Color::White { white } else { -white };
+            // Integer division rounds the evaluation
+            assert!((sample.eval(&values) - white as f64).abs() <= 2.0, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn resolves_captures() {
+        let mut tuner = Tuner::new(&Params::DEFAULT);
+        // White wins the queen, so the quiet position is without it
+        let (pos, result) = parse_entry("4k2r/8/8/3q4/4P3/8/8/R3K3 w - - [1.0]").unwrap();
+        assert!(tuner.add(&pos, result));
+        let queen = Params::MG_VALUES + Role::Queen as usize;
+        assert!(tuner.samples[0].terms.iter().all(|&(i, _)| i as usize != queen));
+
+        // Specialized endgames do not depend on the weights
+        let (pos, result) = parse_entry("8/8/8/3k4/8/8/2BN4/4K3 w - - [1.0]").unwrap();
+        assert!(!tuner.add(&pos, result));
+        assert_eq!(tuner.len(), 1);
+    }
+
+    #[test]
+    fn params_round_trip() {
+        let params = Params::DEFAULT;
+        let mut values = params.to_vec();
+        assert_eq!(values.len(), Params::LEN);
+        assert_eq!(Params::from_slice(&values), Some(params.clone()));
+
+        values[Params::TEMPO] = 25;
+        values[Params::EG_PST + 64 * Role::Knight as usize] = -1;
+        let tuned = Params::from_slice(&values).unwrap();
+        assert_eq!(tuned.tempo, 25);
+        assert_eq!(tuned.eg_pst[Role::Knight as usize][0], -1);
+        assert!(Params::from_slice(&values[1..]).is_none());
+        assert!(params.to_string().contains("pub const MG_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];"));
+    }
+}
@@ -3,21 +3,27 @@
 use std::{fmt, hash, mem, ptr};
 
 use core::castle::Rights;
+use core::color::Color;
 use core::misc::Extract;
-use core::piece::Role;
+use core::piece::Piece;
 use core::square::{File, Square};
 use zero::Zero;
 
 mod tables;
 
-const NUM_PIECES:  usize = 6;
+mod polyglot;
+pub use self::polyglot::POLYGLOT;
+
+const NUM_PIECES:  usize = 12;
 const NUM_SQUARES: usize = 64;
 const PIECE_TOTAL: usize = NUM_SQUARES * NUM_PIECES;
 const NUM_CASTLE:  usize = 0b1111 + 1;
 const NUM_COLORS:  usize = 1;
 const NUM_EP:      usize = 8;
+const NUM_CHECKS:  usize = 4;
+const CHECK_TOTAL: usize = NUM_CHECKS * 2;
 
-const NUM_KEYS:  usize = PIECE_TOTAL + NUM_CASTLE + NUM_EP + NUM_COLORS;
+const NUM_KEYS:  usize = PIECE_TOTAL + NUM_CASTLE + NUM_EP + NUM_COLORS + CHECK_TOTAL;
 const NUM_BYTES: usize = NUM_KEYS * 8;
 
 type Keys = [u64; NUM_KEYS];
@@ -45,6 +51,9 @@ pub struct Zobrist {
     pub en_passant: [u64; NUM_EP],
     /// Key for the playing color.
     pub color: u64,
+    /// Keys for each number of checks left for each color to give in
+    /// Three-check.
+    pub checks: [[u64; NUM_CHECKS]; 2],
 }
 
 unsafe impl Zero for Zobrist {}
@@ -61,15 +70,13 @@ impl Clone for Zobrist {
 impl fmt::Debug for Zobrist {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // `[u64; 64]` does not implement `fmt::Debug`
-        let pieces: [&[u64]; 6] = [
-            &self.pieces[0], &self.pieces[1], &self.pieces[2],
-            &self.pieces[3], &self.pieces[4], &self.pieces[5],
-        ];
+        let pieces: Vec<&[u64]> = self.pieces.iter().map(|p| &p[..]).collect();
         f.debug_struct("Zobrist")
             .field("pieces",     &pieces)
             .field("castle",     &self.castle)
             .field("en_passant", &self.en_passant)
             .field("color",      &self.color)
+            .field("checks",     &self.checks)
             .finish()
     }
 }
@@ -128,10 +135,10 @@ impl ::rand::Rand for Zobrist {
 }
 
 impl Zobrist {
-    /// Returns the key for the piece role at a square.
+    /// Returns the key for the piece at a square.
     #[inline]
-    pub fn piece(&self, role: Role, square: Square) -> u64 {
-        self.pieces[role as usize][square as usize]
+    pub fn piece(&self, piece: Piece, square: Square) -> u64 {
+        self.pieces[piece as usize][square as usize]
     }
 
     /// Returns the key for the castle rights.
@@ -146,6 +153,13 @@ impl Zobrist {
         self.en_passant[file as usize]
     }
 
+    /// Returns the key for `color` having `remaining` checks left to give in
+    /// Three-check.
+    #[inline]
+    pub fn checks(&self, color: Color, remaining: u8) -> u64 {
+        self.checks[color as usize][remaining as usize]
+    }
+
     /// Clear all hashes by setting them to zero.
     #[inline]
     pub fn clear(&mut self) {
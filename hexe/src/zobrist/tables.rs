@@ -8,12 +8,22 @@ pub(super) const STATIC: Zobrist = Zobrist {
         [4776625605055523107,9581526500942039027,12549858597747042291,3580053157411014421,12473869178173277235,2820247995877732294,14037851914594772922,5672504620414158791,17455952803359902460,10698984556958185048,13767544947250237911,5802055452039011315,5909095935758050750,15532757143716096828,8853021543416911457,8450263260011412420,242122216155552533,10195146328103025258,5125562128441558800,13479531500314032096,10129968332773495347,5483136227656735627,11505825802849008908,14064797899071398257,12459896763199795344,12237763761256973767,16825609149573600296,99084746931762886,18229452746148655347,9669253087504986975,16692353637029302009,10855800574672317591,3507286088774809663,2540303808707136838,17319542758208700090,15473900385367111426,826536278727022166,2047611712649355222,12524090039938318610,15683310579295512911,16306566292385733223,5181667361855972832,14475953985061304710,17511549774232765676,17849331984351984488,13937008403034755195,4057290878189067525,1598695930218349330,8400622922715296230,12497664578613804421,5970367907619031958,12957200673363370736,17556353760524415376,16001393469462864748,4090513389536722698,547884349014810480,8951377251762990136,14200936856491477281,6469737958233133295,9394452399095599001,13189403217980739821,15880210963574726489,9973999980718765270,5618422966103626613],
         [14363491623709641160,16678993185665650250,1114511292270032262,9473466901892539551,10164450538341174509,16867660539483747862,4303954857306345066,16939217204792121539,12109218517630809535,14464989255549532347,13676685985708639748,14021696590101584855,2484724173648403857,7707711455155655543,5535937651791603245,125334019837282903,2195588412925274656,17200489720761029719,16292306004792605903,5580385835424558877,15006718649139973913,10318373835116924715,17806433876031683261,7893055701858201106,12248736308326995808,17238588250944179319,6580667218911462116,7211657752957560150,5120117675382216123,14690520459687738547,14961415838050841184,15603344700215086790,12103985457252573470,2678646774443369734,15132158955090818155,15473074922486867136,5208852242188745446,7549590575882984628,1072928033667222911,17481433136821328639,13859648173968028926,13477916505530627964,13653295899144408306,17776783482122929138,8793961449798106428,3943388701972757372,5894085606089344151,7152930844488449226,6085170786667766298,1123683816579192835,5862327319236200989,14605553334028306104,12199401451566974686,2123224803790794751,8143559833498071077,14476827713631333362,12537081184712040323,8813696849974577276,15010217622287170547,17634032565006766568,18197240494024234268,11739636985721623926,10723604450159555209,16345554500773443561],
         [14883850500278067041,6807314213480736893,2323547651051996063,6205759349331751615,1908180712802899541,16548660313218991447,13603767090871149310,13168998429354222133,16023383452748202206,13060984013260571998,3841685214606753469,15761829821634677904,17953928449786022369,13962415601948629662,14016882962673404653,16903350493746071769,4664588990693314220,12781692056903185548,7813213227824366810,16482204852465412412,44391555301463675,13645593231100308259,15210007672889647746,13117699392832960286,9633295527149620973,4751410588157557230,12869324813535646138,13880414072478111994,5651841127900298932,9752965082773596098,16256788356470665837,14884651242740973613,14788001682392556607,5964660152758813736,16801116115124443431,4102794497007801018,9837958155121174188,1613721716370633987,7397893277634502299,9733100991314624642,9810169083439773843,7330415963858047864,12136434855898688448,1123016665440204096,17501763497922303123,11245474763779751305,2709034256286652370,5647517596369982653,6957885223479442829,6439397289861322343,15037544284235398093,14517789122529656114,5438885291792204053,16828199572418460734,11123517007040117597,12887289869697592269,1837388437286088498,12903317634709541889,15611659603307636028,3179854386986630648,9943030316723196818,1016865015725177578,9865356787373871233,6065122138134609862],
+        [7768296551139606405,829440183043631268,18306575347326547715,17794515331583747481,5967934204298283000,6702150684594163217,24553562853884138,12506541658795363767,8202583697837931731,1208103302251265206,14145684954853634555,3013633568337613771,14272580333546609343,1871283099550112824,11440133558328782856,12350449545957766762,10592688709732685132,4151199667073474791,4092969838581120633,10972730037543890675,16494021875440652942,6712222527897042413,11801621227900269619,2617060536733493949,15270047694123887892,4540483577624966632,2092118592640865208,5556222936407461347,10365360148242838012,18126840630210015082,12184944196136606278,7467885290474989877,410553806725521422,6993433913643031542,8332237003641199903,283710697499802261,6309099887131064275,6307666423688669897,16956268756635669464,5176145249038171779,16392601899194886457,14558107168725117936,2073909070516673792,14257420443586794825,11493942305480429645,1406575691226399437,3239549438386388445,7240091738203694987,7147160631875177420,7347732945259064154,12989557178671550863,4329550720401441125,10672049899256648535,1156445294505101147,10270036892677128497,17074512829998106859,8294270182974188655,15539039792284166193,17243190332215615130,14208722590072615208,7407919707895719638,2527040887854352173,9337752010700381749,5623300446566678348],
+        [8482167824964781150,14203644978960891375,11178172189138788141,6816679222757756740,16273717744852753647,3988284795407860121,5640052191726789377,14171542177143043396,5174511668510310350,18252664615855801727,2277541960444251114,14353246641056067126,8422241145310390294,6764542622591716179,18191809009530328762,2203570047044911584,12518243196010365147,4925645559488385473,10913850761351505252,9508116399211894413,10049691476213086481,12468670425913304334,7278318514088020206,16208441634830202613,8412684643665918457,6151285337502627937,17884109280382780751,11387554777207704167,5983853932860519216,6143067759295455821,5882071223448313402,16669160295218440867,7913058827788506530,4984754892364390085,15661510172897779464,13164656461852630897,14457642823665266753,12562963195751056589,3727331429122097412,4188791859597275926,5945263980156359064,16041205519935205197,10478564001290605822,15819414814855651533,8659526274550487324,9850215817314799765,10137026870563030880,10677624980533542602,11191424795519481482,15354723729132166930,17009942469286723188,10425785319190236617,16034783268822716702,14328101154870721820,6451275778638352045,10464734177148082330,386321343635505983,9398766295068304671,1372169238007971692,11820072372796284025,6568058797777574795,14420306793309438870,6901416119243530763,2143133635377715224],
+        [6400533375269759659,12593810545414858853,9648293056122287719,8141877266618231229,5538149378473784975,7535596708326064740,2183461072405208616,6346289903183603046,7585198388969265349,11923773134348417109,11727170408242392167,17537947833795177425,17792652760715759638,11301568698588320160,15164947167863701358,14401078543433726279,12005115869025814519,13540847562311640859,2217275306249655872,5272983536170754587,14784342198828765450,16038191645226235622,5270693165631479988,10289334340135270091,12530940368144135027,16661265940170339257,13862280937086870373,9800703256580953035,6061985712653186903,7914268971597052194,7811347626415229722,8503656955640198670,12329451939730318700,11470075084083186855,17164820450006754359,9880943087149472982,2908607421030621944,5805536852116975677,11339226773303293959,1289612803342242559,7013117059646903918,6017339514951327140,13399089102230266414,4845486526283714036,6892082930860697578,3676161604934786455,12148804895009309280,2184640124338588164,5536202701838988213,14501860220635455018,14135251271683843009,2867297603655908199,11770345142054369499,6230290421374584575,11248048380246435987,8915378407060801032,14834059130482438149,3287810658316385548,14467730044650689967,13148198692516471172,16219615347981596197,4997572167083915102,13975149806192553983,16689224930638445304],
+        [18153918504697514410,1194186491569033101,1544096338454903039,11358999238179630911,10209835685668546481,17485745414157522579,11131927754365926416,8159735893677305248,1640011147065360488,12405411713772638005,15211373204847033736,15280557732175995503,59481554966466537,4186300276787379866,16702967258426759012,10053201752990489528,8011889983001974538,9161595958855706823,5391422069149586129,16533010329433396345,12474240790170451037,10834856777110327037,11628831289298665883,11407375083130330784,3607881043007932441,9415000176461120054,10100846848816417418,4844229817734183044,4578295225879867281,5354329401043928991,12823512340118699291,5285799301604033594,13298425803768081344,10938416373859178004,11718391019790355651,16289200723041024441,17657534423530450776,5661287386020857816,867306801981983814,5704454733275384346,6006851917934114374,10805322254353788278,351814158923012063,14067082341029578218,4612887363409723950,10635782584804515468,10467963810288170394,11675241273709058318,2444650000383833677,5754478284160350061,11726918370407590692,6906312865713877105,15757680407794232807,14433159377529353464,1471338898809505312,16784852043551384232,13624001693402644436,9591162177722607024,16699775931358983397,2216298806263947702,7378499748285884831,15033984256590899114,8033347951215098821,11241714473790705940],
+        [8693710918771649124,5365337104953455600,2701961080812003027,503230100306563939,9076610742712150708,14785830978076726941,1740722216414687381,4990090419479100149,7556130524524351299,18013105383746810338,15323337216529977384,10675739358458447524,8990339286936166554,13261942048227070692,11430309311514225077,14012726432676676852,16482648950094056259,6899226671955119706,15221474840522930241,17330031653068720453,9596248750781920509,4563584674889883247,7341710959772474177,996979224941135473,15366851573822104350,15929758243547616518,16677405362097802342,4742357110761665229,11598649062817433870,1967305180696632604,15288922499363317368,5468466295474670910,7557539013160077911,6480958105355969894,104758883779427037,13005258631611277895,17527126153999117367,8685309914391156018,11454386995317109679,16972979173157419234,1082296724561740304,3549043406849459148,5029152226771104229,1605990418884996943,7599603564529772466,6702656576767760899,10344332888515962099,1235744842417936878,16347815074448916154,3062990274176040723,2186957691146259781,7350178676866087442,3040649250308569559,15944571635523869842,204754239655624177,2340204851395057333,7589935660205885924,4093669104960733075,7199957693403684116,3960014297310248366,10781097228349105753,8653347170671924161,3830324203208355067,14285850399360571657],
+        [6804449429800071759,10702562140799835253,11381462927152892175,2199258076112426356,9850849583942240467,13580159279300172513,19382898079687822,1982545379044530801,4374811566111726851,9751626273870878279,14102731155006250672,14887625839467248812,8458667441930737398,8846075003760601947,5637626350121726807,563394413587421514,5976100280576127902,12153370122361777470,6135952108998068230,4314163896307714705,15599856839419304438,3408569601039561814,12226721455147147689,11070769511090629395,16925593559504344412,8511220863861010683,14373302026771453253,5389061993596300252,6255328246345436847,8799796897422581227,4350948631257529177,6592635134533115980,12424513865650729455,4759370880300036755,3679322519845573669,12127722204716612867,2166488471465746592,10561680831435245050,5257509920988130164,11145418385014808584,3359227266419970733,9283430533886762599,16355383713779907378,10900231164322198568,879650423619955023,7350788080922968656,203828037325220741,40220891227494342,9158384157904434431,725811272350830167,15737209886603139782,15455055403073047497,5037157402426956078,2518524530535007135,7873974954196836428,9685033097503761962,17578933851166503473,3315709734180828995,3143173530338773229,9641008333185993923,3692075967296660677,5128849548245676015,14519994904153302991,13684323175786193533],
     ],
     castle: [
-        7768296551139606405,829440183043631268,18306575347326547715,17794515331583747481,5967934204298283000,6702150684594163217,24553562853884138,12506541658795363767,8202583697837931731,1208103302251265206,14145684954853634555,3013633568337613771,14272580333546609343,1871283099550112824,11440133558328782856,12350449545957766762
+        12676025151518345289,8227596925627109350,5881747704614343675,10077980345841112703,10020935698857799705,16374537949419282525,4590969462120890310,15165014346423113600,3962535640098798705,9573969039256069996,14655364298893242325,11573071981614453284,9488323422276338336,16788080686122096227,16665785258404087609,685980681320161980
     ],
     en_passant: [
-        10592688709732685132,4151199667073474791,4092969838581120633,10972730037543890675,16494021875440652942,6712222527897042413,11801621227900269619,2617060536733493949
+        6944472819808556572,12606729782490624850,9360837121987368037,15547403515495947036,17936859782276487324,2252385297648867248,15994510448776491536,1827046325037985813
+    ],
+    color: 6082557898672045153,
+    checks: [
+        [6820071701382278934,6556290012254814096,14929833545311940420,14149706358772897995],
+        [2614354116420910890,16589987534033906823,14021484823725885300,16389652094050836774],
     ],
-    color: 15270047694123887892
 };
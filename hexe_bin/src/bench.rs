@@ -0,0 +1,59 @@
+//! The `bench` subcommand.
+
+use std::io;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hexe::engine::{bench, Engine, Output, MAX_TABLE_SIZE, MAX_THREADS};
+
+fn validate(max: usize) -> impl Fn(String) -> Result<(), String> {
+    move |val| match val.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= max => Ok(()),
+        Ok(_) => Err(format!("must be between 1 and {}", max)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("bench")
+        .about("Searches a fixed set of positions, reporting the nodes searched \
+                and the speed of the search")
+        .arg(Arg::with_name("depth")
+            .long("depth")
+            .short("d")
+            .value_name("N")
+            .takes_value(true)
+            .validator(validate(u32::max_value() as usize))
+            .help("The depth to which each position is searched; the \
+                   engine's bench depth if not provided"))
+        .arg(Arg::with_name("hash")
+            .long("hash")
+            .short("H")
+            .value_name("SIZE")
+            .default_value("16")
+            .validator(validate(MAX_TABLE_SIZE))
+            .help("The hash table size in megabytes"))
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .value_name("N")
+            .takes_value(true)
+            .validator(validate(MAX_THREADS))
+            .help("The number of threads; the global --threads if not \
+                   provided, or else 1"))
+}
+
+/// Runs the benchmark, with `threads` being the global number of threads, if
+/// any.
+pub fn run(matches: &ArgMatches,
+           threads: Option<usize>,
+           stats: Option<Output>) -> io::Result<()>
+{
+    let value = |name: &str| matches.value_of(name).map(|val| val.parse::<usize>().unwrap());
+    let depth = value("depth").map_or(bench::DEFAULT_DEPTH, |depth| depth as u32);
+    let mut engine = Engine::builder()
+        .num_threads(value("threads").or(threads).unwrap_or(1))
+        .hash_size(value("hash").unwrap())
+        .build();
+    engine.set_stats_output(stats);
+    println!("{}", engine.bench(depth));
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! The `datagen` subcommand.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hexe::datagen::{Config, Sample};
+use hexe::engine::EngineBuilder;
+
+/// The number of games between progress reports.
+const REPORT_INTERVAL: u64 = 100;
+
+fn validate<T: FromStr>(val: String) -> Result<(), String> where T::Err: ToString {
+    val.parse::<T>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("datagen")
+        .about("Generates training data from self-play games")
+        .arg(Arg::with_name("games")
+            .long("games")
+            .short("n")
+            .value_name("N")
+            .takes_value(true)
+            .required_unless("export")
+            .validator(validate::<u64>)
+            .help("The number of games to play"))
+        .arg(Arg::with_name("depth")
+            .long("depth")
+            .short("d")
+            .value_name("N")
+            .takes_value(true)
+            .validator(validate::<u32>)
+            .help("The depth to which each move is searched"))
+        .arg(Arg::with_name("nodes")
+            .long("nodes")
+            .value_name("N")
+            .takes_value(true)
+            .validator(validate::<u32>)
+            .help("The number of nodes searched for each move; 5000 if \
+                   neither this nor --depth is provided"))
+        .arg(Arg::with_name("random plies")
+            .long("random-plies")
+            .value_name("N")
+            .default_value("8")
+            .validator(validate::<u32>)
+            .help("The number of random legal moves that start each game"))
+        .arg(Arg::with_name("max plies")
+            .long("max-plies")
+            .value_name("N")
+            .default_value("400")
+            .validator(validate::<u32>)
+            .help("The number of plies after which a game is drawn"))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("N")
+            .takes_value(true)
+            .validator(validate::<u64>)
+            .help("The seed of the random openings; chosen from the clock \
+                   if not provided"))
+        .arg(Arg::with_name("table size")
+            .long("table-size")
+            .value_name("SIZE")
+            .default_value("16")
+            .validator(validate::<usize>)
+            .help("The transposition table size of each thread in megabytes"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("FILE")
+            .takes_value(true)
+            .required_unless_one(&["text", "export"])
+            .help("Where to write the samples in the binary format"))
+        .arg(Arg::with_name("text")
+            .long("text")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Where to write the samples in the plain-text format"))
+        .arg(Arg::with_name("export")
+            .long("export")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with_all(&["games", "output"])
+            .help("Converts a binary FILE to the plain-text format, written \
+                   to --text or the standard output, instead of playing games"))
+}
+
+pub fn run(engine: &EngineBuilder, matches: &ArgMatches) -> io::Result<()> {
+    if let Some(path) = matches.value_of("export") {
+        return export(path, matches.value_of("text"));
+    }
+
+    let value = |name: &str| matches.value_of(name).map(|val| val.parse().unwrap());
+    let depth = value("depth").unwrap_or(0);
+    let seed = matches.value_of("seed").map(|val| val.parse().unwrap()).unwrap_or_else(|| {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        time.as_secs() ^ time.subsec_nanos() as u64
+    });
+    let config = Config {
+        games: matches.value_of("games").unwrap().parse().unwrap(),
+        depth,
+        nodes: value("nodes").unwrap_or(if depth == 0 { 5000 } else { 0 }),
+        random_plies: value("random plies").unwrap(),
+        max_plies: value("max plies").unwrap(),
+        seed,
+        hash_size: matches.value_of("table size").unwrap().parse().unwrap(),
+    };
+    if config.depth == 0 && config.nodes == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "moves need a depth or node limit"));
+    }
+
+    let mut binary = match matches.value_of("output") {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+    let mut text = match matches.value_of("text") {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let engine = engine.build();
+    eprintln!("Playing {} games on {} threads with seed {}",
+              config.games, engine.num_threads(), config.seed);
+
+    let start = Instant::now();
+    let (mut games, mut positions) = (0u64, 0u64);
+    let total = config.games;
+    for game in engine.self_play(config) {
+        for sample in game.iter() {
+            if let Some(binary) = binary.as_mut() {
+                binary.write_all(&sample.pack())?;
+            }
+            if let Some(text) = text.as_mut() {
+                writeln!(text, "{}", sample)?;
+            }
+        }
+        games += 1;
+        positions += game.len() as u64;
+        if games % REPORT_INTERVAL == 0 || games == total {
+            let elapsed = start.elapsed();
+            let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+            eprintln!("Games: {}/{}, positions: {}, {:.0} positions/s",
+                      games, total, positions, positions as f64 / secs.max(1e-3));
+        }
+    }
+
+    if let Some(mut binary) = binary {
+        binary.flush()?;
+    }
+    if let Some(mut text) = text {
+        text.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes the samples of the binary file at `path` in the plain-text format.
+fn export(path: &str, output: Option<&str>) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    while let Some(sample) = Sample::read_from(&mut reader)? {
+        writeln!(writer, "{}", sample)?;
+    }
+    writer.flush()
+}
@@ -5,13 +5,24 @@ extern crate clap;
 #[macro_use]
 extern crate hexe;
 
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::iter;
+use std::process;
 use std::str::FromStr;
 
 use clap::{Arg, App, AppSettings};
-use hexe::engine::Engine;
+use hexe::engine::{Engine, Output};
+
+mod bench;
+mod datagen;
+#[cfg(feature = "svg")]
+mod svg;
+mod tune;
+mod versus;
 
 const ABOUT: &str = "
-A UCI-compatible chess engine.
+A UCI and XBoard (CECP) compatible chess engine.
 
 Project homepage: https://github.com/hexe-rs/Hexe
 Library docs:     https://docs.rs/hexe";
@@ -59,7 +70,23 @@ fn main() {
             .empty_values(false)
             .help("The number of OS threads used to run the engine; \
                    if not provided or N is 0, all available logical \
-                   cores are used"));
+                   cores are used"))
+        .arg(Arg::with_name("stats json")
+            .long("stats-json")
+            .global(true)
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Where to write the statistics of each search as a line \
+                   of JSON"))
+        .subcommand(bench::subcommand())
+        .subcommand(datagen::subcommand())
+        .subcommand(tune::subcommand())
+        .subcommand(versus::subcommand());
+
+    #[cfg(feature = "svg")]
+    {
+        app = app.subcommand(svg::subcommand());
+    }
 
     // Conditionally include logging flag if feature is enabled
     if cfg!(feature = "log") {
@@ -82,8 +109,6 @@ fn main() {
                 .help("When to color logging output"))
     }
 
-    // Matches unused when "log" is disabled
-    #[allow(unused_variables)]
     let matches = app.get_matches();
 
     let mut engine = Engine::builder();
@@ -115,5 +140,52 @@ fn main() {
         builder.default_format_module_path(false).init();
     }
 
-    engine.build().uci().start();
+    let stats = match matches.value_of("stats json").map(File::create) {
+        Some(Ok(file)) => Some(Output::from_writer(file)),
+        Some(Err(err)) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        },
+        None => None,
+    };
+
+    let result = match matches.subcommand() {
+        ("bench", Some(matches)) => {
+            // Set by `get_matches`
+            let threads = unsafe { NUM_THREADS };
+            Some(bench::run(matches, threads, stats.clone()))
+        },
+        ("datagen", Some(matches)) => Some(datagen::run(&engine, matches)),
+        ("tune", Some(matches)) => Some(tune::run(matches)),
+        ("match", Some(matches)) => Some(versus::run(matches)),
+        #[cfg(feature = "svg")]
+        ("svg", Some(matches)) => Some(svg::run(matches)),
+        _ => None,
+    };
+    if let Some(result) = result {
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut engine = engine.build();
+    engine.set_stats_output(stats);
+
+    // The protocol is chosen by the first command received
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines().filter_map(Result::ok);
+    let first = match lines.next() {
+        Some(line) => line,
+        None => return,
+    };
+    let xboard = first.trim() == "xboard";
+    let commands = iter::once(first).chain(lines);
+
+    if xboard {
+        engine.xboard().start_with(commands);
+    } else {
+        engine.uci().start_with(commands);
+    }
 }
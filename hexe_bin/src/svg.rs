@@ -0,0 +1,118 @@
+//! The `svg` subcommand.
+
+use std::fs::File;
+use std::io::{self, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hexe::fen::Fen;
+use hexe::prelude::*;
+use hexe::svg::{Arrow, Svg};
+
+const ARROW_COLOR: &str = "#15781b";
+
+/// Parses a pair of squares such as `e2e4`.
+fn parse_squares(val: &str) -> Option<(Square, Square)> {
+    if val.len() != 4 || !val.is_char_boundary(2) {
+        return None;
+    }
+    Some((val[..2].parse().ok()?, val[2..].parse().ok()?))
+}
+
+/// Parses an arrow such as `e2e4` or `e2e4:red`.
+fn parse_arrow(val: &str) -> Option<Arrow> {
+    let mut split = val.splitn(2, ':');
+    let (from, to) = parse_squares(split.next()?)?;
+    let color = split.next().unwrap_or(ARROW_COLOR);
+    if color.is_empty() || color.contains('"') {
+        return None;
+    }
+    Some(Arrow::new(from, to, color))
+}
+
+fn validate<T, F>(parse: F, expected: &'static str) -> impl Fn(String) -> Result<(), String>
+    where F: Fn(&str) -> Option<T>
+{
+    move |val| match parse(&val) {
+        Some(_) => Ok(()),
+        None => Err(format!("expected {}", expected)),
+    }
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("svg")
+        .about("Renders a position as an SVG image")
+        .arg(Arg::with_name("FEN")
+            .validator(|val| val.parse::<Fen>().map(|_| ()).map_err(|_| "invalid FEN".into()))
+            .help("The position to render; the starting position if not provided"))
+        .arg(Arg::with_name("flip")
+            .long("flip")
+            .help("Renders the board from Black's side"))
+        .arg(Arg::with_name("coordinates")
+            .long("coordinates")
+            .short("c")
+            .help("Labels the ranks and files"))
+        .arg(Arg::with_name("size")
+            .long("size")
+            .value_name("PX")
+            .default_value("45")
+            .validator(|val| match val.parse::<u32>() {
+                Ok(n) if n >= 1 => Ok(()),
+                Ok(_) => Err("must be at least 1".into()),
+                Err(err) => Err(err.to_string()),
+            })
+            .help("The width of each square in pixels"))
+        .arg(Arg::with_name("last move")
+            .long("last-move")
+            .value_name("MOVE")
+            .validator(validate(parse_squares, "two squares such as e2e4"))
+            .help("The squares of the last move to mark, such as e2e4"))
+        .arg(Arg::with_name("highlight")
+            .long("highlight")
+            .value_name("SQUARE")
+            .multiple(true)
+            .number_of_values(1)
+            .validator(validate(|val| val.parse::<Square>().ok(), "a square such as e4"))
+            .help("A square to highlight"))
+        .arg(Arg::with_name("arrow")
+            .long("arrow")
+            .value_name("ARROW")
+            .multiple(true)
+            .number_of_values(1)
+            .validator(validate(parse_arrow, "two squares and an optional color \
+                                              such as e2e4 or e2e4:red"))
+            .help("An arrow to draw between two squares, with an optional \
+                   color such as e2e4:red"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Where to write the image; standard output if not provided"))
+}
+
+pub fn run(matches: &ArgMatches) -> io::Result<()> {
+    let fen = match matches.value_of("FEN") {
+        Some(fen) => fen.parse().unwrap(),
+        None => Fen::STANDARD,
+    };
+    let svg = Svg {
+        square_size: matches.value_of("size").unwrap().parse().unwrap(),
+        side: if matches.is_present("flip") { Color::Black } else { Color::White },
+        coordinates: matches.is_present("coordinates"),
+        highlight: matches.values_of("highlight").into_iter().flat_map(|vals| vals)
+            .map(|val| val.parse::<Square>().unwrap())
+            .collect(),
+        last_move: matches.value_of("last move").and_then(parse_squares),
+        arrows: matches.values_of("arrow").into_iter().flat_map(|vals| vals)
+            .filter_map(parse_arrow)
+            .collect(),
+        ..Svg::default()
+    };
+
+    let mut image = svg.render(&fen.pieces);
+    image.push('\n');
+    match matches.value_of("output") {
+        Some(output) => File::create(output)?.write_all(image.as_bytes()),
+        None => io::stdout().write_all(image.as_bytes()),
+    }
+}
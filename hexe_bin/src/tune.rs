@@ -0,0 +1,64 @@
+//! The `tune` subcommand.
+
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hexe::eval::Params;
+use hexe::tune::Tuner;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("tune")
+        .about("Tunes the evaluation weights to positions labeled with game results")
+        .arg(Arg::with_name("FILE")
+            .required(true)
+            .help("An EPD or FEN file with the result of each position, \
+                   as a c9 opcode or in brackets"))
+        .arg(Arg::with_name("iterations")
+            .long("iterations")
+            .short("n")
+            .value_name("N")
+            .default_value("1000")
+            .validator(|val| val.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+            .help("The number of gradient descent steps"))
+        .arg(Arg::with_name("rate")
+            .long("rate")
+            .value_name("RATE")
+            .default_value("1.0")
+            .validator(|val| val.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+            .help("The learning rate in centipawns"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Where to write the tuned weights; standard output if not provided"))
+}
+
+pub fn run(matches: &ArgMatches) -> io::Result<()> {
+    let path = matches.value_of("FILE").unwrap();
+    let iterations: usize = matches.value_of("iterations").unwrap().parse().unwrap();
+    let rate: f64 = matches.value_of("rate").unwrap().parse().unwrap();
+
+    let mut tuner = Tuner::new(&Params::DEFAULT);
+    let added = tuner.load(BufReader::new(File::open(path)?))?;
+    eprintln!("Loaded {} positions from \"{}\"", added, path);
+    if tuner.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no positions to tune with"));
+    }
+
+    let k = tuner.fit_k();
+    eprintln!("K = {:.4}, error = {:.6}", k, tuner.error());
+    for i in 1..=iterations {
+        let error = tuner.step(rate);
+        if i % 50 == 0 || i == iterations {
+            eprintln!("Iteration {}: error = {:.6}", i, error);
+        }
+    }
+
+    let params = tuner.params().to_string();
+    match matches.value_of("output") {
+        Some(output) => File::create(output)?.write_all(params.as_bytes()),
+        None => io::stdout().write_all(params.as_bytes()),
+    }
+}
@@ -0,0 +1,189 @@
+//! UCI engines running as child processes.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many seconds an engine has to respond outside of searches.
+const TIMEOUT_SECS: u64 = 10;
+
+/// A score reported by an engine from its own perspective.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// A score in centipawns.
+    Cp(i32),
+    /// Mate in the given number of moves, negative if being mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// Returns the score in centipawns, treating mates as beyond any
+    /// threshold.
+    pub fn cp(self) -> i32 {
+        match self {
+            Score::Cp(cp) => cp,
+            Score::Mate(n) if n > 0 => 100_000,
+            Score::Mate(_) => -100_000,
+        }
+    }
+}
+
+/// The outcome of a search.
+pub struct Search {
+    /// The best move in long algebraic notation.
+    pub best: String,
+    /// The last score reported.
+    pub score: Option<Score>,
+    /// The last depth reported.
+    pub depth: u32,
+    /// The time taken to reply with the best move.
+    pub elapsed: Duration,
+}
+
+/// An engine speaking UCI over its standard input and output.
+pub struct Engine {
+    /// The name the engine reports for itself.
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..50 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Engine {
+    /// Starts `command`, split on whitespace into the program and its
+    /// arguments, and sets `options` once it has identified itself.
+    pub fn spawn(command: &str, options: &[(String, String)]) -> io::Result<Engine> {
+        let mut args = command.split_whitespace();
+        let program = args.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "empty engine command")
+        })?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", program, err)))?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if tx.send(line).is_err() { break },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut engine = Engine { name: command.to_owned(), child, stdin, lines };
+        engine.send("uci")?;
+        let deadline = Instant::now() + Duration::from_secs(TIMEOUT_SECS);
+        loop {
+            let line = engine.recv(deadline)?;
+            let line = line.trim();
+            if line.starts_with("id name ") {
+                engine.name = line["id name ".len()..].trim().to_owned();
+            } else if line == "uciok" {
+                break;
+            }
+        }
+        for (name, value) in options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.sync()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn recv(&self, deadline: Instant) -> io::Result<String> {
+        let now = Instant::now();
+        let timeout = if deadline > now { deadline - now } else { Duration::from_secs(0) };
+        self.lines.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => {
+                io::Error::new(io::ErrorKind::TimedOut, format!("{} stopped responding", self.name))
+            },
+            RecvTimeoutError::Disconnected => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} exited", self.name))
+            },
+        })
+    }
+
+    /// Waits for the engine to process all commands sent so far.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + Duration::from_secs(TIMEOUT_SECS);
+        while self.recv(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Tells the engine that the next search is from a different game.
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    /// Sends the `position` and `go` commands and waits up to `timeout` for
+    /// the best move.
+    pub fn search(&mut self, position: &str, go: &str, timeout: Duration) -> io::Result<Search> {
+        self.send(position)?;
+        self.send(go)?;
+        let start = Instant::now();
+        let deadline = start + timeout;
+
+        let (mut score, mut depth) = (None, 0);
+        loop {
+            let line = self.recv(deadline)?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => while let Some(token) = tokens.next() {
+                    match token {
+                        "depth" => {
+                            depth = tokens.next().and_then(|d| d.parse().ok()).unwrap_or(depth);
+                        },
+                        "score" => {
+                            let kind = tokens.next();
+                            let value = tokens.next().and_then(|v| v.parse().ok());
+                            score = match (kind, value) {
+                                (Some("cp"), Some(cp)) => Some(Score::Cp(cp)),
+                                (Some("mate"), Some(n)) => Some(Score::Mate(n)),
+                                _ => score,
+                            };
+                        },
+                        // The rest of the line is free-form
+                        "string" | "pv" => break,
+                        _ => {},
+                    }
+                },
+                Some("bestmove") => return Ok(Search {
+                    best: tokens.next().unwrap_or("").to_owned(),
+                    score,
+                    depth,
+                    elapsed: start.elapsed(),
+                }),
+                _ => {},
+            }
+        }
+    }
+}
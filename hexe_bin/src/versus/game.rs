@@ -0,0 +1,396 @@
+//! Playing a single game between two engines.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hexe::mv::{Move, MoveVec};
+use hexe::position::{Draw, Outcome, Position};
+use hexe::syzygy::{Tablebase, Wdl};
+use hexe::color::Color;
+
+use super::{millis, secs};
+use super::engine::{Engine, Score, Search};
+use super::openings::Opening;
+
+/// How many seconds past its deadline an engine is waited on before it is
+/// assumed to be stuck.
+const GRACE_SECS: u64 = 5;
+
+/// A time control of `[MOVES/]SECONDS[+INCREMENT]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeControl {
+    /// The number of moves until the base time is added again.
+    pub moves: Option<u32>,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl fmt::Display for TimeControl {
+    /// Formats `self` as the PGN `TimeControl` tag.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{}/", moves)?;
+        }
+        write!(f, "{}", secs(self.base))?;
+        if self.increment > Duration::from_secs(0) {
+            write!(f, "+{}", secs(self.increment))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeControl, String> {
+        let err = || format!("invalid time control: \"{}\"", s);
+        let seconds = |s: &str| match s.parse::<f64>() {
+            Ok(secs) if secs >= 0.0 && secs < u64::max_value() as f64 => {
+                Ok(Duration::new(secs as u64, (secs.fract() * 1e9).round() as u32))
+            },
+            _ => Err(err()),
+        };
+
+        let (moves, rest) = match s.find('/') {
+            Some(i) => (Some(s[..i].parse().map_err(|_| err())?), &s[i + 1..]),
+            None => (None, s),
+        };
+        let (base, increment) = match rest.find('+') {
+            Some(i) => (seconds(&rest[..i])?, seconds(&rest[i + 1..])?),
+            None => (seconds(rest)?, Duration::from_secs(0)),
+        };
+        if base == Duration::from_secs(0) || moves == Some(0) {
+            return Err(err());
+        }
+        Ok(TimeControl { moves, base, increment })
+    }
+}
+
+/// Ending games early by agreement of the engines' scores.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Adjudication {
+    /// An engine loses once its score is at or below minus this many
+    /// centipawns for the given number of its moves in a row.
+    pub resign: Option<(i32, u32)>,
+    /// The game is drawn once both scores are within this many centipawns
+    /// of zero for the given number of moves in a row, after the given move
+    /// number.
+    pub draw: Option<(i32, u32, u32)>,
+}
+
+/// How games are played.
+pub struct Settings {
+    /// The time control of each engine.
+    pub time_controls: [TimeControl; 2],
+    /// How far past its clock an engine may go before losing on time.
+    pub margin: Duration,
+    pub adjudication: Adjudication,
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+/// The result of a game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Color),
+    Draw,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            GameResult::Win(Color::White) => "1-0",
+            GameResult::Win(Color::Black) => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        })
+    }
+}
+
+/// Why a game ended, as in the PGN `Termination` tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Normal,
+    Abandoned,
+    Adjudication,
+    TimeForfeit,
+    RulesInfraction,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Termination::Normal => "normal",
+            Termination::Abandoned => "abandoned",
+            Termination::Adjudication => "adjudication",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::RulesInfraction => "rules infraction",
+        })
+    }
+}
+
+/// A move played by an engine.
+pub struct Played {
+    pub san: String,
+    pub search: Search,
+}
+
+/// A finished game.
+pub struct Game {
+    /// The index of the engine playing each color.
+    pub players: [usize; 2],
+    pub opening: Opening,
+    pub moves: Vec<Played>,
+    pub result: GameResult,
+    pub termination: Termination,
+    /// A description of how the game ended.
+    pub reason: String,
+    /// Whether an engine stopped responding and needs to be restarted.
+    pub restart: bool,
+}
+
+impl Game {
+    /// Returns the result from the perspective of the first engine: 1 for a
+    /// win, 0 for a draw and -1 for a loss.
+    pub fn first_score(&self) -> i32 {
+        match self.result {
+            GameResult::Win(color) if self.players[color as usize] == 0 => 1,
+            GameResult::Win(_) => -1,
+            GameResult::Draw => 0,
+        }
+    }
+}
+
+/// Plays a game from `opening` between `engines`, with `players` giving
+/// the index of the engine playing White then Black.
+///
+/// Errors are returned only if the game could not be started; engines
+/// failing during the game lose it.
+pub fn play(engines: &mut [Engine; 2],
+            players: [usize; 2],
+            opening: &Opening,
+            settings: &Settings) -> io::Result<Game> {
+    let mut game = Game {
+        players,
+        opening: opening.clone(),
+        moves: Vec::new(),
+        result: GameResult::Draw,
+        termination: Termination::Normal,
+        reason: String::new(),
+        restart: false,
+    };
+
+    let mut pos = opening.position();
+    let mut command = match opening.fen {
+        Some(ref fen) => format!("position fen {} moves", fen),
+        None => "position startpos moves".to_owned(),
+    };
+    for &mv in opening.moves.iter() {
+        command.push(' ');
+        command.push_str(&mv.to_string());
+    }
+
+    for engine in engines.iter_mut() {
+        engine.new_game()?;
+    }
+
+    let mut clocks = [
+        settings.time_controls[players[0]].base,
+        settings.time_controls[players[1]].base,
+    ];
+    let mut moves_made = [0u32; 2];
+    let mut resign_streak = [0u32; 2];
+    let mut draw_streak = 0u32;
+
+    let plies = opening.moves.len() as u32;
+    let mut fullmoves = match opening.fen {
+        Some(ref fen) if fen.color == Color::Black => fen.fullmoves + (plies + 1) / 2,
+        Some(ref fen) => fen.fullmoves + plies / 2,
+        None => 1 + plies / 2,
+    };
+
+    loop {
+        if let Some((result, termination, reason)) = finished(&mut pos, settings) {
+            game.result = result;
+            game.termination = termination;
+            game.reason = reason;
+            return Ok(game);
+        }
+
+        let color = pos.player() as usize;
+        let engine = &mut engines[players[color]];
+        let tc = settings.time_controls[players[color]];
+
+        let mut go = format!("go wtime {} btime {} winc {} binc {}",
+                             millis(clocks[0]), millis(clocks[1]),
+                             millis(settings.time_controls[players[0]].increment),
+                             millis(settings.time_controls[players[1]].increment));
+        if let Some(moves) = tc.moves {
+            go.push_str(&format!(" movestogo {}", moves - moves_made[color] % moves));
+        }
+
+        let timeout = clocks[color] + settings.margin + Duration::from_secs(GRACE_SECS);
+        let search = match engine.search(&command, &go, timeout) {
+            Ok(search) => search,
+            Err(err) => {
+                game.result = GameResult::Win(!pos.player());
+                game.termination = match err.kind() {
+                    io::ErrorKind::TimedOut => Termination::TimeForfeit,
+                    _ => Termination::Abandoned,
+                };
+                game.reason = err.to_string();
+                game.restart = true;
+                return Ok(game);
+            },
+        };
+
+        if search.elapsed > clocks[color] + settings.margin {
+            game.result = GameResult::Win(!pos.player());
+            game.termination = Termination::TimeForfeit;
+            game.reason = format!("{} loses on time", color_name(pos.player()));
+            return Ok(game);
+        }
+        clocks[color] = clocks[color] - search.elapsed.min(clocks[color]) + tc.increment;
+        moves_made[color] += 1;
+        if let Some(moves) = tc.moves {
+            if moves_made[color] % moves == 0 {
+                clocks[color] += tc.base;
+            }
+        }
+
+        let mv = match parse_move(&pos, &search.best) {
+            Some(mv) => mv,
+            None => {
+                game.result = GameResult::Win(!pos.player());
+                game.termination = Termination::RulesInfraction;
+                game.reason = format!("{} makes an illegal move: {}",
+                                      color_name(pos.player()), search.best);
+                return Ok(game);
+            },
+        };
+
+        // Adjudicate on the score of the engine that just moved
+        let score = search.score.map(Score::cp);
+        let mover = pos.player();
+        game.moves.push(Played { san: pos.san(mv), search });
+        command.push(' ');
+        command.push_str(&mv.to_string());
+        pos.make_move(mv);
+        if mover == Color::Black {
+            fullmoves += 1;
+        }
+
+        if let (Some((threshold, count)), Some(score)) = (settings.adjudication.resign, score) {
+            if score <= -threshold {
+                resign_streak[color] += 1;
+            } else {
+                resign_streak[color] = 0;
+            }
+            if resign_streak[color] >= count {
+                game.result = GameResult::Win(!mover);
+                game.termination = Termination::Adjudication;
+                game.reason = format!("{} resigns", color_name(mover));
+                return Ok(game);
+            }
+        }
+        if let Some((threshold, count, after)) = settings.adjudication.draw {
+            match score {
+                Some(score) if fullmoves > after && score.abs() <= threshold => draw_streak += 1,
+                _ => draw_streak = 0,
+            }
+            if draw_streak >= 2 * count {
+                game.result = GameResult::Draw;
+                game.termination = Termination::Adjudication;
+                game.reason = "Draw by adjudication".to_owned();
+                return Ok(game);
+            }
+        }
+    }
+}
+
+/// Returns how the game ends at `pos`, if it does.
+fn finished(pos: &mut Position, settings: &Settings) -> Option<(GameResult, Termination, String)> {
+    if let Some(outcome) = pos.outcome() {
+        return Some(match outcome {
+            Outcome::Checkmate { winner } => {
+                (GameResult::Win(winner), Termination::Normal, format!("{} mates", color_name(winner)))
+            },
+            Outcome::ThreeChecks { winner } => {
+                (GameResult::Win(winner), Termination::Normal,
+                 format!("{} gives three checks", color_name(winner)))
+            },
+            Outcome::KingOfTheHill { winner } => {
+                (GameResult::Win(winner), Termination::Normal,
+                 format!("{} king reaches the center", color_name(winner)))
+            },
+            Outcome::Stalemate => {
+                (GameResult::Draw, Termination::Normal, "Draw by stalemate".to_owned())
+            },
+            Outcome::Draw(draw) => (GameResult::Draw, Termination::Normal, match draw {
+                Draw::Repetition => "Draw by 3-fold repetition",
+                Draw::FiftyMoves => "Draw by fifty moves rule",
+                Draw::InsufficientMaterial => "Draw by insufficient mating material",
+            }.to_owned()),
+        });
+    }
+
+    let tablebase = settings.tablebase.as_ref()?;
+    let wdl = tablebase.probe_wdl(pos)?;
+
+    // The tables assume a zeroed fifty-move counter, so a win only stands if
+    // the counter can be zeroed again before the fifty-move rule applies
+    let halfmoves = pos.halfmoves();
+    if (wdl == Wdl::Win || wdl == Wdl::Loss) && halfmoves != 0 {
+        let dtz = tablebase.probe_dtz(pos)?;
+        if halfmoves + dtz.abs() as u32 > 100 {
+            return None;
+        }
+    }
+
+    let result = match wdl {
+        Wdl::Win => GameResult::Win(pos.player()),
+        Wdl::Loss => GameResult::Win(!pos.player()),
+        _ => GameResult::Draw,
+    };
+    let reason = match result {
+        GameResult::Win(color) => format!("{} wins by tablebase adjudication", color_name(color)),
+        GameResult::Draw => "Draw by tablebase adjudication".to_owned(),
+    };
+    Some((result, Termination::Adjudication, reason))
+}
+
+fn parse_move(pos: &Position, s: &str) -> Option<Move> {
+    let mut moves = MoveVec::new();
+    pos.gen(&mut moves).legal();
+    moves.iter().cloned().find(|mv| mv.to_string() == s)
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_control() {
+        let tc: TimeControl = "40/60+0.5".parse().unwrap();
+        assert_eq!(tc.moves, Some(40));
+        assert_eq!(tc.base, Duration::from_secs(60));
+        assert_eq!(tc.increment, Duration::from_millis(500));
+        assert_eq!(tc.to_string(), "40/60+0.5");
+
+        let tc: TimeControl = "8+0.08".parse().unwrap();
+        assert_eq!(tc.to_string(), "8+0.08");
+        assert_eq!("10".parse::<TimeControl>().unwrap().increment, Duration::from_secs(0));
+
+        for &bad in ["", "0", "0/10", "x+1", "10+", "-1"].iter() {
+            assert!(bad.parse::<TimeControl>().is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! The `match` subcommand.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hexe::syzygy::Tablebase;
+
+mod engine;
+mod game;
+mod openings;
+mod pgn;
+mod stats;
+
+use self::engine::Engine;
+use self::game::{Adjudication, Game, Settings, TimeControl};
+use self::openings::Opening;
+use self::stats::{Score, Sprt};
+
+/// The number of games between reports of the Elo difference.
+const RATING_INTERVAL: u32 = 10;
+
+/// Returns `duration` in whole milliseconds.
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_millis() as u64
+}
+
+/// Returns `duration` in fractional seconds.
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+fn validate<T: FromStr>(val: String) -> Result<(), String> where T::Err: ToString {
+    val.parse::<T>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("match")
+        .about("Plays games between two UCI engines and reports their Elo difference")
+        .arg(Arg::with_name("ENGINE1")
+            .required(true)
+            .help("The command that runs the first engine, such as a new build"))
+        .arg(Arg::with_name("ENGINE2")
+            .required(true)
+            .help("The command that runs the second engine, such as a base build"))
+        .arg(Arg::with_name("openings")
+            .long("openings")
+            .short("b")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("An EPD or PGN file of openings, each played twice with colors \
+                   swapped; the standard position if not provided"))
+        .arg(Arg::with_name("tc")
+            .long("tc")
+            .value_name("TC")
+            .min_values(1)
+            .max_values(2)
+            .default_value("10+0.1")
+            .validator(validate::<TimeControl>)
+            .help("The time control as [MOVES/]SECONDS[+INCREMENT]; a second \
+                   value applies to the second engine"))
+        .arg(Arg::with_name("games")
+            .long("games")
+            .short("n")
+            .value_name("N")
+            .takes_value(true)
+            .required_unless("sprt")
+            .validator(validate::<usize>)
+            .help("The number of games to play; with --sprt, the most to play"))
+        .arg(Arg::with_name("concurrency")
+            .long("concurrency")
+            .short("j")
+            .value_name("N")
+            .default_value("1")
+            .validator(|val| match val.parse::<usize>() {
+                Ok(0) => Err("must be at least 1".to_owned()),
+                Ok(_) => Ok(()),
+                Err(err) => Err(err.to_string()),
+            })
+            .help("The number of games to play at once"))
+        .arg(Arg::with_name("option")
+            .long("option")
+            .short("o")
+            .value_name("NAME=VALUE")
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|val| if val.contains('=') {
+                Ok(())
+            } else {
+                Err("expected NAME=VALUE".to_owned())
+            })
+            .help("A UCI option set for both engines"))
+        .arg(Arg::with_name("pgn")
+            .long("pgn")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Where to write the games played"))
+        .arg(Arg::with_name("syzygy")
+            .long("syzygy")
+            .value_name("PATH")
+            .takes_value(true)
+            .help("Syzygy tablebase directories for adjudicating games"))
+        .arg(Arg::with_name("resign")
+            .long("resign")
+            .value_names(&["SCORE", "MOVES"])
+            .validator(validate::<u32>)
+            .help("Adjudicate a loss once an engine scores at or below -SCORE \
+                   centipawns for MOVES moves in a row"))
+        .arg(Arg::with_name("draw")
+            .long("draw")
+            .value_names(&["SCORE", "MOVES", "AFTER"])
+            .validator(validate::<u32>)
+            .help("Adjudicate a draw once both engines score within SCORE \
+                   centipawns for MOVES moves in a row, after move AFTER"))
+        .arg(Arg::with_name("sprt")
+            .long("sprt")
+            .value_names(&["ELO0", "ELO1"])
+            .allow_hyphen_values(true)
+            .validator(validate::<f64>)
+            .help("Stop once a sequential probability ratio test accepts \
+                   either Elo difference"))
+        .arg(Arg::with_name("alpha")
+            .long("alpha")
+            .value_name("ALPHA")
+            .default_value("0.05")
+            .validator(validate::<f64>)
+            .help("The SPRT false positive rate"))
+        .arg(Arg::with_name("beta")
+            .long("beta")
+            .value_name("BETA")
+            .default_value("0.05")
+            .validator(validate::<f64>)
+            .help("The SPRT false negative rate"))
+        .arg(Arg::with_name("margin")
+            .long("margin")
+            .value_name("MS")
+            .default_value("100")
+            .validator(validate::<u64>)
+            .help("How many milliseconds past its clock an engine may take"))
+}
+
+/// The state shared by the threads playing games.
+struct Match {
+    names: [String; 2],
+    commands: [String; 2],
+    options: Vec<(String, String)>,
+    settings: Settings,
+    openings: Vec<Opening>,
+    games: usize,
+    sprt: Option<Sprt>,
+    state: Mutex<State>,
+}
+
+struct State {
+    next: usize,
+    score: Score,
+    pgn: Option<BufWriter<File>>,
+    /// Whether to stop starting games.
+    done: bool,
+    error: Option<io::Error>,
+}
+
+impl Match {
+    /// Returns the index of the next game to play, if any.
+    fn next_game(&self) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.done || state.next >= self.games {
+            return None;
+        }
+        state.next += 1;
+        Some(state.next - 1)
+    }
+
+    fn stop(&self, error: io::Error) {
+        let mut state = self.state.lock().unwrap();
+        state.done = true;
+        state.error.get_or_insert(error);
+    }
+
+    fn finish(&self, index: usize, game: &Game) {
+        let mut state = self.state.lock().unwrap();
+        match game.first_score() {
+            1 => state.score.wins += 1,
+            -1 => state.score.losses += 1,
+            _ => state.score.draws += 1,
+        }
+        if let Some(pgn) = state.pgn.as_mut() {
+            let written = pgn::write(pgn, game, index + 1, &self.names, &self.settings.time_controls)
+                .and_then(|_| pgn.flush());
+            if let Err(err) = written {
+                eprintln!("error: failed to write PGN: {}", err);
+            }
+        }
+
+        println!("Finished game {} ({} vs {}): {} {{{}}}",
+                 index + 1,
+                 self.names[game.players[0]],
+                 self.names[game.players[1]],
+                 game.result,
+                 game.reason);
+        println!("Score of {} vs {}: {}", self.names[0], self.names[1], state.score);
+
+        let sprt = self.sprt.and_then(|sprt| sprt.result(&state.score));
+        if sprt.is_some() {
+            state.done = true;
+        }
+        if state.score.games() % RATING_INTERVAL == 0 || sprt.is_some() {
+            self.report(&state.score);
+        }
+    }
+
+    fn report(&self, score: &Score) {
+        match score.elo() {
+            Some((elo, error)) => println!("Elo difference: {:.1} +/- {:.1}", elo, error),
+            None => println!("Elo difference: unknown"),
+        }
+        if let Some(sprt) = self.sprt {
+            let (lower, upper) = sprt.bounds();
+            let llr = sprt.llr(score);
+            let status = match sprt.result(score) {
+                Some(true) => " - H1 was accepted",
+                Some(false) => " - H0 was accepted",
+                None => "",
+            };
+            println!("SPRT: llr {:.2} ({:.1}%), lbound {:.2}, ubound {:.2}{}",
+                     llr, 100.0 * llr / upper, lower, upper, status);
+        }
+    }
+
+    /// Plays games with `engines` until there are none left.
+    fn play(&self, mut engines: [Engine; 2]) {
+        let (commands, options, settings) = (&self.commands, &self.options, &self.settings);
+        while let Some(index) = self.next_game() {
+            let opening = &self.openings[(index / 2) % self.openings.len()];
+            let players = if index % 2 == 0 { [0, 1] } else { [1, 0] };
+
+            // Restart engines that fail to start a game once before giving up
+            let game = match game::play(&mut engines, players, opening, settings) {
+                Ok(game) => Ok(game),
+                Err(_) => spawn(commands, options).and_then(|new| {
+                    engines = new;
+                    game::play(&mut engines, players, opening, settings)
+                }),
+            };
+            let game = match game {
+                Ok(game) => game,
+                Err(err) => return self.stop(err),
+            };
+            self.finish(index, &game);
+
+            if game.restart {
+                match spawn(commands, options) {
+                    Ok(new) => engines = new,
+                    Err(err) => return self.stop(err),
+                }
+            }
+        }
+    }
+}
+
+fn spawn(commands: &[String; 2], options: &[(String, String)]) -> io::Result<[Engine; 2]> {
+    Ok([Engine::spawn(&commands[0], options)?, Engine::spawn(&commands[1], options)?])
+}
+
+pub fn run(matches: &ArgMatches) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let commands = [
+        matches.value_of("ENGINE1").unwrap().to_owned(),
+        matches.value_of("ENGINE2").unwrap().to_owned(),
+    ];
+
+    let tcs: Vec<TimeControl> = matches.values_of("tc").unwrap().map(|tc| tc.parse().unwrap()).collect();
+    let time_controls = [tcs[0], *tcs.last().unwrap()];
+
+    let numbers = |name: &str| -> Option<Vec<f64>> {
+        matches.values_of(name).map(|vals| vals.map(|v| v.parse().unwrap()).collect())
+    };
+    let adjudication = Adjudication {
+        resign: numbers("resign").map(|v| (v[0] as i32, v[1] as u32)),
+        draw: numbers("draw").map(|v| (v[0] as i32, v[1] as u32, v[2] as u32)),
+    };
+    let sprt = numbers("sprt").map(|v| Sprt {
+        elo0: v[0],
+        elo1: v[1],
+        alpha: matches.value_of("alpha").unwrap().parse().unwrap(),
+        beta: matches.value_of("beta").unwrap().parse().unwrap(),
+    });
+    if let Some(sprt) = sprt {
+        if sprt.elo0 >= sprt.elo1 {
+            return Err(invalid("ELO0 must be less than ELO1".to_owned()));
+        }
+        if !(sprt.alpha > 0.0 && sprt.alpha < 1.0 && sprt.beta > 0.0 && sprt.beta < 1.0) {
+            return Err(invalid("SPRT error rates must be between 0 and 1".to_owned()));
+        }
+    }
+
+    let tablebase = match matches.value_of("syzygy") {
+        Some(paths) => Some(Arc::new(Tablebase::open(paths)?)),
+        None => None,
+    };
+    let settings = Settings {
+        time_controls,
+        margin: Duration::from_millis(matches.value_of("margin").unwrap().parse().unwrap()),
+        adjudication,
+        tablebase,
+    };
+
+    let openings = match matches.value_of("openings") {
+        Some(path) => openings::load(Path::new(path))?,
+        None => vec![Opening::default()],
+    };
+    let options: Vec<(String, String)> = matches.values_of("option").into_iter().flat_map(|opts| opts).map(|opt| {
+        let mut parts = opt.splitn(2, '=');
+        (parts.next().unwrap().trim().to_owned(), parts.next().unwrap().trim().to_owned())
+    }).collect();
+
+    let pgn = match matches.value_of("pgn") {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let concurrency: usize = matches.value_of("concurrency").unwrap().parse().unwrap();
+    let mut workers = Vec::with_capacity(concurrency);
+    for _ in 0..concurrency {
+        workers.push(spawn(&commands, &options)?);
+    }
+
+    // Two builds of the same engine report the same name
+    let mut names = [workers[0][0].name.clone(), workers[0][1].name.clone()];
+    if names[0] == names[1] {
+        names = commands.clone();
+    }
+
+    let games = match matches.value_of("games") {
+        Some(n) => n.parse().unwrap(),
+        None => usize::max_value(),
+    };
+    let state = State { next: 0, score: Score::default(), pgn, done: false, error: None };
+    let shared = Arc::new(Match {
+        names,
+        commands,
+        options,
+        settings,
+        openings,
+        games,
+        sprt,
+        state: Mutex::new(state),
+    });
+
+    let threads: Vec<_> = workers.into_iter().map(|engines| {
+        let shared = shared.clone();
+        thread::spawn(move || shared.play(engines))
+    }).collect();
+    for thread in threads {
+        if thread.join().is_err() {
+            shared.stop(io::Error::new(io::ErrorKind::Other, "a match thread panicked"));
+        }
+    }
+
+    let mut state = shared.state.lock().unwrap();
+    if let Some(err) = state.error.take() {
+        return Err(err);
+    }
+    if state.score.games() % RATING_INTERVAL != 0 && !state.done {
+        shared.report(&state.score);
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Opening positions read from EPD or PGN files.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use hexe::fen::Fen;
+use hexe::prelude::*;
+
+/// A position to start games from.
+#[derive(Clone, Default)]
+pub struct Opening {
+    /// The starting position, or `None` for the standard one.
+    pub fen: Option<Fen>,
+    /// The moves played from the starting position.
+    pub moves: Vec<Move>,
+}
+
+impl Opening {
+    /// Returns the position before the opening moves.
+    pub fn start(&self) -> Position {
+        match self.fen {
+            Some(ref fen) => Position::from_fen(fen).unwrap(),
+            None => Position::default(),
+        }
+    }
+
+    /// Returns the position after the opening moves.
+    pub fn position(&self) -> Position {
+        let mut pos = self.start();
+        for &mv in self.moves.iter() {
+            pos.make_move(mv);
+        }
+        pos
+    }
+}
+
+/// Reads the openings in the file at `path`, as PGN games if its extension
+/// is `pgn` and as EPD lines otherwise.
+pub fn load(path: &Path) -> io::Result<Vec<Opening>> {
+    let text = fs::read_to_string(path)?;
+    let is_pgn = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("pgn"));
+    let openings = if is_pgn { parse_pgn(&text)? } else { parse_epd(&text)? };
+    if openings.is_empty() {
+        return Err(invalid(format!("no openings in \"{}\"", path.display())));
+    }
+    Ok(openings)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_fen(fen: &str) -> Option<Fen> {
+    let fen: Fen = fen.parse().ok()?;
+    Position::from_fen(&fen)?;
+    Some(fen)
+}
+
+/// Parses one position per line, ignoring EPD operations.
+fn parse_epd(text: &str) -> io::Result<Vec<Opening>> {
+    let mut openings = Vec::new();
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let mut fen: Vec<&str> = fields.by_ref().take(4).collect();
+        fen.extend(fields.take(2).take_while(|f| f.parse::<u32>().is_ok()));
+
+        let fen = parse_fen(&fen.join(" ")).ok_or_else(|| {
+            invalid(format!("invalid position on line {}", num + 1))
+        })?;
+        openings.push(Opening { fen: Some(fen), moves: Vec::new() });
+    }
+    Ok(openings)
+}
+
+/// Parses the main line of each game.
+fn parse_pgn(text: &str) -> io::Result<Vec<Opening>> {
+    let mut openings = Vec::new();
+    let mut opening = Opening::default();
+    let mut pos = Position::default();
+    let mut in_moves = false;
+
+    // Comments and variations are stripped as the text is split into tokens
+    let mut tokens = Vec::new();
+    let (mut depth, mut comment, mut token) = (0, false, String::new());
+    for line in text.lines() {
+        let line = line.trim();
+        if !comment && depth == 0 && line.starts_with('[') {
+            tokens.push(line.to_owned());
+            continue;
+        }
+        if line.starts_with('%') {
+            continue;
+        }
+        for ch in line.chars().chain(Some('\n')) {
+            match ch {
+                _ if comment => comment = ch != '}',
+                '{' => comment = true,
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                // Skip the rest of the line
+                ';' if depth == 0 => break,
+                _ if depth > 0 => {},
+                _ if ch.is_whitespace() || ch == '.' => if !token.is_empty() {
+                    tokens.push(token.clone());
+                    token.clear();
+                },
+                _ => token.push(ch),
+            }
+        }
+        if !token.is_empty() {
+            tokens.push(token.clone());
+            token.clear();
+        }
+    }
+
+    for token in tokens {
+        if token.starts_with('[') {
+            if in_moves {
+                openings.push(opening);
+                opening = Opening::default();
+                pos = Position::default();
+                in_moves = false;
+            }
+            let mut parts = token.trim_matches(|c| c == '[' || c == ']').splitn(2, ' ');
+            if parts.next() == Some("FEN") {
+                let value = parts.next().unwrap_or("").trim().trim_matches('"');
+                let fen = parse_fen(value).ok_or_else(|| {
+                    invalid(format!("invalid FEN tag: \"{}\"", value))
+                })?;
+                pos = Position::from_fen(&fen).unwrap();
+                opening.fen = Some(fen);
+            }
+            continue;
+        }
+
+        in_moves = true;
+        match token.as_str() {
+            "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                openings.push(opening);
+                opening = Opening::default();
+                pos = Position::default();
+                in_moves = false;
+            },
+            _ if token.starts_with('$') || token.bytes().all(|b| b.is_ascii_digit()) => {},
+            san => {
+                let mv = pos.parse_san(san).ok_or_else(|| {
+                    invalid(format!("illegal move in opening: \"{}\"", san))
+                })?;
+                pos.make_move(mv);
+                opening.moves.push(mv);
+            },
+        }
+    }
+    if in_moves {
+        openings.push(opening);
+    }
+    Ok(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgn() {
+        let pgn = r#"
+[Event "?"]
+[White "?"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4) Nc6 $1 3. Bb5 1/2-1/2
+
+[Event "?"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+[SetUp "1"]
+
+1.e3 Kd7 *
+"#;
+        let openings = parse_pgn(pgn).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].moves.len(), 5);
+        assert!(openings[0].fen.is_none());
+        assert_eq!(openings[1].moves.len(), 2);
+        assert!(openings[1].fen.is_some());
+
+        let pos = openings[0].position();
+        assert_eq!(pos.player(), Color::Black);
+        assert!(pos.pieces().get(Square::B5).is_some());
+
+        assert!(parse_pgn("1. e4 e4").is_err());
+    }
+
+    #[test]
+    fn epd() {
+        let epd = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"e4\";\n\
+                   \n\
+                   4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\n";
+        let openings = parse_epd(epd).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].position().player(), Color::Black);
+        assert!(parse_epd("8/8/8/8/8/8/8/8 w - -").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Writing games as PGN.
+
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hexe::color::Color;
+
+use super::secs;
+use super::engine::Score;
+use super::game::{Game, TimeControl};
+
+/// The column at which movetext is wrapped.
+const WIDTH: usize = 80;
+
+/// Writes `game`, played between engines named `names`, as round `round`.
+pub fn write<W: Write>(writer: &mut W,
+                       game: &Game,
+                       round: usize,
+                       names: &[String; 2],
+                       time_controls: &[TimeControl; 2]) -> io::Result<()> {
+    let tag = |writer: &mut W, name: &str, value: &str| {
+        writeln!(writer, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+    };
+    tag(writer, "Event", "hexe match")?;
+    tag(writer, "Site", "?")?;
+    tag(writer, "Date", &date())?;
+    tag(writer, "Round", &round.to_string())?;
+    tag(writer, "White", &names[game.players[0]])?;
+    tag(writer, "Black", &names[game.players[1]])?;
+    tag(writer, "Result", &game.result.to_string())?;
+    if let Some(ref fen) = game.opening.fen {
+        tag(writer, "FEN", &fen.to_string())?;
+        tag(writer, "SetUp", "1")?;
+    }
+    let plies = game.opening.moves.len() + game.moves.len();
+    tag(writer, "PlyCount", &plies.to_string())?;
+    let (white, black) = (time_controls[game.players[0]], time_controls[game.players[1]]);
+    if white == black {
+        tag(writer, "TimeControl", &white.to_string())?;
+    } else {
+        tag(writer, "WhiteTimeControl", &white.to_string())?;
+        tag(writer, "BlackTimeControl", &black.to_string())?;
+    }
+    tag(writer, "Termination", &game.termination.to_string())?;
+    writeln!(writer)?;
+
+    let mut pos = game.opening.start();
+    let (mut fullmoves, mut player) = match game.opening.fen {
+        Some(ref fen) => (fen.fullmoves, fen.color),
+        None => (1, Color::White),
+    };
+
+    let mut tokens = Vec::with_capacity(2 * plies + 2);
+    let opening = game.opening.moves.iter().map(|&mv| {
+        let san = pos.san(mv);
+        pos.make_move(mv);
+        (san, None)
+    }).collect::<Vec<_>>();
+    let played = game.moves.iter().map(|played| (played.san.clone(), Some(&played.search)));
+    for (i, (san, search)) in opening.into_iter().chain(played).enumerate() {
+        match player {
+            Color::White => tokens.push(format!("{}.", fullmoves)),
+            Color::Black if i == 0 => tokens.push(format!("{}...", fullmoves)),
+            Color::Black => {},
+        }
+        tokens.push(san);
+        if let Some(search) = search {
+            let secs = secs(search.elapsed);
+            let score = match search.score {
+                Some(Score::Cp(cp)) => format!("{:+.2}/{} ", cp as f64 / 100.0, search.depth),
+                Some(Score::Mate(n)) if n > 0 => format!("+M{}/{} ", n, search.depth),
+                Some(Score::Mate(n)) => format!("-M{}/{} ", -n, search.depth),
+                None => String::new(),
+            };
+            tokens.push(format!("{{{}{:.3}s}}", score, secs));
+        }
+        if player == Color::Black {
+            fullmoves += 1;
+        }
+        player = !player;
+    }
+    if !game.reason.is_empty() {
+        tokens.push(format!("{{{}}}", game.reason));
+    }
+    tokens.push(game.result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > WIDTH {
+            writeln!(writer, "{}", line)?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    writeln!(writer, "{}", line)?;
+    writeln!(writer)
+}
+
+/// Returns the current date as `YYYY.MM.DD`.
+fn date() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Converts days since 1970-01-01 to a Gregorian calendar date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_744), (2026, 10, 18));
+        // Before March 1st of year 0, where days are floored into the previous era
+        assert_eq!(civil_from_days(-719_469), (0, 2, 29));
+    }
+}
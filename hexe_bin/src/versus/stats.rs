@@ -0,0 +1,146 @@
+//! Elo estimates and the sequential probability ratio test.
+
+use std::fmt;
+
+/// The z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.959_964;
+
+/// Game results from the perspective of the first engine.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {} - {}  [{:.3}] {}",
+               self.wins, self.losses, self.draws, self.ratio(), self.games())
+    }
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Returns the mean score per game.
+    pub fn ratio(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            n => (self.wins as f64 + self.draws as f64 / 2.0) / n as f64,
+        }
+    }
+
+    /// Returns the variance of the score of a single game.
+    fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        let mean = self.ratio();
+        (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2)) / n
+    }
+
+    /// Returns the Elo difference and the half-width of its 95% confidence
+    /// interval, or `None` while either is unbounded.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let n = self.games() as f64;
+        let mean = self.ratio();
+        let margin = Z_95 * (self.variance() / n).sqrt();
+        let (lo, hi) = (mean - margin, mean + margin);
+        if n == 0.0 || lo <= 0.0 || hi >= 1.0 {
+            return None;
+        }
+        Some((elo(mean), (elo(hi) - elo(lo)) / 2.0))
+    }
+}
+
+/// Returns the Elo difference for an expected score of `score`.
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Returns the expected score for an Elo difference of `elo`.
+fn expected(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test of whether the first engine is `elo1`
+/// rather than `elo0` stronger than the second.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Returns the log-likelihood ratio bounds at which `elo0` and `elo1`
+    /// are accepted respectively.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Returns the log-likelihood ratio of `elo1` against `elo0` given
+    /// `score`, using a normal approximation of the game results.
+    pub fn llr(&self, score: &Score) -> f64 {
+        let variance = score.variance();
+        if score.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected(self.elo0), expected(self.elo1));
+        let n = score.games() as f64;
+        n * (s1 - s0) * (2.0 * score.ratio() - s0 - s1) / (2.0 * variance)
+    }
+
+    /// Returns `Some(true)` if `elo1` is accepted, `Some(false)` if `elo0`
+    /// is accepted, or `None` if more games are needed.
+    pub fn result(&self, score: &Score) -> Option<bool> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(true)
+        } else if llr <= lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(wins: u32, losses: u32, draws: u32) -> Score {
+        Score { wins, losses, draws }
+    }
+
+    #[test]
+    fn elo() {
+        assert_eq!(score(0, 0, 0).elo(), None);
+        assert_eq!(score(10, 0, 0).elo(), None);
+
+        let (elo, error) = score(50, 50, 100).elo().unwrap();
+        assert!(elo.abs() < 1e-9);
+        assert!((error - 34.16).abs() < 0.01, "{}", error);
+
+        let (elo, _) = score(60, 10, 30).elo().unwrap();
+        assert!((elo - 190.85).abs() < 0.01, "{}", elo);
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 };
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+
+        assert_eq!(sprt.llr(&Score::default()), 0.0);
+        assert_eq!(sprt.result(&score(100, 100, 200)), None);
+        assert!(sprt.llr(&score(100, 100, 200)) < 0.0);
+        assert_eq!(sprt.result(&score(3000, 2000, 5000)), Some(true));
+        assert_eq!(sprt.result(&score(2000, 3000, 5000)), Some(false));
+    }
+}
@@ -291,8 +291,11 @@ impl BitBoard {
         self.0.contains_zero_byte()
     }
 
-    /// Returns whether the path for `right` is empty within `self`.
+    /// Returns whether the path for `right` in standard chess is empty within
+    /// `self`.
     #[inline]
+    #[deprecated(note = "only holds for standard chess; use `Files::path` instead")]
+    #[allow(deprecated)]
     pub fn path_is_empty(self, right: Right) -> bool {
         (self & right.path()).is_empty()
     }
@@ -424,6 +427,8 @@ impl BitBoard {
 
     /// Returns the result of applying a function to a mutable string
     /// representation of `self`.
+    ///
+    /// See [`Diagram`](../diagram/struct.Diagram.html) for other formats.
     #[inline]
     pub fn map_str<T, F: FnOnce(&mut str) -> T>(&self, f: F) -> T {
         let mut buf = ::consts::BOARD_DOTS;
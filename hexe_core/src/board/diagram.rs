@@ -0,0 +1,229 @@
+//! Configurable text diagrams of boards.
+//!
+//! A [`Diagram`] describes how a board is drawn: with ASCII letters or Unicode
+//! figurines, with or without coordinates, from either side, and with a mask
+//! of squares highlighted. It writes to any [`fmt::Write`], so it works
+//! without the standard library.
+//!
+//! Highlighted squares are enclosed in brackets, which stay aligned with the
+//! rest of the board:
+//!
+//! ```
+//! # use hexe_core::board::{Diagram, PieceMap};
+//! # use hexe_core::prelude::*;
+//! let diagram = Diagram {
+//!     coordinates: true,
+//!     highlight: Square::G1.knight_attacks(),
+//!     ..Diagram::DEFAULT
+//! };
+//! let board = diagram.pieces(&PieceMap::STANDARD).to_string();
+//!
+//! assert_eq!(board.lines().nth(5).unwrap(), "3 . . . . .[.].[.]");
+//! assert_eq!(board.lines().nth(6).unwrap(), "2 P P P P[P]P P P");
+//! assert_eq!(board.lines().last().unwrap(), "  a b c d e f g h");
+//! ```
+//!
+//! [`Diagram`]: struct.Diagram.html
+//! [`fmt::Write`]: https://doc.rust-lang.org/core/fmt/trait.Write.html
+
+use core::fmt;
+
+use board::{BitBoard, PieceMap};
+use prelude::*;
+
+/// How a board is drawn as text.
+///
+/// The default draws ASCII letters from White's side without coordinates,
+/// matching the `Display` of [`PieceMap`] and [`BitBoard`].
+///
+/// [`PieceMap`]: ../piece_map/struct.PieceMap.html
+/// [`BitBoard`]: ../bit_board/struct.BitBoard.html
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Diagram {
+    /// Whether pieces are drawn as Unicode figurines instead of letters.
+    pub figurines: bool,
+    /// Whether ranks are labeled on the left and files below.
+    pub coordinates: bool,
+    /// The side the board is viewed from, which is at the bottom.
+    pub side: Color,
+    /// The squares to enclose in brackets.
+    pub highlight: BitBoard,
+}
+
+impl Default for Diagram {
+    #[inline]
+    fn default() -> Diagram { Diagram::DEFAULT }
+}
+
+impl Diagram {
+    /// ASCII letters from White's side without coordinates or highlights.
+    pub const DEFAULT: Diagram = Diagram {
+        figurines: false,
+        coordinates: false,
+        side: Color::White,
+        highlight: BitBoard::EMPTY,
+    };
+
+    /// Returns a value that formats `pieces` with `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hexe_core::board::{Diagram, PieceMap};
+    /// # use hexe_core::prelude::*;
+    /// let diagram = Diagram { figurines: true, side: Color::Black, ..Diagram::DEFAULT };
+    /// let board = diagram.pieces(&PieceMap::STANDARD).to_string();
+    ///
+    /// assert_eq!(board.lines().next().unwrap(), "♖ ♘ ♗ ♔ ♕ ♗ ♘ ♖");
+    /// ```
+    #[inline]
+    pub fn pieces<'a>(self, pieces: &'a PieceMap) -> Render<'a> {
+        Render { diagram: self, board: Board::Pieces(pieces) }
+    }
+
+    /// Returns a value that formats `bits` with `self`, drawing set squares
+    /// as `1`, or `■` with figurines.
+    #[inline]
+    pub fn bits<'a>(self, bits: BitBoard) -> Render<'a> {
+        Render { diagram: self, board: Board::Bits(bits) }
+    }
+
+    /// Writes `pieces` to `w`.
+    pub fn write_pieces<W: fmt::Write>(&self, w: &mut W, pieces: &PieceMap) -> fmt::Result {
+        self.write(w, |square| match pieces.get(square) {
+            Some(&piece) if self.figurines => piece.figurine(),
+            Some(&piece) => char::from(piece),
+            None => self.empty(),
+        })
+    }
+
+    /// Writes `bits` to `w`.
+    pub fn write_bits<W: fmt::Write>(&self, w: &mut W, bits: BitBoard) -> fmt::Result {
+        self.write(w, |square| match (bits.contains(square), self.figurines) {
+            (true, true) => '■',
+            (true, false) => '1',
+            (false, _) => self.empty(),
+        })
+    }
+
+    #[inline]
+    fn empty(&self) -> char {
+        if self.figurines { '·' } else { '.' }
+    }
+
+    fn write<W, F>(&self, w: &mut W, cell: F) -> fmt::Result
+        where W: fmt::Write, F: Fn(Square) -> char
+    {
+        // A bracket may be needed before the first file
+        let lead = self.coordinates || !self.highlight.is_empty();
+
+        for row in 0..8u8 {
+            let rank = match self.side {
+                Color::White => 7 - row,
+                Color::Black => row,
+            };
+            if row != 0 {
+                w.write_char('\n')?;
+            }
+            if self.coordinates {
+                w.write_char((b'1' + rank) as char)?;
+            }
+
+            let mut prev = false;
+            for col in 0..8u8 {
+                let file = match self.side {
+                    Color::White => col,
+                    Color::Black => 7 - col,
+                };
+                let square = Square::new(File::from(file), Rank::from(rank));
+                let lit = self.highlight.contains(square);
+                if col != 0 || lead {
+                    w.write_char(match (prev, lit) {
+                        (false, false) => ' ',
+                        (false, true) => '[',
+                        (true, false) => ']',
+                        (true, true) => '|',
+                    })?;
+                }
+                w.write_char(cell(square))?;
+                prev = lit;
+            }
+            if prev {
+                w.write_char(']')?;
+            }
+        }
+
+        if self.coordinates {
+            w.write_str("\n ")?;
+            for col in 0..8u8 {
+                let file = match self.side {
+                    Color::White => col,
+                    Color::Black => 7 - col,
+                };
+                w.write_char(' ')?;
+                w.write_char((b'a' + file) as char)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Board<'a> {
+    Pieces(&'a PieceMap),
+    Bits(BitBoard),
+}
+
+/// A board formatted with a [`Diagram`](struct.Diagram.html).
+#[derive(Copy, Clone)]
+pub struct Render<'a> {
+    diagram: Diagram,
+    board: Board<'a>,
+}
+
+impl<'a> fmt::Display for Render<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.board {
+            Board::Pieces(pieces) => self.diagram.write_pieces(f, pieces),
+            Board::Bits(bits) => self.diagram.write_bits(f, bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_map_str() {
+        let pieces = PieceMap::STANDARD;
+        let bits = Square::E4.queen_attacks(BitBoard::EMPTY);
+        let diagram = Diagram::DEFAULT;
+
+        pieces.map_str(|s| assert_eq!(diagram.pieces(&pieces).to_string(), *s));
+        bits.map_str(|s| assert_eq!(diagram.bits(bits).to_string(), *s));
+    }
+
+    #[test]
+    fn highlight() {
+        let diagram = Diagram {
+            highlight: Square::A1 | Square::B1 | Square::H8,
+            ..Diagram::DEFAULT
+        };
+        let board = diagram.pieces(&PieceMap::STANDARD).to_string();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[0], " r n b q k b n[r]");
+        assert_eq!(lines[1], " p p p p p p p p");
+        assert_eq!(lines[7], "[R|N]B Q K B N R");
+    }
+
+    #[test]
+    fn black_side() {
+        let diagram = Diagram { coordinates: true, side: Color::Black, ..Diagram::DEFAULT };
+        let board = diagram.bits(Square::A1.into()).to_string();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[0], "1 . . . . . . . 1");
+        assert_eq!(lines[7], "8 . . . . . . . .");
+        assert_eq!(lines[8], "  h g f e d c b a");
+    }
+}
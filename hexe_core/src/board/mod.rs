@@ -88,15 +88,17 @@
 //! [`Square`]: ../square/enum.Square.html
 
 pub mod bit_board;
+pub mod diagram;
 pub mod multi_board;
 pub mod piece_map;
 
 #[doc(inline)] pub use self::bit_board::BitBoard;
+#[doc(inline)] pub use self::diagram::Diagram;
 #[doc(inline)] pub use self::multi_board::MultiBoard;
 #[doc(inline)] pub use self::piece_map::PieceMap;
 
 /// Chess variants that Hexe supports (or plans to support).
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Variant {
     /// Standard vanilla chess.
     Standard,
@@ -105,6 +107,12 @@ pub enum Variant {
     ///
     /// This variant may also be called Fischer Random Chess.
     Chess960,
+    /// [Three-check](https://en.wikipedia.org/wiki/Three-check_chess), where
+    /// giving check for the third time wins the game.
+    ThreeCheck,
+    /// [King of the Hill](https://lichess.org/variant/kingOfTheHill), where
+    /// bringing the king to one of the four center squares wins the game.
+    KingOfTheHill,
     #[doc(hidden)]
     // Here be dragons and nasal demons.
     // TODO: https://github.com/rust-lang/rust/issues/44109
@@ -115,3 +123,21 @@ impl Default for Variant {
     #[inline]
     fn default() -> Variant { Variant::Standard }
 }
+
+impl Variant {
+    /// Returns whether games of `self` are won and drawn the same way as in
+    /// standard chess, so that endgame knowledge and tablebases apply.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe_core::board::Variant;
+    ///
+    /// assert!(Variant::Chess960.has_standard_goal());
+    /// assert!(!Variant::ThreeCheck.has_standard_goal());
+    /// ```
+    #[inline]
+    pub fn has_standard_goal(self) -> bool {
+        self == Variant::Standard || self == Variant::Chess960
+    }
+}
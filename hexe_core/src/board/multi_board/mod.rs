@@ -484,6 +484,9 @@ impl MultiBoard {
     /// The above are all the result of properly defined behavior. They are just
     /// side effects of how the board is represented and this use of [XOR].
     ///
+    /// This assumes the king and rook start on their squares in standard
+    /// chess, as in `Files::STANDARD`.
+    ///
     ///
     /// # Examples
     ///
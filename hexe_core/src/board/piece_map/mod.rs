@@ -510,6 +510,9 @@ impl PieceMap {
     ///
     /// Under legal castling circumstances, this method makes it so that squares
     /// involved with castling using `right` are in a correct state post-castle.
+    ///
+    /// This assumes the king and rook start on their squares in standard
+    /// chess, as in `Files::STANDARD`.
     #[inline]
     pub fn castle(&mut self, right: Right) {
         let (king_sq, start_sq) = castle::TABLES.pm_pairs[right as usize];
@@ -708,7 +711,8 @@ impl PieceMap {
     /// Returns the result of applying a function to a mutable string
     /// representation of `self`.
     ///
-    /// This method has the same benefits as [`Square::map_str`]
+    /// This method has the same benefits as [`Square::map_str`]. See
+    /// [`Diagram`] for other formats.
     ///
     /// # Examples
     ///
@@ -732,6 +736,7 @@ impl PieceMap {
     ///
     /// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
     /// [`Square::map_str`]: ../../square/enum.Square.html#method.map_str
+    /// [`Diagram`]: ../diagram/struct.Diagram.html
     #[inline]
     pub fn map_str<T, F: FnOnce(&mut str) -> T>(&self, f: F) -> T {
         let mut buf = ::consts::BOARD_DOTS;
@@ -272,15 +272,18 @@ impl Right {
         }
     }
 
-    /// Returns the path between the rook and king for this right.
+    /// Returns the path between the rook and king for this right in
+    /// standard chess.
     #[inline]
+    #[deprecated(note = "only holds for standard chess; use `Files::path` instead")]
     pub fn path(self) -> BitBoard {
         TABLES.path[self as usize]
     }
 
     /// Returns an efficient iterator over each square in the path between the
-    /// rook and king for `self`.
+    /// rook and king for `self` in standard chess.
     #[inline]
+    #[deprecated(note = "only holds for standard chess; use `Files::path` instead")]
     pub fn path_iter(self) -> iter::Range<Square> {
         TABLES.path_iter[self as usize].clone()
     }
@@ -296,6 +299,164 @@ impl Right {
     pub fn side(self) -> Side {
         (1 & self as u8).into()
     }
+
+    /// Returns the square the king lands on when castling with `self`.
+    ///
+    /// This is the same in Chess960 as in standard chess.
+    #[inline]
+    pub fn king_dst(self) -> Square {
+        let file = match self.side() {
+            Side::King  => File::G,
+            Side::Queen => File::C,
+        };
+        Square::new(file, Rank::first(self.color()))
+    }
+
+    /// Returns the square the rook lands on when castling with `self`.
+    ///
+    /// This is the same in Chess960 as in standard chess.
+    #[inline]
+    pub fn rook_dst(self) -> Square {
+        let file = match self.side() {
+            Side::King  => File::F,
+            Side::Queen => File::D,
+        };
+        Square::new(file, Rank::first(self.color()))
+    }
+}
+
+/// The files on which the kings and castling rooks start.
+///
+/// In standard chess, kings start on the e-file and rooks on the a and h
+/// files. In [Chess960](https://en.wikipedia.org/wiki/Chess960), these vary
+/// per game, but castling still places the king and rook on the same squares
+/// as in standard chess.
+///
+/// # Examples
+///
+/// ```
+/// # use hexe_core::prelude::*;
+/// use hexe_core::castle::Files;
+///
+/// // Kings on the b-file with rooks on the a and c files
+/// let files = Files::new(File::B, File::C, File::A).unwrap();
+///
+/// assert_eq!(files.rook_square(Right::BlackKing), Square::C8);
+/// assert_eq!(files.path(Right::WhiteKing), Square::D1 | Square::E1 | Square::F1 | Square::G1);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Files {
+    kings: [File; 2],
+    rooks: [File; 4],
+}
+
+impl Default for Files {
+    #[inline]
+    fn default() -> Files { Files::STANDARD }
+}
+
+impl Files {
+    /// The files of standard chess.
+    pub const STANDARD: Files = Files {
+        kings: [File::E, File::E],
+        rooks: [File::H, File::A, File::H, File::A],
+    };
+
+    /// Creates files for both colors with the king on `king` between the
+    /// kingside rook on `king_rook` and the queenside rook on `queen_rook`,
+    /// returning `None` if the king is not between them.
+    #[inline]
+    pub fn new(king: File, king_rook: File, queen_rook: File) -> Option<Files> {
+        if (queen_rook as u8) < (king as u8) && (king as u8) < (king_rook as u8) {
+            Some(Files {
+                kings: [king; 2],
+                rooks: [king_rook, queen_rook, king_rook, queen_rook],
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns `self` with the king of `color` starting on `file`.
+    #[inline]
+    pub fn with_king(mut self, color: Color, file: File) -> Files {
+        self.kings[color as usize] = file;
+        self
+    }
+
+    /// Returns `self` with the rook that castles with `right` starting on
+    /// `file`.
+    #[inline]
+    pub fn with_rook(mut self, right: Right, file: File) -> Files {
+        self.rooks[right as usize] = file;
+        self
+    }
+
+    /// Returns whether `self` matches standard chess.
+    #[inline]
+    pub fn is_standard(&self) -> bool {
+        *self == Files::STANDARD
+    }
+
+    /// Returns the file on which the king of `color` starts.
+    #[inline]
+    pub fn king(&self, color: Color) -> File {
+        self.kings[color as usize]
+    }
+
+    /// Returns the file on which the rook that castles with `right` starts.
+    #[inline]
+    pub fn rook(&self, right: Right) -> File {
+        self.rooks[right as usize]
+    }
+
+    /// Returns the square on which the king of `color` starts.
+    #[inline]
+    pub fn king_square(&self, color: Color) -> Square {
+        Square::new(self.king(color), Rank::first(color))
+    }
+
+    /// Returns the square on which the rook that castles with `right` starts.
+    #[inline]
+    pub fn rook_square(&self, right: Right) -> Square {
+        Square::new(self.rook(right), Rank::first(right.color()))
+    }
+
+    /// Returns the squares that must be empty, other than those of the king
+    /// and rook themselves, to castle with `right`.
+    pub fn path(&self, right: Right) -> BitBoard {
+        let king = self.king_square(right.color());
+        let rook = self.rook_square(right);
+        let (king_dst, rook_dst) = (right.king_dst(), right.rook_dst());
+
+        let path = king.between(king_dst) | king_dst | rook.between(rook_dst) | rook_dst;
+        path - king - rook
+    }
+
+    /// Returns the squares the king passes through and lands on when castling
+    /// with `right`, none of which may be attacked.
+    #[inline]
+    pub fn king_path(&self, right: Right) -> BitBoard {
+        let king = self.king_square(right.color());
+        king.between(right.king_dst()) | right.king_dst()
+    }
+
+    /// Returns the castle rights lost when a piece moves from or to `square`.
+    #[inline]
+    pub fn rights_lost(&self, square: Square) -> Rights {
+        let mut rights = Rights::EMPTY;
+        for color in Color::ALL {
+            if square == self.king_square(color) {
+                rights |= Rights::from(color);
+            }
+        }
+        for right in Rights::FULL {
+            if square == self.rook_square(right) {
+                rights |= right;
+            }
+        }
+        rights
+    }
 }
 
 pub mod path {
@@ -372,6 +533,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn castle_right_path() {
         fn path(right: Right) -> BitBoard {
             use self::Right::*;
@@ -389,6 +551,42 @@ mod tests {
         }
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn files_standard() {
+        let files = Files::STANDARD;
+        for right in Rights::FULL {
+            assert_eq!(files.path(right), right.path());
+            assert_eq!(Move::castle(right).src(), files.king_square(right.color()));
+            assert_eq!(Move::castle(right).dst(), right.king_dst());
+        }
+        for square in Square::ALL {
+            let exp = match square {
+                Square::E1 => Rights::WHITE,
+                Square::H1 => Rights::WHITE_KING,
+                Square::A1 => Rights::WHITE_QUEEN,
+                Square::E8 => Rights::BLACK,
+                Square::H8 => Rights::BLACK_KING,
+                Square::A8 => Rights::BLACK_QUEEN,
+                _ => Rights::EMPTY,
+            };
+            assert_eq!(files.rights_lost(square), exp);
+        }
+    }
+
+    #[test]
+    fn files_chess960() {
+        assert!(Files::new(File::A, File::H, File::B).is_none());
+
+        // The king already stands on its destination
+        let files = Files::new(File::G, File::H, File::B).unwrap();
+        assert_eq!(files.path(Right::WhiteKing), Square::F1.into());
+        assert_eq!(files.king_path(Right::WhiteKing), Square::G1.into());
+        assert_eq!(files.path(Right::BlackQueen), Square::C8 | Square::D8 | Square::E8 | Square::F8);
+        assert_eq!(files.rights_lost(Square::G8), Rights::BLACK);
+        assert_eq!(files.rights_lost(Square::B1), Rights::WHITE_QUEEN);
+    }
+
     #[test]
     fn castle_rights_string() {
         use self::Right::*;
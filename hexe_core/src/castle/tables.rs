@@ -14,6 +14,9 @@ macro_rules! quad {
     }
 }
 
+/// Lookup tables for castling in standard chess.
+///
+/// Castling with the kings and rooks starting elsewhere goes through `Files`.
 #[repr(align(64))]
 pub struct Tables {
     pub mb_masks: [(u64, u64); 4],
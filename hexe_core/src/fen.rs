@@ -7,6 +7,7 @@ use core::str;
 
 use prelude::*;
 use board::PieceMap;
+use castle::{Files, Side};
 
 /// A type that can be used to parse [Forsyth–Edwards Notation (FEN)][fen].
 ///
@@ -19,12 +20,18 @@ pub struct Fen {
     pub color: Color,
     /// The castling rights.
     pub castling: Rights,
+    /// The files on which the kings and castling rooks start, which differ
+    /// from those of standard chess in Chess960.
+    pub castle_files: Files,
     /// The en passant target square.
     pub en_passant: Option<Square>,
     /// The number of halfmoves since the last capture or pawn advance.
     pub halfmoves: u32,
     /// The fullmove number.
     pub fullmoves: u32,
+    /// The number of checks given by each color in Three-check, indexed by
+    /// `Color`, as written in the `+N+M` suffix.
+    pub checks: Option<[u8; 2]>,
 }
 
 impl fmt::Display for Fen {
@@ -41,11 +48,11 @@ impl fmt::Display for Fen {
             f.write_str(string)?;
         }
 
-        self.castling.map_str(|s| f.write_str(s))?;
+        self.write_castling(f)?;
 
         if let Some(sq) = self.en_passant {
             let mut buf: [u8; 4] = *b"    ";
-            buf[1] = char::from(sq.file()) as u8;
+            buf[1] = 32 | char::from(sq.file()) as u8;
             buf[2] = char::from(sq.rank()) as u8;
             let string = unsafe { str::from_utf8_unchecked(&buf) };
             f.write_str(string)?;
@@ -56,8 +63,153 @@ impl fmt::Display for Fen {
         self.halfmoves.fmt(f)?;
         f.write_char(' ')?;
 
-        self.fullmoves.fmt(f)
+        self.fullmoves.fmt(f)?;
+
+        if let Some([white, black]) = self.checks {
+            write!(f, " +{}+{}", white, black)?;
+        }
+        Ok(())
+    }
+}
+
+define_from_str_error! { Fen;
+    /// The error returned when `Fen::from_str` fails.
+    "failed to parse a string as FEN"
+}
+
+impl str::FromStr for Fen {
+    type Err = FromStrError;
+
+    /// Parses a FEN string, defaulting the halfmove clock and fullmove number
+    /// to 0 and 1 respectively if they are omitted.
+    ///
+    /// The checks given in Three-check may follow as a `+N+M` suffix.
+    fn from_str(s: &str) -> Result<Fen, FromStrError> {
+        const ERR: FromStrError = FromStrError(());
+
+        let mut fields = s.split_whitespace();
+        let mut next = || fields.next().ok_or(ERR);
+
+        let pieces = PieceMap::from_fen(next()?).ok_or(ERR)?;
+
+        let color = match next()? {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(ERR),
+        };
+
+        let (castling, castle_files) = parse_castling(next()?, &pieces).ok_or(ERR)?;
+
+        let en_passant = match next()? {
+            "-" => None,
+            sq => Some(sq.parse().map_err(|_| ERR)?),
+        };
+
+        let halfmoves = next().ok().map_or(Ok(0), str::parse).map_err(|_| ERR)?;
+        let fullmoves = next().ok().map_or(Ok(1), str::parse).map_err(|_| ERR)?;
+
+        // Only a `+N+M` suffix is taken as checks; any other trailing field
+        // is ignored like those after it
+        let checks = match next().ok() {
+            Some(s) if s.starts_with('+') => Some(parse_checks(s).ok_or(ERR)?),
+            _ => None,
+        };
+
+        Ok(Fen { pieces, color, castling, castle_files, en_passant, halfmoves, fullmoves, checks })
+    }
+}
+
+/// Parses the checks given by each color from a `+N+M` suffix, where each
+/// count is at most 3.
+fn parse_checks(s: &str) -> Option<[u8; 2]> {
+    match *s.as_bytes() {
+        [b'+', white, b'+', black] => {
+            let (white, black) = (white.wrapping_sub(b'0'), black.wrapping_sub(b'0'));
+            if white <= 3 && black <= 3 { Some([white, black]) } else { None }
+        },
+        _ => None,
+    }
+}
+
+/// Returns a mask of the files of the rooks of `color` on its first rank in
+/// `pieces` that are on `side` of the king on `king`.
+fn rook_mask(pieces: &PieceMap, color: Color, king: File, side: Side) -> u8 {
+    let rook = Piece::new(Role::Rook, color);
+    let rank = Rank::first(color);
+    let mut mask = 0;
+    for file in 0..8u8 {
+        let beside = match side {
+            Side::King  => file > king as u8,
+            Side::Queen => file < king as u8,
+        };
+        if beside && pieces.get(Square::new(File::from(file), rank)) == Some(&rook) {
+            mask |= 1 << file;
+        }
     }
+    mask
+}
+
+/// Parses the castling field of standard FEN, X-FEN or Shredder-FEN, finding
+/// the king and rook that castle for each right within `pieces`.
+///
+/// `K` and `Q` refer to the outermost rook on each side of the king, whereas a
+/// file letter refers to the rook on that file.
+fn parse_castling(s: &str, pieces: &PieceMap) -> Option<(Rights, Files)> {
+    let mut rights = Rights::EMPTY;
+    let mut files = Files::STANDARD;
+    if s == "-" {
+        return Some((rights, files));
+    }
+
+    for ch in s.chars() {
+        let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let king = Piece::new(Role::King, color);
+        let rank = Rank::first(color);
+        let king_file = (0..8u8).map(File::from).find(|&file| {
+            pieces.get(Square::new(file, rank)) == Some(&king)
+        });
+
+        let (side, rook_file) = match ch.to_ascii_lowercase() {
+            lower @ 'k' | lower @ 'q' => {
+                let side = if lower == 'k' { Side::King } else { Side::Queen };
+                let outermost = king_file.and_then(|king_file| {
+                    match rook_mask(pieces, color, king_file, side) {
+                        0 => None,
+                        mask => Some(File::from(match side {
+                            Side::King  => 7 - mask.leading_zeros() as u8,
+                            Side::Queen => mask.trailing_zeros() as u8,
+                        })),
+                    }
+                });
+                match (king_file, outermost) {
+                    (Some(king_file), Some(rook_file)) => {
+                        files = files.with_king(color, king_file);
+                        (side, rook_file)
+                    },
+                    // Keep accepting rights without a king and rook in place
+                    _ => (side, Files::STANDARD.rook(Right::new(color, side))),
+                }
+            },
+            lower => {
+                let rook_file = File::from_char(lower)?;
+                let king_file = king_file?;
+                let side = if rook_file as u8 > king_file as u8 {
+                    Side::King
+                } else if (rook_file as u8) < king_file as u8 {
+                    Side::Queen
+                } else {
+                    return None;
+                };
+                files = files.with_king(color, king_file);
+                (side, rook_file)
+            },
+        };
+
+        let right = Right::new(color, side);
+        rights |= right;
+        files = files.with_rook(right, rook_file);
+    }
+    Some((rights, files))
 }
 
 impl Fen {
@@ -70,9 +222,11 @@ impl Fen {
         pieces: PieceMap::STANDARD,
         color: Color::White,
         castling: Rights::FULL,
+        castle_files: Files::STANDARD,
         en_passant: None,
         halfmoves: 0,
         fullmoves: 1,
+        checks: None,
     };
 
     /// FEN for the empty position. It is equivalent to:
@@ -84,10 +238,112 @@ impl Fen {
         pieces: PieceMap::EMPTY,
         color: Color::White,
         castling: Rights::EMPTY,
+        castle_files: Files::STANDARD,
         en_passant: None,
         halfmoves: 0,
         fullmoves: 1,
+        checks: None,
     };
+
+    /// Returns the starting position of Chess960 with the [Scharnagl
+    /// index][index] `index`, or `None` if it is not less than 960.
+    ///
+    /// Index 518 is the starting position of standard chess.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hexe_core::fen::Fen;
+    ///
+    /// let fen = Fen::chess960(0).unwrap();
+    /// assert_eq!(fen.to_string(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    /// assert!(Fen::chess960(518).unwrap() == Fen::STANDARD);
+    /// ```
+    ///
+    /// [index]: https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+    pub fn chess960(index: u32) -> Option<Fen> {
+        /// The files of both knights among the five files left after placing
+        /// the bishops and queen.
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1), (0, 2), (0, 3), (0, 4), (1, 2),
+            (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+        ];
+
+        /// Places `role` on the `nth` empty file of `rank`.
+        fn place(rank: &mut [Option<Role>; 8], nth: usize, role: Role) -> usize {
+            let file = (0..8).filter(|&file| rank[file].is_none()).nth(nth).unwrap();
+            rank[file] = Some(role);
+            file
+        }
+
+        if index >= 960 {
+            return None;
+        }
+        let mut n = index as usize;
+        let mut rank = [None; 8];
+
+        rank[2 * (n % 4) + 1] = Some(Role::Bishop);
+        n /= 4;
+        rank[2 * (n % 4)] = Some(Role::Bishop);
+        n /= 4;
+        place(&mut rank, n % 6, Role::Queen);
+        n /= 6;
+
+        // Placing the second knight first leaves the index of the first as is
+        let (first, second) = KNIGHTS[n];
+        place(&mut rank, second, Role::Knight);
+        place(&mut rank, first, Role::Knight);
+
+        let queen_rook = place(&mut rank, 0, Role::Rook);
+        let king = place(&mut rank, 0, Role::King);
+        let king_rook = place(&mut rank, 0, Role::Rook);
+
+        let mut pieces = PieceMap::EMPTY;
+        for (file, role) in rank.iter().enumerate() {
+            let file = File::from(file as u8);
+            let role = role.unwrap();
+            pieces.insert(Square::new(file, Rank::One), Piece::new(role, Color::White));
+            pieces.insert(Square::new(file, Rank::Two), Piece::WhitePawn);
+            pieces.insert(Square::new(file, Rank::Seven), Piece::BlackPawn);
+            pieces.insert(Square::new(file, Rank::Eight), Piece::new(role, Color::Black));
+        }
+
+        let files = |file: usize| File::from(file as u8);
+        Some(Fen {
+            pieces,
+            castle_files: Files::new(files(king), files(king_rook), files(queen_rook))?,
+            ..Fen::STANDARD
+        })
+    }
+
+    /// Writes the castling field, naming the file of a rook as in X-FEN only
+    /// when another rook lies further out on the same side of its king.
+    fn write_castling(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.castle_files.is_standard() || self.castling.is_empty() {
+            return self.castling.map_str(|s| f.write_str(s));
+        }
+        for right in self.castling {
+            let color = right.color();
+            let king = self.castle_files.king(color);
+            let file = self.castle_files.rook(right) as u8;
+            let mask = rook_mask(&self.pieces, color, king, right.side()) as u32;
+            let outermost = match right.side() {
+                Side::King  => mask >> (file + 1) == 0,
+                Side::Queen => mask & ((1 << file) - 1) == 0,
+            };
+            let ch = if outermost {
+                char::from(right)
+            } else {
+                let ch = char::from(File::from(file));
+                match color {
+                    Color::White => ch,
+                    Color::Black => ch.to_ascii_lowercase(),
+                }
+            };
+            f.write_char(ch)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -107,4 +363,81 @@ mod tests {
             assert_eq!(string, exp);
         }
     }
+
+    #[test]
+    fn from_str() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq e3 12 40",
+        ];
+
+        for &fen in fens.iter() {
+            let parsed = fen.parse::<Fen>().unwrap();
+            assert_eq!(parsed.to_string(), fen);
+        }
+
+        let short = "8/8/8/8/8/8/8/8 b - -".parse::<Fen>().unwrap();
+        assert_eq!((short.halfmoves, short.fullmoves), (0, 1));
+        assert_eq!(short.checks, None);
+
+        for &bad in ["", "8/8/8 w - - 0 1", "8/8/8/8/8/8/8/8 x - - 0 1"].iter() {
+            assert!(bad.parse::<Fen>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn three_check() {
+        let fen = "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3 +1+0";
+        let parsed = fen.parse::<Fen>().unwrap();
+        assert_eq!(parsed.checks, Some([1, 0]));
+        assert_eq!(parsed.to_string(), fen);
+
+        for &bad in ["+4+0", "+1", "+/+0", "+1+0+0"].iter() {
+            let fen = format!("8/8/8/8/8/8/8/8 w - - 0 1 {}", bad);
+            assert!(fen.parse::<Fen>().is_err(), "{:?}", fen);
+        }
+
+        let extra = "8/8/8/8/8/8/8/8 w - - 0 1 1+1 +1+0".parse::<Fen>().unwrap();
+        assert_eq!(extra.checks, None);
+    }
+
+    #[test]
+    fn chess960_castling() {
+        // Shredder-FEN and X-FEN of the same position
+        let shredder = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let x_fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9";
+
+        let fen = shredder.parse::<Fen>().unwrap();
+        assert!(fen == x_fen.parse::<Fen>().unwrap());
+        assert_eq!(fen.to_string(), x_fen);
+        assert_eq!(fen.castling, Rights::FULL);
+        assert_eq!(fen.castle_files, Files::new(File::G, File::H, File::F).unwrap());
+
+        // Only the inner rook is named by its file
+        let inner = "4k3/8/8/8/8/8/8/R1R1K2R w KCk - 0 1";
+        let fen = inner.parse::<Fen>().unwrap();
+        assert_eq!(fen.castle_files.rook(Right::WhiteQueen), File::C);
+        assert_eq!(fen.to_string(), inner);
+        assert!(fen == "4k3/8/8/8/8/8/8/R1R1K2R w HCk - 0 1".parse::<Fen>().unwrap());
+
+        for &bad in ["4k3/8/8/8/8/8/8/4K3 w E - 0 1", "8/8/8/8/8/8/8/R7 w A - 0 1"].iter() {
+            assert!(bad.parse::<Fen>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn chess960() {
+        let fens = [
+            (0,   "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"),
+            (518, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (959, "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"),
+        ];
+        for &(index, exp) in fens.iter() {
+            let fen = Fen::chess960(index).unwrap();
+            assert_eq!(fen.to_string(), exp);
+            assert!(fen == exp.parse::<Fen>().unwrap());
+        }
+        assert!(Fen::chess960(960).is_none());
+    }
 }
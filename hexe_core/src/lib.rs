@@ -111,6 +111,9 @@ pub mod mv;
 pub mod piece;
 pub mod square;
 
+#[cfg(feature = "svg")]
+pub mod svg;
+
 // Modules shared with hexe that aren't meant for public use
 #[doc(hidden)]
 pub mod _shared {
@@ -18,11 +18,11 @@ fn en_passant_try_new_1000(b: &mut Bencher) {
 }
 
 #[bench]
-fn castle_try_new_1000(b: &mut Bencher) {
+fn castle_try_from_files_1000(b: &mut Bencher) {
     let squares = gen_squares();
     b.iter(|| {
         for &(s1, s2) in squares.iter().map(black_box) {
-            if let Some(mv) = kind::Castle::try_new(s1, s2) {
+            if let Some(mv) = kind::Castle::try_from_files(s1, s2, &castle::Files::STANDARD) {
                 black_box(mv);
             }
         }
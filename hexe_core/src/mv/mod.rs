@@ -89,6 +89,40 @@ impl fmt::Debug for Move {
     }
 }
 
+impl fmt::Display for Move {
+    /// Formats `self` in the long algebraic notation used by the [Universal
+    /// Chess Interface][uci] (e.g. `e2e4` or `e7e8q`).
+    ///
+    /// The null move is formatted as `0000`.
+    ///
+    /// [uci]: http://wbec-ridderkerk.nl/html/UCIProtocol.html
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_null() {
+            return f.write_str("0000");
+        }
+
+        let (src, dst) = (self.src(), self.dst());
+        let mut buf = [
+            32 | char::from(src.file()) as u8,
+            char::from(src.rank()) as u8,
+            32 | char::from(dst.file()) as u8,
+            char::from(dst.rank()) as u8,
+            0,
+        ];
+
+        let len = match self.kind() {
+            Kind::Promotion => {
+                let piece = kind::Promotion(*self).piece();
+                buf[4] = 32 | char::from(piece) as u8;
+                5
+            },
+            _ => 4,
+        };
+
+        f.write_str(unsafe { ::core::str::from_utf8_unchecked(&buf[..len]) })
+    }
+}
+
 impl Move {
     /// Creates a new `Move` from one square to another.
     #[inline]
@@ -149,12 +183,13 @@ impl Move {
         (self.0 >> META_SHIFT) & META_MASK
     }
 
-    /// Returns `self` a castle move if it can be converted into one.
+    /// Returns `self` a castle move if it can be converted into one in
+    /// standard chess.
     #[inline]
     pub fn to_castle(self) -> Option<kind::Castle> {
         match self.kind() {
             Kind::Castle => Some(kind::Castle(self)),
-            _ => kind::Castle::try_new(self.src(), self.dst()),
+            _ => kind::Castle::try_from_files(self.src(), self.dst(), &castle::Files::STANDARD),
         }
     }
 
@@ -398,22 +433,67 @@ pub mod kind {
         #[inline]
         pub fn new(right: Right) -> Castle { right.into() }
 
-        /// Attempts to create a new castle move for the given squares.
+        /// Attempts to create a new castle move for the given squares in
+        /// standard chess.
         #[inline]
+        #[deprecated(note = "only holds for standard chess; use `Castle::try_from_files` instead")]
         pub fn try_new(src: Square, dst: Square) -> Option<Castle> {
-            match src {
-                Square::E1 => match dst {
-                    Square::G1 => Some(Right::WhiteKing.into()),
-                    Square::C1 => Some(Right::WhiteQueen.into()),
-                    _ => None,
-                },
-                Square::E8 => match dst {
-                    Square::G8 => Some(Right::BlackKing.into()),
-                    Square::C8 => Some(Right::BlackQueen.into()),
-                    _ => None,
-                },
-                _ => None,
+            Castle::try_from_files(src, dst, &castle::Files::STANDARD)
+        }
+
+        /// Attempts to create a new castle move for the given squares with
+        /// the king and rooks starting on `files`.
+        ///
+        /// With standard files, the move is written as the king's move (e.g.
+        /// `e1g1`) as with [`new`](#method.new). Otherwise, it is written as
+        /// the king capturing its own rook as with
+        /// [`try_chess960`](#method.try_chess960), since the king's move may
+        /// then look like a normal king move.
+        #[inline]
+        pub fn try_from_files(src: Square, dst: Square, files: &castle::Files) -> Option<Castle> {
+            if !files.is_standard() {
+                return Castle::try_chess960(src, dst, files);
+            }
+            let color = match src.rank() {
+                Rank::One   => Color::White,
+                Rank::Eight => Color::Black,
+                _ => return None,
+            };
+            if src != files.king_square(color) {
+                return None;
+            }
+            [castle::Side::King, castle::Side::Queen].iter()
+                .map(|&side| Right::new(color, side))
+                .find(|&right| dst == right.king_dst())
+                .map(Castle::new)
+        }
+
+        /// Creates a new instance for the castle right with the king and
+        /// rook starting on `files`, written as the king capturing its own
+        /// rook as in Chess960 (e.g. `e1h1` rather than `e1g1`).
+        #[inline]
+        pub fn chess960(right: Right, files: &castle::Files) -> Castle {
+            let king = files.king_square(right.color());
+            let rook = files.rook_square(right);
+            Castle(Move(base!(king, rook) | kind!(Castle) | meta!(right)))
+        }
+
+        /// Attempts to create a new castle move for the given squares, where
+        /// `src` is the king and `dst` is the rook it castles with on `files`.
+        #[inline]
+        pub fn try_chess960(src: Square, dst: Square, files: &castle::Files) -> Option<Castle> {
+            let color = match src.rank() {
+                Rank::One   => Color::White,
+                Rank::Eight => Color::Black,
+                _ => return None,
+            };
+            if src != files.king_square(color) {
+                return None;
             }
+            [castle::Side::King, castle::Side::Queen].iter()
+                .map(|&side| Right::new(color, side))
+                .find(|&right| dst == files.rook_square(right))
+                .map(|right| Castle::chess960(right, files))
         }
 
         /// Returns the kind for `self`.
@@ -455,6 +535,15 @@ pub mod kind {
             Promotion(Move(file | rank | kind!(Promotion) | meta!(piece)))
         }
 
+        /// Creates a new promotion move from `src` to `dst`.
+        ///
+        /// Unlike [`new`](#method.new), the files of `src` and `dst` may
+        /// differ, as is the case when a pawn promotes by capturing.
+        #[inline]
+        pub fn with_squares(src: Square, dst: Square, piece: piece::Promotion) -> Promotion {
+            Promotion(Move(base!(src, dst) | kind!(Promotion) | meta!(piece)))
+        }
+
         /// Returns the kind for `self`.
         #[inline]
         pub fn kind(self) -> Kind { Kind::Promotion }
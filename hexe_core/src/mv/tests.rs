@@ -15,9 +15,36 @@ fn castle() {
         assert_eq!(mv.right(), right, "{:?}", mv);
         assert_eq!(mv.src(),   src,   "{:?}", mv);
         assert_eq!(mv.dst(),   dst,   "{:?}", mv);
+        assert_eq!(kind::Castle::try_from_files(src, dst, &castle::Files::STANDARD), Some(mv));
     }
 }
 
+#[test]
+fn castle_chess960() {
+    use prelude::*;
+    use castle::Files;
+
+    let files = Files::new(File::B, File::C, File::A).unwrap();
+    for right in Right::ALL {
+        let mv = kind::Castle::chess960(right, &files);
+        assert_eq!(mv.right(), right, "{:?}", mv);
+        assert_eq!(mv.src(), files.king_square(right.color()), "{:?}", mv);
+        assert_eq!(mv.dst(), files.rook_square(right), "{:?}", mv);
+        assert_eq!(kind::Castle::try_chess960(mv.src(), mv.dst(), &files), Some(mv));
+    }
+
+    let mv = kind::Castle::chess960(Right::WhiteKing, &Files::STANDARD);
+    assert_eq!((mv.src(), mv.dst()), (Square::E1, Square::H1));
+    assert_eq!(kind::Castle::try_chess960(Square::B1, Square::D1, &files), None);
+    assert_eq!(kind::Castle::try_chess960(Square::B2, Square::C2, &files), None);
+
+    // The king's move is only a castle with standard files
+    let mv = kind::Castle::chess960(Right::WhiteQueen, &files);
+    assert_eq!(kind::Castle::try_from_files(Square::B1, Square::A1, &files), Some(mv));
+    assert_eq!(kind::Castle::try_from_files(Square::B1, Square::G1, &files), None);
+    assert_eq!(kind::Castle::try_from_files(Square::E1, Square::H1, &Files::STANDARD), None);
+}
+
 #[test]
 fn promotion() {
     use prelude::*;
@@ -43,3 +70,21 @@ fn promotion() {
         }
     }
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn display() {
+    use prelude::*;
+
+    let moves = [
+        (Move::normal(Square::E2, Square::E4), "e2e4"),
+        (Move::castle(Right::BlackQueen), "e8c8"),
+        (Move::promotion(File::H, Color::White, piece::Promotion::Knight), "h7h8n"),
+        (kind::Promotion::with_squares(Square::B2, Square::A1, piece::Promotion::Queen).into(), "b2a1q"),
+        (Move::normal(Square::A1, Square::A1), "0000"),
+    ];
+
+    for &(mv, exp) in moves.iter() {
+        assert_eq!(mv.to_string(), exp);
+    }
+}
@@ -43,6 +43,10 @@ pub enum Piece {
 
 static PIECE_CHARS_ASCII: [u8; 12] = *b"PpNnBbRrQqKk";
 
+static PIECE_FIGURINES: [char; 12] = [
+    '♙', '♟', '♘', '♞', '♗', '♝', '♖', '♜', '♕', '♛', '♔', '♚',
+];
+
 impl From<Piece> for char {
     #[inline]
     fn from(p: Piece) -> char {
@@ -96,6 +100,20 @@ impl Piece {
     pub fn into_char(self) -> char {
         self.into()
     }
+
+    /// Returns the Unicode chess symbol for `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hexe_core::piece::Piece;
+    /// assert_eq!(Piece::WhiteKnight.figurine(), '♘');
+    /// assert_eq!(Piece::BlackQueen.figurine(), '♛');
+    /// ```
+    #[inline]
+    pub fn figurine(self) -> char {
+        PIECE_FIGURINES[self as usize]
+    }
 }
 
 /// A chess piece role.
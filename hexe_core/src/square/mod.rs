@@ -636,7 +636,7 @@ impl File {
     #[inline]
     pub fn from_char(ch: char) -> Option<File> {
         match 32 | ch as u8 {
-            b @ b'a' ... b'f' => unsafe {
+            b @ b'a' ... b'h' => unsafe {
                 Some((b - b'a').into_unchecked())
             },
             _ => None,
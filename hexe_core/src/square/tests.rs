@@ -135,11 +135,12 @@ fn pawn_attacks() {
 
 #[test]
 fn file_from_char() {
-    for ch in b'A'..(b'F' + 1) {
+    for ch in b'A'..(b'H' + 1) {
         for &ch in &[ch, ch | 32] {
             assert!(File::from_char(ch as _).is_some());
         }
     }
+    assert!(File::from_char('i').is_none());
 }
 
 #[test]
@@ -0,0 +1,311 @@
+//! Rendering boards as standalone SVG images.
+//!
+//! This module requires the `svg` feature, which implies `std`.
+//!
+//! Pieces are drawn as Unicode figurines in the viewer's default font, so the
+//! image has no external dependencies.
+//!
+//! # Examples
+//!
+//! ```
+//! use hexe_core::board::PieceMap;
+//! use hexe_core::prelude::*;
+//! use hexe_core::svg::{Arrow, Svg};
+//!
+//! let svg = Svg {
+//!     coordinates: true,
+//!     last_move: Some((Square::E2, Square::E4)),
+//!     arrows: vec![Arrow::new(Square::G1, Square::F3, "#15781b")],
+//!     ..Svg::default()
+//! };
+//! let image = svg.render(&PieceMap::STANDARD);
+//! assert!(image.starts_with("<svg"));
+//! ```
+
+use core::fmt;
+
+use board::{BitBoard, PieceMap};
+use prelude::*;
+
+/// Formats a string escaped for use within an XML attribute value.
+struct Escape<'a>(&'a str);
+
+impl<'a> fmt::Display for Escape<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let special: &[char] = &['&', '<', '>', '"', '\''];
+        let mut rest = self.0;
+        while let Some(i) = rest.find(special) {
+            f.write_str(&rest[..i])?;
+            f.write_str(match rest.as_bytes()[i] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'"' => "&quot;",
+                _    => "&apos;",
+            })?;
+            rest = &rest[i + 1..];
+        }
+        f.write_str(rest)
+    }
+}
+
+/// An arrow drawn from the center of one square to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Arrow {
+    /// The square the arrow starts from.
+    pub from: Square,
+    /// The square the arrow points to.
+    pub to: Square,
+    /// The SVG color of the arrow, such as `"#15781b"` or `"red"`.
+    ///
+    /// Like all colors of an [`Svg`](struct.Svg.html), it is escaped when
+    /// written.
+    pub color: String,
+}
+
+impl Arrow {
+    /// Creates an arrow from `from` to `to` with `color`.
+    #[inline]
+    pub fn new<C: Into<String>>(from: Square, to: Square, color: C) -> Arrow {
+        Arrow { from, to, color: color.into() }
+    }
+}
+
+impl<C: Into<String>> From<(Square, Square, C)> for Arrow {
+    #[inline]
+    fn from((from, to, color): (Square, Square, C)) -> Arrow {
+        Arrow::new(from, to, color)
+    }
+}
+
+/// How a board is rendered as SVG.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Svg {
+    /// The width of each square in pixels.
+    pub square_size: u32,
+    /// The side the board is viewed from, which is at the bottom.
+    pub side: Color,
+    /// Whether ranks are labeled on the left and files below.
+    pub coordinates: bool,
+    /// The squares to highlight.
+    pub highlight: BitBoard,
+    /// The color of highlighted squares.
+    pub highlight_color: String,
+    /// The source and destination squares of the last move, which are
+    /// marked.
+    pub last_move: Option<(Square, Square)>,
+    /// The color of the last move's squares.
+    pub last_move_color: String,
+    /// The arrows drawn over the pieces, in order.
+    pub arrows: Vec<Arrow>,
+    /// The color of light squares.
+    pub light: String,
+    /// The color of dark squares.
+    pub dark: String,
+}
+
+impl Default for Svg {
+    fn default() -> Svg {
+        Svg {
+            square_size: 45,
+            side: Color::White,
+            coordinates: false,
+            highlight: BitBoard::EMPTY,
+            highlight_color: "rgba(20, 85, 200, 0.5)".to_owned(),
+            last_move: None,
+            last_move_color: "rgba(155, 199, 0, 0.41)".to_owned(),
+            arrows: Vec::new(),
+            light: "#f0d9b5".to_owned(),
+            dark: "#b58863".to_owned(),
+        }
+    }
+}
+
+impl Svg {
+    /// Returns `pieces` rendered as an SVG image.
+    pub fn render(&self, pieces: &PieceMap) -> String {
+        let mut svg = String::new();
+        // Writing to a `String` cannot fail
+        let _ = self.write(&mut svg, pieces);
+        svg
+    }
+
+    /// Writes `pieces` rendered as an SVG image to `w`.
+    pub fn write<W: fmt::Write>(&self, w: &mut W, pieces: &PieceMap) -> fmt::Result {
+        let size = self.square_size;
+        let margin = self.margin();
+        let full = 8 * size + 2 * margin;
+
+        writeln!(w, "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                     width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">", full)?;
+
+        for square in Square::ALL {
+            let (x, y) = self.corner(square);
+            let light = (square.file() as u8 + square.rank() as u8) % 2 == 1;
+            let fill = if light { &self.light } else { &self.dark };
+            writeln!(w, "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>",
+                     x, y, size, Escape(fill))?;
+        }
+
+        if let Some((src, dst)) = self.last_move {
+            for &square in [src, dst].iter() {
+                self.write_fill(w, square, &self.last_move_color)?;
+            }
+        }
+        for square in self.highlight {
+            self.write_fill(w, square, &self.highlight_color)?;
+        }
+
+        for (square, &piece) in pieces {
+            let (x, y) = self.center(square);
+            // Both colors use the solid glyphs, filled with their own color
+            let glyph = Piece::new(piece.role(), Color::Black).figurine();
+            let (fill, stroke) = match piece.color() {
+                Color::White => ("#fff", "#000"),
+                Color::Black => ("#000", "#000"),
+            };
+            writeln!(w, "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                         dominant-baseline=\"central\" fill=\"{}\" stroke=\"{}\" \
+                         stroke-width=\"1\">{}</text>",
+                     x, y, size * 4 / 5, fill, stroke, glyph)?;
+        }
+
+        if self.coordinates {
+            for i in 0..8u8 {
+                let index = match self.side {
+                    Color::White => i,
+                    Color::Black => 7 - i,
+                };
+                let offset = margin + u32::from(i) * size + size / 2;
+                let font = margin * 3 / 4;
+                writeln!(w, "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                             dominant-baseline=\"central\">{}</text>",
+                         offset, full - margin / 2, font, (b'a' + index) as char)?;
+                writeln!(w, "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                             dominant-baseline=\"central\">{}</text>",
+                         margin / 2, offset, font, (b'8' - index) as char)?;
+            }
+        }
+
+        for arrow in self.arrows.iter() {
+            self.write_arrow(w, arrow)?;
+        }
+
+        write!(w, "</svg>")
+    }
+
+    #[inline]
+    fn margin(&self) -> u32 {
+        if self.coordinates { self.square_size / 2 } else { 0 }
+    }
+
+    /// Returns the top-left corner of `square` in pixels.
+    fn corner(&self, square: Square) -> (u32, u32) {
+        let (file, rank) = (square.file() as u32, square.rank() as u32);
+        let (col, row) = match self.side {
+            Color::White => (file, 7 - rank),
+            Color::Black => (7 - file, rank),
+        };
+        let margin = self.margin();
+        (margin + col * self.square_size, margin + row * self.square_size)
+    }
+
+    /// Returns the center of `square` in pixels.
+    fn center(&self, square: Square) -> (u32, u32) {
+        let (x, y) = self.corner(square);
+        (x + self.square_size / 2, y + self.square_size / 2)
+    }
+
+    fn write_fill<W: fmt::Write>(&self, w: &mut W, square: Square, color: &str) -> fmt::Result {
+        let (x, y) = self.corner(square);
+        writeln!(w, "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>",
+                 x, y, self.square_size, Escape(color))
+    }
+
+    fn write_arrow<W: fmt::Write>(&self, w: &mut W, arrow: &Arrow) -> fmt::Result {
+        let size = f64::from(self.square_size);
+        let (x1, y1) = self.center(arrow.from);
+        let (x2, y2) = self.center(arrow.to);
+        let (x1, y1, x2, y2) = (f64::from(x1), f64::from(y1), f64::from(x2), f64::from(y2));
+
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return Ok(());
+        }
+        // The unit vector along the arrow and its normal
+        let (ux, uy) = (dx / len, dy / len);
+        let (nx, ny) = (-uy, ux);
+
+        let head = size * 0.4;
+        let half = size * 0.2;
+        let (bx, by) = (x2 - ux * head, y2 - uy * head);
+
+        writeln!(w, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" \
+                     stroke-width=\"{:.1}\" stroke-linecap=\"butt\" opacity=\"0.8\"/>",
+                 x1, y1, bx, by, Escape(&arrow.color), size * 0.15)?;
+        writeln!(w, "<polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"{}\" \
+                     opacity=\"0.8\"/>",
+                 x2, y2, bx + nx * half, by + ny * half, bx - nx * half, by - ny * half,
+                 Escape(&arrow.color))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(haystack: &str, needle: &str) -> usize {
+        haystack.matches(needle).count()
+    }
+
+    #[test]
+    fn standard() {
+        let svg = Svg::default().render(&PieceMap::STANDARD);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"360\""));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(count(&svg, "<rect"), 64);
+        assert_eq!(count(&svg, "<text"), 32);
+        assert_eq!(count(&svg, "fill=\"#fff\""), 16);
+        assert_eq!(count(&svg, "♚"), 2);
+
+        // A8 is light and at the top left
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"45\" height=\"45\" fill=\"#f0d9b5\"/>"));
+        assert!(svg.contains("<rect x=\"0\" y=\"315\" width=\"45\" height=\"45\" fill=\"#b58863\"/>"));
+    }
+
+    #[test]
+    fn marks() {
+        let svg = Svg {
+            side: Color::Black,
+            coordinates: true,
+            highlight: Square::D4 | Square::E5,
+            last_move: Some((Square::E2, Square::E4)),
+            arrows: vec![(Square::E1, Square::E8, "red").into()],
+            ..Svg::default()
+        };
+        let image = svg.render(&PieceMap::STANDARD);
+        assert_eq!(count(&image, "<rect"), 64 + 2 + 2);
+        assert_eq!(count(&image, "<text"), 32 + 16);
+        assert_eq!(count(&image, "<polygon"), 1);
+        assert!(image.contains("width=\"404\""));
+
+        // From Black's side, H1 is at the top left
+        assert_eq!(svg.corner(Square::H1), (22, 22));
+        assert!(image.contains(">h</text>"));
+        assert!(image.contains("stroke=\"red\""));
+    }
+
+    #[test]
+    fn escape() {
+        let svg = Svg {
+            dark: "\"/><script>&'".to_owned(),
+            arrows: vec![(Square::E1, Square::E8, "red\" onload=\"x").into()],
+            ..Svg::default()
+        };
+        let image = svg.render(&PieceMap::STANDARD);
+        assert!(!image.contains("<script"));
+        assert!(image.contains("fill=\"&quot;/&gt;&lt;script&gt;&amp;&apos;\""));
+        assert!(image.contains("stroke=\"red&quot; onload=&quot;x\""));
+    }
+}